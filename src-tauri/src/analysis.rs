@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{compiler::ast_unknowns, error::{self, AppError}, parser::{simplify_tree, substitute_func, BinaryOperation, Node}};
+
+pub mod discontinuities;
+pub mod interval;
+pub mod points;
+pub mod roots;
+pub mod table;
+#[cfg(test)]
+mod tests;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Returns f(x) for an expression written as y=f(x), f(x)=y or just f(x), as it's drawn by the shader
+pub fn explicit_function(root: &Node) -> error::Result<Node> {
+    let not_explicit = || AppError::MathError("Only expressions written as y=f(x) can be analysed".to_owned());
+    let is_y = |node: &Node| matches!(node, Node::Unknown { name } if name == "y");
+
+    match root {
+        Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } => {
+            if is_y(lhs) && !ast_unknowns(rhs)?.1 {
+                Ok(*rhs.to_owned())
+            } else if is_y(rhs) && !ast_unknowns(lhs)?.1 {
+                Ok(*lhs.to_owned())
+            } else {
+                Err(not_explicit())
+            }
+        }
+        _ => {
+            if ast_unknowns(root)?.1 {
+                Err(not_explicit())
+            } else {
+                Ok(root.clone())
+            }
+        }
+    }
+}
+
+/// Evaluates an expression of one unknown in a point, it's None where it isn't defined
+pub fn evaluate_at(root: &Node, x: f64, variable_map: &HashMap<String, f64>) -> Option<f64> {
+    let mut root = root.clone();
+    substitute_func(&mut root, &Node::Constant { value: x }).ok()?;
+
+    simplify_tree(&mut root, variable_map);
+    simplify_tree(&mut root, variable_map).filter(|v| v.is_finite())
+}
+
+/// The ranges of the analysis must be finite and not empty
+pub(crate) fn check_range(from: f64, to: f64) -> error::Result<()> {
+    if from.is_finite() && to.is_finite() && from < to {
+        Ok(())
+    } else {
+        Err(AppError::MathError(format!("[{from}, {to}] isn't a valid range")))
+    }
+}
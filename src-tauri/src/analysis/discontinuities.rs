@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{compiler::ast_unknowns, parser::{Approach, BinaryOperation, Limit, Node, Side, UnaryOperation}};
+
+use super::{evaluate_at, roots::{dedup_roots, find_roots, SAMPLES}};
+
+const BISECTIONS: usize = 64;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscontinuityKind {
+    /// The function diverges to infinity in at least one side, like 1/x in 0
+    Asymptote,
+    /// The limits from both sides are different, like floor(x) in the integers
+    Jump,
+    /// The limit exists but the function isn't defined there or has another value, like sin(x)/x in 0
+    Removable,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Discontinuity {
+    pub x: f64,
+    pub kind: DiscontinuityKind,
+}
+
+/// Finds the discontinuities of y=f(x) in [from, to]. The zeros of the denominators and of the arguments of logarithms,
+/// the poles of tan and the steps of floor and ceil are the candidates, which are classified by their limits from both sides
+pub fn discontinuities(f: &Node, from: f64, to: f64, variable_map: &HashMap<String, f64>) -> Vec<Discontinuity> {
+    let mut candidates = Vec::new();
+    collect_candidates(f, from, to, variable_map, &mut candidates);
+
+    dedup_roots(candidates, (to - from) * 1e-9)
+        .into_iter()
+        .filter_map(|x| classify(f, x, variable_map).map(|kind| Discontinuity { x, kind }))
+        .collect()
+}
+
+fn collect_candidates(root: &Node, from: f64, to: f64, variable_map: &HashMap<String, f64>, candidates: &mut Vec<f64>) {
+    let eval = |node: &Node, x: f64| evaluate_at(node, x, variable_map);
+
+    match root {
+        Node::Unary { op_type, child: Some(child) } => {
+            match op_type {
+                UnaryOperation::Tan => candidates.extend(find_roots(|x| eval(child, x).map(f64::cos), from, to)),
+                UnaryOperation::Ln | UnaryOperation::Log => candidates.extend(find_roots(|x| eval(child, x), from, to)),
+                UnaryOperation::Floor => candidates.extend(steps(|x| eval(child, x).map(f64::floor), from, to)),
+                UnaryOperation::Ceil => candidates.extend(steps(|x| eval(child, x).map(f64::ceil), from, to)),
+                _ => (),
+            }
+
+            collect_candidates(child, from, to, variable_map, candidates);
+        }
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => {
+            match op_type {
+                BinaryOperation::Division => candidates.extend(find_roots(|x| eval(rhs, x), from, to)),
+                //The negative powers are denominators too
+                BinaryOperation::Power if matches!(ast_unknowns(rhs), Ok((false, false))) && eval(rhs, 0.0).is_some_and(|e| e < 0.0) => {
+                    candidates.extend(find_roots(|x| eval(lhs, x), from, to));
+                }
+                _ => (),
+            }
+
+            collect_candidates(lhs, from, to, variable_map, candidates);
+            collect_candidates(rhs, from, to, variable_map, candidates);
+        }
+        Node::NAry { children, .. } => children.iter().for_each(|e| collect_candidates(e, from, to, variable_map, candidates)),
+        _ => (),
+    }
+}
+
+/// The points where a function of integer values changes, they are bisected between the samples where it changes
+fn steps<F: Fn(f64) -> Option<f64>>(g: F, from: f64, to: f64) -> Vec<f64> {
+    let step = (to - from) / SAMPLES as f64;
+
+    let mut points = Vec::new();
+    for i in 0..SAMPLES {
+        let (mut a, mut b) = (from + step * i as f64, from + step * (i + 1) as f64);
+        let (Some(ga), Some(gb)) = (g(a), g(b)) else { continue };
+        if ga == gb {
+            continue;
+        }
+
+        for _ in 0..BISECTIONS {
+            let m = (a + b) / 2.0;
+            if g(m) == Some(ga) { a = m; } else { b = m; }
+        }
+        points.push((a + b) / 2.0);
+    }
+
+    points
+}
+
+/// The kind of discontinuity in x, it's None if the function is continuous there or it can't be classified
+fn classify(f: &Node, x: f64, variable_map: &HashMap<String, f64>) -> Option<DiscontinuityKind> {
+    let limit = Limit { body: f.clone(), target: x, side: Side::Both };
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-6 * a.abs().max(1.0);
+
+    match limit.approaches(variable_map)[..] {
+        [Approach::Diverges(_), _] | [_, Approach::Diverges(_)] => Some(DiscontinuityKind::Asymptote),
+        [Approach::Converges(a), Approach::Converges(b)] if !close(a, b) => Some(DiscontinuityKind::Jump),
+        [Approach::Converges(a), Approach::Converges(_)] => match evaluate_at(f, x, variable_map) {
+            Some(value) if close(a, value) => None,
+            _ => Some(DiscontinuityKind::Removable),
+        },
+        _ => None,
+    }
+}
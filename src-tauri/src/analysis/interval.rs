@@ -0,0 +1,333 @@
+use std::{collections::HashMap, f64::consts::{FRAC_PI_2, PI}};
+
+use serde::Serialize;
+
+use crate::{compiler::{ast_unknowns, bytecode::Instruction, domain::Requirement, verifier::MAX_LOCALS}, error::{self, AppError}, parser::{BinaryOperation, NAryOperation, Node, UnaryOperation}};
+
+/// A closed set of real numbers that contains every value an expression can take, the bounds are rounded outwards.
+/// It's empty where the expression isn't defined anywhere
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub const EMPTY: Self = Self { lo: f64::INFINITY, hi: f64::NEG_INFINITY };
+    pub const ENTIRE: Self = Self { lo: f64::NEG_INFINITY, hi: f64::INFINITY };
+
+    pub fn new(a: f64, b: f64) -> Self {
+        if a.is_nan() || b.is_nan() {
+            Self::EMPTY
+        } else {
+            Self { lo: a.min(b), hi: a.max(b) }
+        }
+    }
+
+    pub fn point(x: f64) -> Self {
+        Self::new(x, x)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lo > self.hi
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        if self.is_empty() { 0.0 } else { self.hi - self.lo }
+    }
+
+    /// The smallest interval with both
+    pub fn hull(&self, other: &Self) -> Self {
+        match (self.is_empty(), other.is_empty()) {
+            (true, _) => *other,
+            (_, true) => *self,
+            _ => Self { lo: self.lo.min(other.lo), hi: self.hi.max(other.hi) },
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (lo, hi) = (self.lo.max(other.lo), self.hi.min(other.hi));
+        if lo > hi { Self::EMPTY } else { Self { lo, hi } }
+    }
+
+    /// Widens the bounds by one ulp, as the operations of the bounds are rounded to the nearest
+    fn outward(self) -> Self {
+        if self.is_empty() { self } else { Self { lo: self.lo.next_down(), hi: self.hi.next_up() } }
+    }
+
+    /// Applies a non decreasing function to the bounds
+    fn increasing(self, f: fn(f64) -> f64) -> Self {
+        if self.is_empty() { self } else { Self::new(f(self.lo), f(self.hi)) }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return Self::EMPTY;
+        }
+        Self::new(self.lo + other.lo, self.hi + other.hi).outward()
+    }
+
+    pub fn neg(&self) -> Self {
+        if self.is_empty() { *self } else { Self { lo: -self.hi, hi: -self.lo } }
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return Self::EMPTY;
+        }
+
+        //0·∞ is 0 here, as the infinities are only bounds
+        let product = |a: f64, b: f64| if a == 0.0 || b == 0.0 { 0.0 } else { a * b };
+        let products = [product(self.lo, other.lo), product(self.lo, other.hi), product(self.hi, other.lo), product(self.hi, other.hi)];
+        let lo = products.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = products.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self::new(lo, hi).outward()
+    }
+
+    pub fn div(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() || (other.lo == 0.0 && other.hi == 0.0) {
+            Self::EMPTY
+        } else if other.lo < 0.0 && other.hi > 0.0 {
+            Self::ENTIRE
+        } else if other.lo == 0.0 {
+            self.mul(&Self::new(1.0 / other.hi, f64::INFINITY))
+        } else if other.hi == 0.0 {
+            self.mul(&Self::new(f64::NEG_INFINITY, 1.0 / other.lo))
+        } else {
+            self.mul(&Self::new(1.0 / other.hi, 1.0 / other.lo).outward())
+        }
+    }
+
+    pub fn powi(&self, n: i32) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        match n {
+            0 => Self::point(1.0),
+            n if n < 0 => Self::point(1.0).div(&self.powi(-n)),
+            n if n % 2 == 1 => Self::new(self.lo.powi(n), self.hi.powi(n)).outward(),
+            n if self.contains(0.0) => Self::new(0.0, self.lo.powi(n).max(self.hi.powi(n))).outward(),
+            n => Self::new(self.lo.powi(n), self.hi.powi(n)).outward(),
+        }
+    }
+
+    /// The real power is only defined for positive bases, or 0 with positive exponents
+    pub fn pow(&self, exponent: &Self) -> Self {
+        if exponent.lo == exponent.hi && exponent.lo.fract() == 0.0 && exponent.lo.abs() < i32::MAX as f64 {
+            return self.powi(exponent.lo as i32);
+        }
+
+        let positive = self.intersection(&Self::new(0.0, f64::INFINITY));
+        let power = exponent.mul(&positive.ln()).exp();
+        if positive.contains(0.0) && exponent.hi > 0.0 { power.hull(&Self::point(0.0)) } else { power }
+    }
+
+    pub fn exp(&self) -> Self {
+        self.increasing(f64::exp).outward()
+    }
+
+    pub fn ln(&self) -> Self {
+        self.intersection(&Self::new(0.0, f64::INFINITY)).increasing(f64::ln).outward()
+    }
+
+    pub fn sqrt(&self) -> Self {
+        self.intersection(&Self::new(0.0, f64::INFINITY)).increasing(f64::sqrt).outward()
+            .intersection(&Self::new(0.0, f64::INFINITY))
+    }
+
+    pub fn abs(&self) -> Self {
+        if self.is_empty() {
+            *self
+        } else if self.contains(0.0) {
+            Self::new(0.0, self.lo.abs().max(self.hi.abs()))
+        } else {
+            Self::new(self.lo.abs(), self.hi.abs())
+        }
+    }
+
+    /// The cosine reaches 1 in the multiples of 2π and -1 in the odd multiples of π, otherwise it's the cosine of the bounds
+    pub fn cos(&self) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.lo.is_finite() || !self.hi.is_finite() || self.width() >= 2.0 * PI {
+            return Self::new(-1.0, 1.0);
+        }
+
+        let (first, last) = ((self.lo / PI).ceil(), (self.hi / PI).floor());
+        let (a, b) = (self.lo.cos(), self.hi.cos());
+        let mut cos = Self::new(a, b).outward();
+        if first <= last {
+            let even = first % 2.0 == 0.0 || first < last;
+            let odd = first % 2.0 != 0.0 || first < last;
+            if even { cos.hi = 1.0; }
+            if odd { cos.lo = -1.0; }
+        }
+
+        cos.intersection(&Self::new(-1.0, 1.0))
+    }
+
+    pub fn sin(&self) -> Self {
+        self.add(&Self::point(-FRAC_PI_2)).cos()
+    }
+
+    /// The tangent is increasing between its poles, which are in π/2 plus the multiples of π
+    pub fn tan(&self) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.lo.is_finite() || !self.hi.is_finite() || self.width() >= PI {
+            return Self::ENTIRE;
+        }
+
+        let (first, last) = ((self.lo / PI - 0.5).ceil(), (self.hi / PI - 0.5).floor());
+        if first <= last {
+            Self::ENTIRE
+        } else {
+            self.increasing(f64::tan).outward()
+        }
+    }
+
+    pub fn floor(&self) -> Self {
+        self.increasing(f64::floor)
+    }
+
+    pub fn ceil(&self) -> Self {
+        self.increasing(f64::ceil)
+    }
+
+    pub fn unary(&self, op: &UnaryOperation) -> Self {
+        match op {
+            UnaryOperation::Minus => self.neg(),
+            UnaryOperation::Sin => self.sin(),
+            UnaryOperation::Cos => self.cos(),
+            UnaryOperation::Tan => self.tan(),
+            UnaryOperation::Floor => self.floor(),
+            UnaryOperation::Ceil => self.ceil(),
+            UnaryOperation::Abs => self.abs(),
+            UnaryOperation::Ln => self.ln(),
+            UnaryOperation::Log => self.ln().div(&Self::point(std::f64::consts::LN_10)),
+            UnaryOperation::Sqrt => self.sqrt(),
+            //The factorial of the integer part doesn't decrease for the natural numbers
+            UnaryOperation::Fact => self.intersection(&Self::new(0.0, f64::INFINITY)).increasing(UnaryOperation::factorial).outward(),
+            UnaryOperation::Erf => self.increasing(UnaryOperation::erf).outward().intersection(&Self::new(-1.0, 1.0)),
+            UnaryOperation::Erfc => self.neg().increasing(|x| UnaryOperation::erfc(-x)).outward().intersection(&Self::new(0.0, 2.0)),
+            UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median => *self,
+            UnaryOperation::Stdev | UnaryOperation::Var => Self::EMPTY,
+        }
+    }
+}
+
+/// Bounds the values of the expression in the box of the plane, the equations are lhs-rhs as the shader draws them
+pub fn evaluate_interval(root: &Node, x: Interval, y: Interval, variable_map: &HashMap<String, f64>) -> error::Result<Interval> {
+    let eval = |node: &Option<Box<Node>>| -> error::Result<Interval> {
+        evaluate_interval(node.as_ref().ok_or(AppError::EmptyError)?, x, y, variable_map)
+    };
+
+    match root {
+        Node::Constant { value } => Ok(Interval::point(*value)),
+        Node::Variable { name } => variable_map.get(name)
+            .map(|v| Interval::point(*v))
+            .ok_or_else(|| AppError::IoError(format!("There are no variable called {name}"))),
+        Node::Unknown { name } => match name.as_str() {
+            "x" => Ok(x),
+            "y" => Ok(y),
+            _ => Err(AppError::MathError(format!("There aren't any unknowns called: {name}"))),
+        },
+        Node::Unary { op_type, child } => Ok(eval(child)?.unary(op_type)),
+        Node::Binary { op_type, lhs, rhs } => {
+            let (a, b) = (eval(lhs)?, eval(rhs)?);
+            Ok(match op_type {
+                BinaryOperation::Division => a.div(&b),
+                BinaryOperation::Power => a.pow(&b),
+                BinaryOperation::Equal => a.add(&b.neg()),
+            })
+        }
+        Node::NAry { op_type, children } => {
+            let mut values = children.iter().map(|e| evaluate_interval(e, x, y, variable_map));
+            let first = values.next().ok_or(AppError::EmptyError)??;
+            values.try_fold(first, |acc, value| Ok(match op_type {
+                NAryOperation::Add => acc.add(&value?),
+                NAryOperation::Multiply => acc.mul(&value?),
+            }))
+        }
+        Node::Matrix { .. } => Err(AppError::MathError("A matrix can't be drawn".to_owned())),
+    }
+}
+
+/// Bounds every expression of a bytecode program in the box, it runs like the shader does
+pub fn evaluate_bytecode_interval(program: &[Instruction], x: Interval, y: Interval) -> error::Result<Vec<Interval>> {
+    let underflow = || AppError::MathError("The bytecode pops from an empty stack".to_owned());
+
+    let mut stack: Vec<Interval> = Vec::new();
+    let mut locals = [Interval::EMPTY; MAX_LOCALS];
+    let mut stored = Interval::EMPTY;
+    let mut outputs = Vec::new();
+    for instruction in program {
+        let binary = |stack: &mut Vec<Interval>, op: fn(&Interval, &Interval) -> Interval| -> error::Result<()> {
+            let b = stack.pop().ok_or_else(underflow)?;
+            let a = stack.pop().ok_or_else(underflow)?;
+            stack.push(op(&a, &b));
+            Ok(())
+        };
+
+        match instruction {
+            Instruction::Push(v) => stack.push(Interval::point(*v)),
+            Instruction::PushX => stack.push(x),
+            Instruction::PushY => stack.push(y),
+            Instruction::Cpy => stack.push(*stack.last().ok_or_else(underflow)?),
+            Instruction::Pop => { stack.pop().ok_or_else(underflow)?; }
+            Instruction::Store => stored = *stack.last().ok_or_else(underflow)?,
+            Instruction::Load(local) => stack.push(*locals.get(*local).ok_or_else(|| AppError::MathError(format!("There isn't a local {local}")))?),
+            Instruction::Save(local) => {
+                let value = *stack.last().ok_or_else(underflow)?;
+                *locals.get_mut(*local).ok_or_else(|| AppError::MathError(format!("There isn't a local {local}")))? = value;
+            }
+            Instruction::Require(requirement) => {
+                //If the condition can't hold anywhere in the box the expression isn't defined in it
+                let value = stack.pop().ok_or_else(underflow)?;
+                let possible = match requirement {
+                    Requirement::Positive => value.hi > 0.0,
+                    Requirement::NonNegative => value.hi >= 0.0,
+                    Requirement::NonZero => value.lo != 0.0 || value.hi != 0.0,
+                };
+                if !possible {
+                    stored = Interval::EMPTY;
+                }
+            }
+            Instruction::Ret => {
+                outputs.push(stored);
+                stored = Interval::EMPTY;
+                stack.clear();
+            }
+            Instruction::Add => binary(&mut stack, Interval::add)?,
+            Instruction::Sub => binary(&mut stack, |a, b| a.add(&b.neg()))?,
+            Instruction::Mul => binary(&mut stack, Interval::mul)?,
+            Instruction::Div => binary(&mut stack, Interval::div)?,
+            Instruction::Pow => binary(&mut stack, Interval::pow)?,
+            Instruction::UnaryOperation(op) => {
+                let a = stack.pop().ok_or_else(underflow)?;
+                stack.push(a.unary(op));
+            }
+        }
+    }
+
+    Ok(outputs)
+}
+
+/// The interval of the expression as it's drawn, f(x)-y or f(y)-x if it isn't an equation.
+/// The curve surely doesn't pass through the box if it doesn't contain 0
+pub fn drawn_interval(root: &Node, x: Interval, y: Interval, variable_map: &HashMap<String, f64>) -> error::Result<Interval> {
+    let value = evaluate_interval(root, x, y, variable_map)?;
+
+    match root {
+        Node::Binary { op_type: BinaryOperation::Equal, .. } => Ok(value),
+        _ if ast_unknowns(root)?.0 => Ok(value.add(&y.neg())),
+        _ => Ok(value.add(&x.neg())),
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{error, parser::{derive_function, simplify_tree, Node}};
+
+use super::{evaluate_at, roots::find_roots};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PointKind {
+    XIntercept, YIntercept, Minimum, Maximum, Inflection
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct PointOfInterest {
+    pub x: f64,
+    pub y: f64,
+    pub kind: PointKind,
+}
+
+/// Finds the intercepts, the extrema and the inflection points of y=f(x) in [from, to]
+pub fn points_of_interest(f: &Node, from: f64, to: f64, variable_map: &HashMap<String, f64>) -> error::Result<Vec<PointOfInterest>> {
+    let eval = |node: &Node, x: f64| evaluate_at(node, x, variable_map);
+    let h = (to - from) * 1e-6;
+
+    let mut points: Vec<PointOfInterest> = find_roots(|x| eval(f, x), from, to)
+        .into_iter()
+        .map(|x| PointOfInterest { x, y: 0.0, kind: PointKind::XIntercept })
+        .collect();
+
+    if from <= 0.0 && 0.0 <= to {
+        if let Some(y) = eval(f, 0.0) {
+            points.push(PointOfInterest { x: 0.0, y, kind: PointKind::YIntercept });
+        }
+    }
+
+    //The functions like abs or floor don't have derivatives, but their intercepts are still useful
+    let derivatives = derive(f).and_then(|d1| Ok((derive(&d1)?, d1)));
+    match derivatives {
+        Ok((d2, d1)) => {
+            for x in find_roots(|x| eval(&d1, x), from, to) {
+                let Some(y) = eval(f, x) else { continue };
+
+                let kind = match eval(&d2, x) {
+                    Some(c) if c > 1e-9 => Some(PointKind::Minimum),
+                    Some(c) if c < -1e-9 => Some(PointKind::Maximum),
+                    _ => {
+                        //If the second derivative is zero, the neighbours are compared
+                        match (eval(f, x - h), eval(f, x + h)) {
+                            (Some(a), Some(b)) if a > y && b > y => Some(PointKind::Minimum),
+                            (Some(a), Some(b)) if a < y && b < y => Some(PointKind::Maximum),
+                            _ => None,
+                        }
+                    }
+                };
+
+                if let Some(kind) = kind {
+                    points.push(PointOfInterest { x, y, kind });
+                }
+            }
+
+            for x in find_roots(|x| eval(&d2, x), from, to) {
+                let changes_concavity = match (eval(&d2, x - h), eval(&d2, x + h)) {
+                    (Some(a), Some(b)) => a.signum() != b.signum(),
+                    _ => false,
+                };
+
+                if let (true, Some(y)) = (changes_concavity, eval(f, x)) {
+                    points.push(PointOfInterest { x, y, kind: PointKind::Inflection });
+                }
+            }
+        }
+        Err(e) => warn!("The extrema couldn't be found: {e}"),
+    }
+
+    points.sort_by(|a, b| a.x.total_cmp(&b.x));
+    Ok(points)
+}
+
+fn derive(f: &Node) -> error::Result<Node> {
+    let mut d = derive_function(f)?;
+    simplify_tree(&mut d, &HashMap::new());
+    Ok(*d)
+}
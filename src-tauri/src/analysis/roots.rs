@@ -0,0 +1,139 @@
+/// Number of intervals the range is split into to bracket the roots
+pub const SAMPLES: usize = 1000;
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+/// Finds the real roots of f in [from, to]: the sign changes are refined with Brent's method,
+/// and the roots that touch the axis without crossing it are found by minimizing |f|
+pub fn find_roots<F: Fn(f64) -> Option<f64>>(f: F, from: f64, to: f64) -> Vec<f64> {
+    let step = (to - from) / SAMPLES as f64;
+    let samples: Vec<(f64, Option<f64>)> = (0..=SAMPLES)
+        .map(|i| from + step * i as f64)
+        .map(|x| (x, f(x)))
+        .collect();
+
+    let mut roots = Vec::new();
+    for (i, window) in samples.windows(2).enumerate() {
+        let ((a, fa), (b, fb)) = (window[0], window[1]);
+        let (Some(fa), Some(fb)) = (fa, fb) else { continue };
+
+        if fa == 0.0 {
+            roots.push(a);
+        } else if fa.signum() != fb.signum() && fb != 0.0 {
+            //A pole like 1/x also changes sign, so the root must really be close to zero
+            if let Some(r) = brent(&f, a, b) {
+                if f(r).is_some_and(|fr| fr.abs() <= 1e-6 * (1.0 + fa.abs().min(fb.abs()))) {
+                    roots.push(r);
+                }
+            }
+        } else if let Some((c, Some(fc))) = samples.get(i + 2).copied() {
+            if fb.abs() <= fa.abs() && fb.abs() <= fc.abs() && fb.signum() == fa.signum() && fb.signum() == fc.signum() {
+                let m = minimize_abs(&f, a, c);
+                if f(m).is_some_and(|fm| fm.abs() < 1e-10) {
+                    roots.push(m);
+                }
+            }
+        }
+    }
+
+    if let Some((x, Some(fx))) = samples.last() {
+        if *fx == 0.0 {
+            roots.push(*x);
+        }
+    }
+
+    dedup_roots(roots, (to - from) * 1e-9)
+}
+
+/// Brent's method, f(a) and f(b) must have different signs
+pub fn brent<F: Fn(f64) -> Option<f64>>(f: &F, mut a: f64, mut b: f64) -> Option<f64> {
+    let mut fa = f(a)?;
+    let mut fb = f(b)?;
+    if fa.signum() == fb.signum() {
+        return None;
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let (mut d, mut e) = (b - a, b - a);
+
+    for _ in 0..MAX_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+
+        let tol = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let m = 0.5 * (c - b);
+        if m.abs() <= tol || fb == 0.0 {
+            return Some(b);
+        }
+
+        if e.abs() >= tol && fa.abs() > fb.abs() {
+            //Inverse quadratic interpolation, or the secant method if there are only two points
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * m * s, 1.0 - s)
+            } else {
+                let q = fa / fc;
+                let r = fb / fc;
+                (s * (2.0 * m * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+
+            if p > 0.0 { q = -q; } else { p = -p; }
+
+            if 2.0 * p < f64::min(3.0 * m * q - (tol * q).abs(), (e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = m;
+                e = m;
+            }
+        } else {
+            d = m;
+            e = m;
+        }
+
+        (a, fa) = (b, fb);
+        b += if d.abs() > tol { d } else { tol.copysign(m) };
+        fb = f(b)?;
+    }
+
+    Some(b)
+}
+
+/// Golden section search of the minimum of |f| in [a, b]
+fn minimize_abs<F: Fn(f64) -> Option<f64>>(f: &F, mut a: f64, mut b: f64) -> f64 {
+    let inv_phi = (5f64.sqrt() - 1.0) / 2.0;
+    let g = |x: f64| f(x).map_or(f64::INFINITY, f64::abs);
+
+    let mut c = b - inv_phi * (b - a);
+    let mut d = a + inv_phi * (b - a);
+    for _ in 0..MAX_ITERATIONS {
+        if (b - a).abs() <= TOLERANCE {
+            break;
+        }
+
+        if g(c) < g(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+        c = b - inv_phi * (b - a);
+        d = a + inv_phi * (b - a);
+    }
+
+    (a + b) / 2.0
+}
+
+/// Sorts the roots and merges the ones closer than the tolerance
+pub fn dedup_roots(mut roots: Vec<f64>, tolerance: f64) -> Vec<f64> {
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|b, a| (*b - *a).abs() <= tolerance);
+    roots
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{error::{self, AppError}, parser::Node};
+
+use super::evaluate_at;
+
+/// The frontend can't show more rows than this anyway
+pub const MAX_ROWS: usize = 10_000;
+
+/// A row of a table of values, y is None where f(x) isn't defined
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TableRow {
+    pub x: f64,
+    pub y: Option<f64>,
+}
+
+/// Evaluates y=f(x) from `from` to `to`, both included, every `step`
+pub fn table_of_values(f: &Node, from: f64, to: f64, step: f64, variable_map: &HashMap<String, f64>) -> error::Result<Vec<TableRow>> {
+    if !step.is_finite() || step <= 0.0 {
+        return Err(AppError::MathError(format!("The step of a table must be positive, not {step}")));
+    }
+
+    //The last row is included even if the step doesn't divide the range exactly because of rounding
+    let intervals = ((to - from) / step + 1e-9).floor();
+    if intervals >= MAX_ROWS as f64 {
+        return Err(AppError::MathError(format!("A table can't have more than {MAX_ROWS} rows")));
+    }
+
+    //Every x is computed from the start so the errors don't add up
+    Ok((0..=intervals as usize)
+        .map(|i| from + i as f64 * step)
+        .map(|x| TableRow { x, y: evaluate_at(f, x, variable_map) })
+        .collect())
+}
+
+/// Reads the columns of a CSV file of numbers, the first line is skipped if it's a header
+pub fn from_csv(content: &str) -> error::Result<Vec<Vec<f64>>> {
+    let mut lines = content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line.split(',').map(str::trim).collect::<Vec<_>>()))
+        .peekable();
+
+    let header = lines.peek().is_some_and(|(_, cells)| cells.iter().any(|e| e.parse::<f64>().is_err()));
+    if header {
+        lines.next();
+    }
+
+    let mut columns: Vec<Vec<f64>> = Vec::new();
+    for (i, cells) in lines {
+        if columns.is_empty() {
+            columns = vec![Vec::new(); cells.len()];
+        } else if cells.len() != columns.len() {
+            return Err(AppError::ParseError(format!("The line {i} has {} values instead of {}", cells.len(), columns.len())));
+        }
+
+        for (column, cell) in columns.iter_mut().zip(cells) {
+            column.push(cell.parse().map_err(|_| AppError::ParseError(format!("{cell} in the line {i} isn't a number")))?);
+        }
+    }
+
+    if columns.is_empty() {
+        return Err(AppError::ParseError("The file doesn't have any values".to_owned()));
+    }
+
+    Ok(columns)
+}
+
+/// The table as comma separated values, with a header and "undefined" where there isn't a value
+pub fn to_csv(rows: &[TableRow]) -> String {
+    let mut csv = String::from("x,y\n");
+    for row in rows {
+        match row.y {
+            Some(y) => csv += &format!("{},{}\n", row.x, y),
+            None => csv += &format!("{},undefined\n", row.x),
+        }
+    }
+
+    csv
+}
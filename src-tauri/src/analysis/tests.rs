@@ -0,0 +1,227 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use crate::{compiler::bytecode::compile_to_bytecode, error, find_intersections, parser::{parse_latex, simplify_tree, Node}, CompilerState, ExpressionRef};
+
+use super::{discontinuities::{discontinuities, Discontinuity, DiscontinuityKind}, evaluate_at, explicit_function, interval::{drawn_interval, evaluate_bytecode_interval, evaluate_interval, Interval}, roots::find_roots, table};
+
+fn roots_of(latex: &str, from: f64, to: f64) -> error::Result<Vec<f64>> {
+    let f = explicit_function(&parse_latex(latex, &HashMap::new())?)?;
+    Ok(find_roots(|x| evaluate_at(&f, x, &HashMap::new()), from, to))
+}
+
+fn assert_roots(found: &[f64], expected: &[f64]) {
+    assert_eq!(found.len(), expected.len(), "found {found:?}, expected {expected:?}");
+    for (a, b) in found.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-8, "found {found:?}, expected {expected:?}");
+    }
+}
+
+#[test]
+fn roots() -> error::Result<()> {
+    assert_roots(&roots_of("y=x^{2}-2", -3.0, 3.0)?, &[-f64::sqrt(2.0), f64::sqrt(2.0)]);
+    assert_roots(&roots_of("\\sin\\left(x\\right)", -0.5, 10.0)?, &[0.0, PI, 2.0*PI, 3.0*PI]);
+    assert_roots(&roots_of("x^{3}-3x+2=y", -4.0, 4.0)?, &[-2.0, 1.0]);
+
+    Ok(())
+}
+
+#[test]
+fn intersections() -> error::Result<()> {
+    let mut state = CompilerState::default();
+    crate::process("y=x^{2}", &mut state, 0)?;
+    crate::process("y=x+2", &mut state, 1)?;
+
+    let points = find_intersections(&ExpressionRef::Index(0), &ExpressionRef::Index(1), -5.0, 5.0, &state)?;
+    assert_roots(&points.iter().map(|p| p.x).collect::<Vec<_>>(), &[-1.0, 2.0]);
+    assert_roots(&points.iter().map(|p| p.y).collect::<Vec<_>>(), &[1.0, 4.0]);
+    assert!(find_intersections(&ExpressionRef::Index(0), &ExpressionRef::Index(2), -5.0, 5.0, &state).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn poles_arent_roots() -> error::Result<()> {
+    assert_roots(&roots_of("\\frac{1}{x}", -1.0, 1.3)?, &[]);
+    assert_roots(&roots_of("\\tan\\left(x\\right)", 1.0, 4.0)?, &[PI]);
+
+    Ok(())
+}
+
+#[test]
+fn implicit_curves_arent_explicit() -> error::Result<()> {
+    let root = parse_latex("x^{2}+y^{2}=1", &HashMap::new())?;
+    assert!(explicit_function(&root).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn points_of_interest() -> error::Result<()> {
+    use super::points::{points_of_interest, PointKind};
+
+    let f = parse_latex("x^{3}-3x", &HashMap::new())?;
+    let points = points_of_interest(&f, -3.0, 3.0, &HashMap::new())?;
+    let kinds: Vec<PointKind> = points.iter().map(|p| p.kind).collect();
+
+    assert_eq!(kinds, [
+        PointKind::XIntercept, PointKind::Maximum, 
+        PointKind::XIntercept, PointKind::YIntercept, PointKind::Inflection, 
+        PointKind::Minimum, PointKind::XIntercept,
+    ]);
+    assert!((points[1].x + 1.0).abs() < 1e-8 && (points[1].y - 2.0).abs() < 1e-8);
+    assert!((points[5].x - 1.0).abs() < 1e-8 && (points[5].y + 2.0).abs() < 1e-8);
+
+    Ok(())
+}
+
+#[test]
+fn table_of_values() -> error::Result<()> {
+    let f = explicit_function(&parse_latex("y=\\frac{1}{x}", &HashMap::new())?)?;
+    let rows = table::table_of_values(&f, -1.0, 1.0, 0.5, &HashMap::new())?;
+
+    let xs: Vec<f64> = rows.iter().map(|r| r.x).collect();
+    assert_eq!(xs, [-1.0, -0.5, 0.0, 0.5, 1.0]);
+    assert_eq!(rows[1].y, Some(-2.0));
+    assert_eq!(rows[2].y, None);
+
+    //0.1 doesn't divide the range exactly in binary, but the end is still in the table
+    assert_eq!(table::table_of_values(&f, 0.0, 1.0, 0.1, &HashMap::new())?.len(), 11);
+
+    assert!(table::table_of_values(&f, 0.0, 1.0, 0.0, &HashMap::new()).is_err());
+    assert!(table::table_of_values(&f, 0.0, 1.0, 1e-6, &HashMap::new()).is_err());
+
+    assert_eq!(table::to_csv(&rows[1..3]), "x,y\n-0.5,-2\n0,undefined\n");
+
+    Ok(())
+}
+
+#[test]
+fn csv_import() -> error::Result<()> {
+    assert_eq!(table::from_csv("time, distance\n0, 1.5\n\n1,3\n2,-4.5\n")?, [vec![0.0, 1.0, 2.0], vec![1.5, 3.0, -4.5]]);
+    assert_eq!(table::from_csv("1,2,3")?, [vec![1.0], vec![2.0], vec![3.0]]);
+
+    assert!(table::from_csv("x,y\n1,2\n3").is_err());
+    assert!(table::from_csv("x,y\n1,2\n3,a").is_err());
+    assert!(table::from_csv("x,y\n").is_err());
+
+    Ok(())
+}
+
+/// Checks that the bounds in the box contain the values of the expression in a grid of points inside it
+fn assert_bounds(latex: &str, x: Interval, y: Interval) -> error::Result<Interval> {
+    let root = parse_latex(latex, &HashMap::new())?;
+    let bounds = evaluate_interval(&root, x, y, &HashMap::new())?;
+
+    for i in 0..=50 {
+        for j in 0..=10 {
+            let (px, py) = (x.lo + x.width() * i as f64 / 50.0, y.lo + y.width() * j as f64 / 10.0);
+            let mut point = root.clone();
+            substitute_unknowns(&mut point, px, py);
+            if let Some(value) = simplify_tree(&mut point, &HashMap::new()).filter(|v| v.is_finite()) {
+                assert!(bounds.contains(value), "{latex}: {value} in ({px}, {py}) isn't in {bounds:?}");
+            }
+        }
+    }
+
+    Ok(bounds)
+}
+
+fn substitute_unknowns(root: &mut Node, x: f64, y: f64) {
+    match root {
+        Node::Unknown { name } => *root = Node::Constant { value: if name == "x" { x } else { y } },
+        Node::Unary { child: Some(child), .. } => substitute_unknowns(child, x, y),
+        Node::Binary { lhs: Some(lhs), rhs: Some(rhs), .. } => {
+            substitute_unknowns(lhs, x, y);
+            substitute_unknowns(rhs, x, y);
+        }
+        Node::NAry { children, .. } => children.iter_mut().for_each(|e| substitute_unknowns(e, x, y)),
+        _ => (),
+    }
+}
+
+#[test]
+fn interval_arithmetic() -> error::Result<()> {
+    let (x, y) = (Interval::new(-2.5, 3.7), Interval::new(0.5, 2.0));
+    let expressions = [
+        "x^{2}-3xy+1", "\\frac{x}{y}", "\\sqrt{x}", "\\ln\\left(x\\right)", "\\sin\\left(x\\right)\\cos\\left(y\\right)",
+        "\\tan\\left(y\\right)", "\\operatorname{floor}\\left(x\\right)", "\\operatorname{ceil}\\left(xy\\right)", "\\operatorname{abs}\\left(x-1\\right)",
+        "e^{x}", "y^{x}", "x^{-3}", "\\operatorname{erf}\\left(x\\right)", "y!", "\\frac{1}{x-y}",
+    ];
+    for latex in expressions {
+        assert_bounds(latex, x, y)?;
+    }
+
+    //Periodic functions reach their extremes inside the box
+    let sin = assert_bounds("\\sin\\left(x\\right)", Interval::new(0.0, PI), y)?;
+    assert!(sin.hi == 1.0 && sin.lo.abs() < 1e-15);
+    assert_eq!(assert_bounds("\\cos\\left(x\\right)", Interval::new(3.0, 10.0), y)?, Interval::new(-1.0, 1.0));
+    assert_eq!(assert_bounds("\\tan\\left(x\\right)", Interval::new(1.0, 2.0), y)?, Interval::ENTIRE);
+    assert!(assert_bounds("\\tan\\left(x\\right)", Interval::new(-1.0, 1.0), y)?.width() < 3.2);
+
+    //The discontinuities of floor are included and the undefined parts are left out
+    assert_eq!(assert_bounds("\\operatorname{floor}\\left(x\\right)", Interval::new(0.5, 2.5), y)?, Interval::new(0.0, 2.0));
+    let sqrt = assert_bounds("\\sqrt{x}", Interval::new(-1.0, 4.0), y)?;
+    assert!(sqrt.lo == 0.0 && (sqrt.hi - 2.0).abs() < 1e-15);
+    assert!(assert_bounds("\\ln\\left(x\\right)", Interval::new(-2.0, -1.0), y)?.is_empty());
+    assert_eq!(assert_bounds("\\frac{1}{x}", Interval::new(-1.0, 1.0), y)?, Interval::ENTIRE);
+
+    Ok(())
+}
+
+#[test]
+fn curves_outside_of_a_box() -> error::Result<()> {
+    let circle = parse_latex("x^{2}+y^{2}=1", &HashMap::new())?;
+    let crosses = |x: Interval, y: Interval| drawn_interval(&circle, x, y, &HashMap::new()).map(|i| i.contains(0.0));
+    assert!(!crosses(Interval::new(2.0, 3.0), Interval::new(2.0, 3.0))?);
+    assert!(!crosses(Interval::new(-0.5, 0.5), Interval::new(-0.5, 0.5))?);
+    assert!(crosses(Interval::new(0.5, 1.0), Interval::new(0.5, 1.0))?);
+
+    //The bytecode gives the same bounds as the tree
+    let parabola = parse_latex("x^{2}-\\frac{1}{2}", &HashMap::new())?;
+    let (x, y) = (Interval::new(1.0, 2.0), Interval::new(-1.0, 0.0));
+    let program = compile_to_bytecode(&parabola, &HashMap::new())?;
+    let bounds = evaluate_bytecode_interval(&program, x, y)?;
+    assert_eq!(bounds.len(), 1);
+    assert!((bounds[0].lo - 0.5).abs() < 1e-12 && (bounds[0].hi - 4.5).abs() < 1e-12, "{bounds:?}");
+    assert!(!bounds[0].contains(0.0));
+    assert_eq!(bounds[0].contains(0.0), drawn_interval(&parabola, x, y, &HashMap::new())?.contains(0.0));
+
+    //It isn't defined anywhere in the box if the domain can't hold
+    let program = compile_to_bytecode(&parse_latex("\\sqrt{x}", &HashMap::new())?, &HashMap::new())?;
+    assert!(evaluate_bytecode_interval(&program, Interval::new(-2.0, -1.0), y)?[0].is_empty());
+
+    Ok(())
+}
+
+fn discontinuities_of(latex: &str, from: f64, to: f64) -> error::Result<Vec<Discontinuity>> {
+    let f = explicit_function(&parse_latex(latex, &HashMap::new())?)?;
+    Ok(discontinuities(&f, from, to, &HashMap::new()))
+}
+
+fn assert_discontinuities(found: &[Discontinuity], expected: &[(f64, DiscontinuityKind)]) {
+    assert_eq!(found.len(), expected.len(), "found {found:?}, expected {expected:?}");
+    for (d, (x, kind)) in found.iter().zip(expected) {
+        assert!((d.x - x).abs() < 1e-8 && d.kind == *kind, "found {found:?}, expected {expected:?}");
+    }
+}
+
+#[test]
+fn discontinuities_kinds() -> error::Result<()> {
+    use DiscontinuityKind::*;
+
+    assert_discontinuities(&discontinuities_of("y=\\frac{1}{x-1}", -3.0, 3.0)?, &[(1.0, Asymptote)]);
+    assert_discontinuities(&discontinuities_of("y=x^{-2}", -3.0, 3.0)?, &[(0.0, Asymptote)]);
+    assert_discontinuities(&discontinuities_of("y=\\tan\\left(x\\right)", 0.0, 5.0)?, &[(PI / 2.0, Asymptote), (3.0 * PI / 2.0, Asymptote)]);
+    assert_discontinuities(&discontinuities_of("y=\\ln\\left(x\\right)", -1.0, 1.0)?, &[(0.0, Asymptote)]);
+    assert_discontinuities(&discontinuities_of("y=\\operatorname{floor}\\left(x\\right)", -1.5, 2.5)?, &[(-1.0, Jump), (0.0, Jump), (1.0, Jump), (2.0, Jump)]);
+    assert_discontinuities(&discontinuities_of("y=\\operatorname{ceil}\\left(\\frac{x}{2}\\right)", 0.5, 3.0)?, &[(2.0, Jump)]);
+    assert_discontinuities(&discontinuities_of("y=\\frac{\\sin\\left(x\\right)}{x}", -2.0, 2.0)?, &[(0.0, Removable)]);
+    assert_discontinuities(&discontinuities_of("y=\\frac{x^{2}-1}{x-1}", -2.0, 2.0)?, &[(1.0, Removable)]);
+    assert_discontinuities(&discontinuities_of("y=\\frac{x}{x^{2}-4}", -3.0, 3.0)?, &[(-2.0, Asymptote), (2.0, Asymptote)]);
+
+    //The continuous functions don't have any
+    assert!(discontinuities_of("y=x^{3}-\\sin\\left(x\\right)", -3.0, 3.0)?.is_empty());
+    assert!(discontinuities_of("y=\\frac{1}{x^{2}+1}", -3.0, 3.0)?.is_empty());
+
+    Ok(())
+}
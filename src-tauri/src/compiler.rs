@@ -1,147 +1,137 @@
 use std::{collections::HashMap, f64::EPSILON};
 
-use log::info;
+use crate::{error::{self, AppError}, parser::{Node, UnaryOperation}};
 
-use crate::{error::{self, AppError}, parser::{BinaryOperation, NAryOperation, Node, UnaryOperation}};
-
-use self::bytecode::{compile_to_bytecode, print_instructions};
+use self::{ir::{lower, Function, Op}, verifier::{MAX_EXPRESSIONS, MAX_MASKS}};
 
 #[cfg(test)]
 mod tests;
+pub mod assembly;
 pub mod bytecode;
-
-struct CompileState <'a> {
-    variable_map: &'a HashMap<String, f64>,
-    denominators: Vec<String>,
+pub mod codegen;
+pub mod domain;
+pub mod ir;
+mod peephole;
+pub mod verifier;
+pub mod vm;
+
+struct CompileState {
+    /// The values computed before the expression, in the order they are needed
+    definitions: Vec<Definition>,
     expr_idx: usize,
 }
 
-pub fn compile_to_string(root: &Node, variable_map: &HashMap<String, f64>, expr_idx: usize) -> error::Result<String> {
-    let bytecode = compile_to_bytecode(root, variable_map, expr_idx)?;
-    print_instructions(&bytecode);
+struct Definition {
+    name: String,
+    code: String,
+    /// The GLSL function that masks the values where the expression can be discontinuous: the sign of the denominators and the parity of floor and ceil.
+    /// The temporaries of the repeated values aren't masked
+    mask: Option<&'static str>,
+}
 
-    let unknowns = ast_unknowns(root)?;
-    if unknowns == (false, false) {
-        return Err(AppError::MathError(format!("This equation doesn't have any unknowns")));
+pub fn compile_to_string(root: &Node, variable_map: &HashMap<String, f64>, expr_idx: usize) -> error::Result<String> {
+    if expr_idx >= MAX_EXPRESSIONS {
+        return Err(AppError::MathError(format!("Only {MAX_EXPRESSIONS} expressions can be drawn at the same time")));
     }
 
-    let mut compile_state = CompileState {
-        variable_map,
-        denominators: Vec::new(),
-        expr_idx
-    };
+    emit_glsl(&lower(root, variable_map)?, expr_idx)
+}
 
-    let code = if let Node::Binary{ op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } = root {
-        info!("The equation has an equal sign");
+/// Writes the function as GLSL. The values used more than once are computed once into temporaries
+fn emit_glsl(function: &Function, expr_idx: usize) -> error::Result<String> {
+    let uses = function.uses();
+    let mut compile_state = CompileState { definitions: Vec::new(), expr_idx };
+
+    let mut code: Vec<String> = Vec::with_capacity(function.instructions.len());
+    for (i, op) in function.instructions.iter().enumerate() {
+        let compiled = match op {
+            Op::Const(value) => format!("float({value})"),
+            Op::X => "x".to_owned(),
+            Op::Y => "y".to_owned(),
+            Op::Add(a, b) => match &function.instructions[*b] {
+                //The negation is fused only if nothing else uses it, otherwise it's already a temporary
+                Op::Unary(UnaryOperation::Minus, c) if uses[*b] == 1 => format!("fsub({}, {})", code[*a], code[*c]),
+                _ => format!("fadd({}, {})", code[*a], code[*b]),
+            },
+            Op::Mul(a, b) => format!("fmul({}, {})", code[*a], code[*b]),
+            Op::Div(a, b) => compile_div(&code[*a], code[*b].clone(), &mut compile_state),
+            Op::Pow(a, b) => format!("fpow({}, {})", code[*a], code[*b]),
+            Op::Unary(op_type, a) => {
+                let compiled = format!("{}({})", glsl_function(op_type)?, code[*a]);
+                match op_type {
+                    UnaryOperation::Floor | UnaryOperation::Ceil => compile_step(compiled, &mut compile_state),
+                    _ => compiled,
+                }
+            }
+        };
 
-        let compiled_lhs = compile(lhs, &mut compile_state)?;
-        let compiled_rhs = compile(rhs, &mut compile_state)?;
+        code.push(if uses[i] > 1 { shared(compiled, &mut compile_state) } else { compiled });
+    }
 
-        format!("fsub({compiled_lhs}, {compiled_rhs})")
-    } else {
-        let code = compile(root, &mut compile_state)?;
-        
-        if unknowns.0 == false {
-            format!("fsub({code}, x)")
-        } else {
-            format!("fsub({code}, y)")
-        }
-    };
+    //The pixels where the expression isn't defined are masked
+    let checks: Vec<String> = function.requirements.iter()
+        .map(|(v, requirement)| format!("fdefined({}, {})", code[*v], *requirement as i32))
+        .collect();
+    let undefined = if checks.is_empty() { "0".to_owned() } else { format!("int(!({}))", checks.join(" && ")) };
 
-    handle_denominators(code, &compile_state.denominators, expr_idx)
+    handle_denominators(code[function.result].clone(), undefined, &compile_state.definitions)
 }
 
-fn compile(root: &Node, compile_state: &mut CompileState) -> error::Result<String> {
-    match root {
-        Node::Constant { value } => Ok(format!("float({value})")),
-        Node::Variable { name } => {
-            let v = compile_state.variable_map.get(name).ok_or(AppError::IoError(format!("There are no variable called {name}")))?;
-            Ok(format!("float({v})"))
-        }
-        Node::Unknown { name } => {
-            Ok(name.clone()) 
-        }
-        Node::Unary { op_type, child } => {
-            let child = child.as_ref().ok_or(AppError::MathError(format!("There is nothing to operate on in {op_type:?}")))?;
-            let compiled_child = compile(&child, compile_state)?;
-
-            match op_type {
-                UnaryOperation::Minus => Ok(format!("fminus({compiled_child})")),
-                UnaryOperation::Sin => Ok(format!("fsin({compiled_child})")), 
-                UnaryOperation::Cos => Ok(format!("fcos({compiled_child})")), 
-                UnaryOperation::Floor => Ok(format!("ffloor({compiled_child})")), 
-                UnaryOperation::Abs => Ok(format!("fabs({compiled_child})")), 
-                UnaryOperation::Ceil => Ok(format!("fceil({compiled_child})")), 
-                UnaryOperation::Log => Ok(format!("flog({compiled_child})")), 
-                UnaryOperation::Ln => Ok(format!("fln({compiled_child})")), 
-                
-                UnaryOperation::Tan => compile_div(format!("fsin({compiled_child})"), format!("fcos({compiled_child})"), compile_state), 
-                UnaryOperation::Sqrt => Ok(format!("fsqrt({compiled_child})")), 
-                
-                UnaryOperation::Fact => Err(AppError::MathError("Factorial isn't implemented yet!".to_owned())),
-            }
-        },
-        Node::Binary { op_type, lhs, rhs } => {
-            let lhs = lhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the left to operate on in {op_type:?}")))?;
-            let rhs = rhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the right to operate on in {op_type:?}")))?;
-            let compiled_lhs = compile(&lhs, compile_state)?;
-            let compiled_rhs = compile(rhs, compile_state)?;
-            
-            match op_type {
-                BinaryOperation::Division => compile_div(compiled_lhs, compiled_rhs, compile_state),
-                BinaryOperation::Power => {
-                    if let Node::Constant { value } = **rhs {
-                        if f64::abs((value as i32) as f64 - value) < EPSILON {
-                            return compile_pow_integer(&compiled_lhs, value as i32, compile_state);
-                        }
-                    }
-
-                    Ok(format!("fpow({compiled_lhs}, {compiled_rhs})"))
-                }
-                BinaryOperation::Equal => Err(AppError::MathError("Equal is not an operation in this context".to_owned())),
-            }
-        }
-        Node::NAry { op_type, children } => {
-            let op = match op_type {
-                NAryOperation::Add => "fadd",
-                NAryOperation::Multiply => "fmul",
-            };
-
-            if children.len() < 2 { 
-                Err(AppError::MathError(format!("A {op_type:?} cannot be of less than two terms"))) 
-            } else {
-                let mut code = format!("{op}({}, {})", compile(&children[0], compile_state)?, compile(&children[1], compile_state)?);
+fn glsl_function(op_type: &UnaryOperation) -> error::Result<&'static str> {
+    match op_type {
+        UnaryOperation::Minus => Ok("fminus"),
+        UnaryOperation::Sin => Ok("fsin"),
+        UnaryOperation::Cos => Ok("fcos"),
+        UnaryOperation::Floor => Ok("ffloor"),
+        UnaryOperation::Abs => Ok("fabs"),
+        UnaryOperation::Ceil => Ok("fceil"),
+        UnaryOperation::Log => Ok("flog"),
+        UnaryOperation::Ln => Ok("fln"),
+        UnaryOperation::Sqrt => Ok("fsqrt"),
+        UnaryOperation::Fact => Ok("ffact"),
+        UnaryOperation::Erf => Ok("ferf"),
+        UnaryOperation::Erfc => Ok("ferfc"),
+        op_type => Err(AppError::MathError(format!("{op_type:?} isn't an operation of the shaders"))),
+    }
+}
 
-                for t in children.iter().skip(2) {
-                    code = format!("{op}({code}, {})", compile(t, compile_state)?);
-                }
+fn compile_div(num: &str, den: String, compile_state: &mut CompileState) -> String {
+    let den = define(den, Some("fneg"), compile_state);
+    format!("fdiv( {num}, {den} )")
+}
 
-                Ok(code)
-            }
-        }
-    }
+//The pixels at both sides of a jump of floor or ceil have different parities, so they aren't joined by a false vertical line
+fn compile_step(code: String, compile_state: &mut CompileState) -> String {
+    define(code, Some("fodd"), compile_state)
 }
 
-fn compile_div(num: String, den: String, compile_state: &mut CompileState) -> error::Result<String> {
-    compile_state.denominators.push(den);
-    Ok(format!("fdiv( {num}, var_{}_{} )", compile_state.expr_idx, compile_state.denominators.len()-1))
+/// Stores the code in a variable before the expression. The masked values are var_{expr}_{n}, as their bits are in the same order, and the temporaries t_{expr}_{k}
+fn define(code: String, mask: Option<&'static str>, compile_state: &mut CompileState) -> String {
+    let n = compile_state.definitions.iter().filter(|d| d.mask.is_some() == mask.is_some()).count();
+    let name = match mask {
+        Some(_) => format!("var_{}_{n}", compile_state.expr_idx),
+        None => format!("t_{}_{n}", compile_state.expr_idx),
+    };
+
+    compile_state.definitions.push(Definition { name: name.clone(), code, mask });
+    name
 }
 
-fn compile_pow_integer(code: &str, times: i32, compile_state: &mut CompileState) -> error::Result<String> {
-    if times == 0 {
-        Ok(format!("1.0"))
-    } else if times < 0 {
-        Ok(compile_div(
-            "1.0".to_owned(), 
-            compile_pow_integer(code, i32::abs(times), compile_state)?, 
-            compile_state,
-        )?)
-    } else if times == 1 {
-        Ok(format!("{code}"))
-    } else if times == 2 {
-        Ok(format!("fmul({code}, {code})"))
+/// The code of a value used several times is stored in a temporary, unless it's already a single value like var_0_1
+fn shared(code: String, compile_state: &mut CompileState) -> String {
+    let literal = code.starts_with("float(") && code.matches('(').count() == 1;
+    if literal || code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        code
     } else {
-        Ok(format!("fmul({code}, {})", compile_pow_integer(code, times-1, compile_state)?))
+        define(code, None, compile_state)
+    }
+}
+
+/// The exponent of a power if it's an integer constant, then the power is expanded into products
+pub(crate) fn integer_exponent(rhs: &Node) -> Option<i32> {
+    match rhs {
+        Node::Constant { value } if f64::abs((*value as i32) as f64 - value) < EPSILON => Some(*value as i32),
+        _ => None,
     }
 }
 
@@ -181,22 +171,34 @@ pub fn ast_unknowns(root: &Node) -> error::Result<(bool, bool)> {
 
             Ok(unk)
         }
+        Node::Matrix { rows } => {
+            let mut unk = (false, false);
+            for node in rows.iter().flatten() {
+                let a = ast_unknowns(node)?;
+                unk = (unk.0 | a.0, unk.1 | a.1);
+            }
+
+            Ok(unk)
+        }
         _ => Ok((false, false))
     }
 }
 
-fn handle_denominators(code: String, denominators: &Vec<String>, expr_idx: usize) -> error::Result<String> {
-    if denominators.len() > 32 {
-        return Err(AppError::IoError(format!("A function can't have more than 32 denominators")));
+fn handle_denominators(code: String, undefined: String, definitions: &[Definition]) -> error::Result<String> {
+    if definitions.iter().filter(|d| d.mask.is_some()).count() > MAX_MASKS {
+        return Err(AppError::IoError(format!("A function can't have more than {MAX_MASKS} denominators, floors and ceils")));
     }
 
-    let dens = denominators.iter().enumerate().fold(String::new(), |s, (i, e)| {
-        s + &format!("
-            float var_{expr_idx}_{i} = {e};
+    let dens = definitions.iter().fold(String::new(), |s, Definition { name, code: e, mask }| match mask {
+        Some(mask) => s + &format!("
+            float {name} = {e};
             ret.y <<= 1; 
-            ret.y |= int(fneg(var_{expr_idx}_{i}));")
+            ret.y |= int({mask}({name}));"),
+        None => s + &format!("
+            float {name} = {e};"),
     });
 
     Ok(format!("{dens}
-            ret.x = int(fneg({code}));"))
+            ret.x = int(fneg({code}));
+            ret.z = {undefined};"))
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use crate::{error::{self, AppError}, parser::UnaryOperation};
+
+use super::{bytecode::Instruction, domain::Requirement};
+
+/// The mnemonics of the unary operations that the shader runs
+const UNARY_MNEMONICS: [(&str, UnaryOperation); 13] = [
+    ("minus", UnaryOperation::Minus), ("sin", UnaryOperation::Sin), ("cos", UnaryOperation::Cos), ("floor", UnaryOperation::Floor),
+    ("abs", UnaryOperation::Abs), ("ceil", UnaryOperation::Ceil), ("log", UnaryOperation::Log), ("ln", UnaryOperation::Ln),
+    ("sqrt", UnaryOperation::Sqrt), ("tan", UnaryOperation::Tan), ("erf", UnaryOperation::Erf), ("erfc", UnaryOperation::Erfc),
+    ("fact", UnaryOperation::Fact),
+];
+
+const REQUIREMENT_MNEMONICS: [(&str, Requirement); 3] = [
+    ("positive", Requirement::Positive), ("non_negative", Requirement::NonNegative), ("non_zero", Requirement::NonZero),
+];
+
+/// Writes the program as assembly, one instruction per line. Every expression is a section that starts with the label expr_{n}:
+pub fn disassemble(program: &[Instruction]) -> error::Result<String> {
+    let mut source = String::new();
+    let mut new_section = true;
+    let mut expressions = 0;
+
+    for instruction in program {
+        if new_section {
+            source += &format!("expr_{expressions}:\n");
+            expressions += 1;
+            new_section = false;
+        }
+
+        let line = match instruction {
+            Instruction::Push(c) => format!("push {c}"),
+            Instruction::PushX => "push_x".to_owned(),
+            Instruction::PushY => "push_y".to_owned(),
+            Instruction::Cpy => "cpy".to_owned(),
+            Instruction::Pop => "pop".to_owned(),
+            Instruction::Store => "store".to_owned(),
+            Instruction::Require(r) => {
+                let (name, _) = REQUIREMENT_MNEMONICS.iter().find(|(_, requirement)| requirement == r).expect("Every requirement has a mnemonic");
+                format!("require {name}")
+            }
+            Instruction::Load(l) => format!("load {l}"),
+            Instruction::Save(l) => format!("save {l}"),
+            Instruction::Add => "add".to_owned(),
+            Instruction::Sub => "sub".to_owned(),
+            Instruction::Mul => "mul".to_owned(),
+            Instruction::Div => "div".to_owned(),
+            Instruction::Pow => "pow".to_owned(),
+            Instruction::UnaryOperation(op) => {
+                let (name, _) = UNARY_MNEMONICS.iter().find(|(_, unary)| unary == op)
+                    .ok_or(AppError::MathError(format!("{op:?} of a number isn't an instruction")))?;
+                name.to_string()
+            }
+            Instruction::Ret => {
+                new_section = true;
+                "ret".to_owned()
+            }
+        };
+
+        source += &format!("    {line}\n");
+    }
+
+    Ok(source)
+}
+
+/// Reads the assembly written by disassemble. The labels and the comments after a ; are ignored, the sections end with ret
+pub fn assemble(source: &str) -> error::Result<Vec<Instruction>> {
+    let mut program = Vec::new();
+
+    for (n, line) in source.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() || line.ends_with(':') {
+            continue;
+        }
+
+        let error = |message: &str| AppError::ParseError(format!("{message} in the line {}: {line}", n + 1));
+        let mut words = line.split_whitespace();
+        let mnemonic = words.next().unwrap_or_default();
+        let operand = words.next();
+        if words.next().is_some() {
+            return Err(error("There are too many operands"));
+        }
+
+        let index = || -> error::Result<usize> {
+            operand.ok_or(error("The index is missing"))?.parse().map_err(|_| error("The operand isn't an index"))
+        };
+        let instruction = match mnemonic {
+            "push" => Instruction::Push(operand.ok_or(error("The constant is missing"))?.parse().map_err(|_| error("The operand isn't a number"))?),
+            "require" => {
+                let requirement = REQUIREMENT_MNEMONICS.iter().find(|(name, _)| Some(*name) == operand).ok_or(error("The requirement isn't known"))?;
+                Instruction::Require(requirement.1)
+            }
+            "load" => Instruction::Load(index()?),
+            "save" => Instruction::Save(index()?),
+            _ if operand.is_some() => return Err(error("This instruction doesn't have operands")),
+            "push_x" => Instruction::PushX,
+            "push_y" => Instruction::PushY,
+            "cpy" => Instruction::Cpy,
+            "pop" => Instruction::Pop,
+            "store" => Instruction::Store,
+            "ret" => Instruction::Ret,
+            "add" => Instruction::Add,
+            "sub" => Instruction::Sub,
+            "mul" => Instruction::Mul,
+            "div" => Instruction::Div,
+            "pow" => Instruction::Pow,
+            _ => {
+                let (_, op) = UNARY_MNEMONICS.iter().find(|(name, _)| *name == mnemonic).ok_or(error("The instruction isn't known"))?;
+                Instruction::UnaryOperation(op.clone())
+            }
+        };
+
+        program.push(instruction);
+    }
+
+    Ok(program)
+}
@@ -1,173 +1,115 @@
-use std::{collections::HashMap, f64::EPSILON, vec};
+use std::collections::HashMap;
 
-use log::info;
+use crate::{error::{self, AppError}, parser::{Node, UnaryOperation}};
 
-use crate::{error::{self, AppError}, parser::{BinaryOperation, NAryOperation, Node, UnaryOperation}};
-
-use super::{ast_unknowns, CompileState};
+use super::{domain::Requirement, ir::{lower, Function, Op, Value}, peephole::optimize, verifier::{verify, MAX_LOCALS}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
-    Push(f64), PushX, PushY, Cpy, Pop, Ret, Store, Add, Mul, Div, Pow, UnaryOperation(UnaryOperation)
+    Push(f64), PushX, PushY, Cpy, Pop, Ret, Store, Require(Requirement), Load(usize), Save(usize), Add, Sub, Mul, Div, Pow, UnaryOperation(UnaryOperation)
 }
 
-pub fn compile_to_bytecode(root: &Node, variable_map: &HashMap<String, f64>, expr_idx: usize) -> error::Result<Vec<Instruction>> {
-    let unknowns = ast_unknowns(root)?;
-    if unknowns == (false, false) {
-        return Err(AppError::MathError(format!("This equation doesn't have any unknowns")));
-    }
-
-    let mut compile_state = CompileState {
-        variable_map,
-        denominators: Vec::new(),
-        expr_idx
-    };
-
-    let mut code = Vec::new();
+pub fn compile_to_bytecode(root: &Node, variable_map: &HashMap<String, f64>) -> error::Result<Vec<Instruction>> {
+    let program = optimize(&emit_bytecode(&lower(root, variable_map)?)?);
+    verify(&program)?;
 
-    if let Node::Binary{ op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } = root {
-        info!("The equation has an equal sign");
-
-        let compiled_lhs = compile_bytecode(lhs, &mut compile_state)?;
-        let compiled_rhs = compile_bytecode(rhs, &mut compile_state)?;
+    Ok(program)
+}
 
-        code.extend(compiled_lhs);
-        code.extend(compiled_rhs);
-    } else {
-        code.extend(compile_bytecode(root, &mut compile_state)?);
-        
-        if unknowns.0 == false {
-            code.push(Instruction::PushX);
-        } else {
-            code.push(Instruction::PushY);
-        }
+/// Writes the function for the stack machine of the shader. The value stays in the stack while the conditions of the domain are checked
+pub fn emit_bytecode(function: &Function) -> error::Result<Vec<Instruction>> {
+    let mut emitter = Emitter {
+        function,
+        remaining: function.uses(),
+        locals: vec![None; function.instructions.len()],
+        free: Vec::new(),
+        used_locals: 0,
+        code: Vec::new(),
     };
 
-    code.push(Instruction::UnaryOperation(UnaryOperation::Minus));
-    code.push(Instruction::Add);
-    code.push(Instruction::Store);
-    code.push(Instruction::Ret);
+    emitter.emit(function.result)?;
+    emitter.code.push(Instruction::Store);
+    for (value, requirement) in &function.requirements {
+        emitter.emit(*value)?;
+        emitter.code.push(Instruction::Require(*requirement));
+    }
+    emitter.code.push(Instruction::Ret);
+
+    Ok(emitter.code)
+}
 
-    Ok(code)
+struct Emitter<'a> {
+    function: &'a Function,
+    /// The uses of each value that haven't been emitted yet
+    remaining: Vec<usize>,
+    /// The local that keeps each value used more than once after it's computed
+    locals: Vec<Option<usize>>,
+    /// The locals of the values that won't be used again
+    free: Vec<usize>,
+    used_locals: usize,
+    code: Vec<Instruction>,
 }
 
-fn compile_bytecode(root: &Node, compile_state: &mut CompileState) -> error::Result<Vec<Instruction>> {
-    match root {
-        Node::Constant { value } => Ok(vec![Instruction::Push(*value)]),
-        Node::Variable { name } => {
-            let v = compile_state.variable_map.get(name).ok_or(AppError::IoError(format!("There are no variable called {name}")))?;
-            Ok(vec![Instruction::Push(*v)])
+impl Emitter<'_> {
+    fn emit(&mut self, value: Value) -> error::Result<()> {
+        if let Some(local) = self.locals[value] {
+            self.code.push(Instruction::Load(local));
+            self.release(value);
+            return Ok(());
         }
-        Node::Unknown { name } => {
-            match name.as_str() {
-                "x" => Ok(vec![Instruction::PushX]),
-                "y" => Ok(vec![Instruction::PushY]),
-                _ => Err(AppError::MathError(format!("There aren't any unknowns called: {name}")))
+
+        let op = &self.function.instructions[value];
+        match op {
+            Op::Const(c) => self.code.push(Instruction::Push(*c)),
+            Op::X => self.code.push(Instruction::PushX),
+            Op::Y => self.code.push(Instruction::PushY),
+            Op::Add(a, b) => self.binary(*a, *b, Instruction::Add)?,
+            Op::Mul(a, b) => self.binary(*a, *b, Instruction::Mul)?,
+            Op::Div(a, b) => self.binary(*a, *b, Instruction::Div)?,
+            Op::Pow(a, b) => self.binary(*a, *b, Instruction::Pow)?,
+            Op::Unary(op_type, a) => {
+                self.emit(*a)?;
+                self.code.push(Instruction::UnaryOperation(op_type.clone()));
             }
         }
-        Node::Unary { op_type, child } => {
-            let child = child.as_ref().ok_or(AppError::MathError(format!("There is nothing to operate on in {op_type:?}")))?;
-            let mut compiled_child = compile_bytecode(&child, compile_state)?;
-
-            match op_type {                
-                UnaryOperation::Fact => Err(AppError::MathError("Factorial isn't implemented yet!".to_owned())),
-                
-                op => {
-                    compiled_child.push(Instruction::UnaryOperation(op.clone()));
-                    Ok(compiled_child)
-                }
-            }
-        },
-        Node::Binary { op_type, lhs, rhs } => {
-            let lhs = lhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the left to operate on in {op_type:?}")))?;
-            let rhs = rhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the right to operate on in {op_type:?}")))?;
-            let compiled_lhs = compile_bytecode(&lhs, compile_state)?;
-            let compiled_rhs = compile_bytecode(rhs, compile_state)?;
-            
-            match op_type {
-                BinaryOperation::Division => {
-                    let mut compiled = compiled_lhs;
-                    compiled.extend(compiled_rhs);
-                    compiled.push(Instruction::Div);
-                    Ok(compiled)
-                },
-                BinaryOperation::Power => {
-                    let mut compiled = compiled_lhs;
-                    
-                    if let Node::Constant { value } = **rhs {
-                        if f64::abs((value as i32) as f64 - value) < EPSILON {
-                            compiled.extend(compile_pow_integer(value as i32, compile_state)?);
-                            return Ok(compiled);
-                        }
-                    }
-
-                    compiled.extend(compiled_rhs);
-                    compiled.push(Instruction::Pow);
-                    Ok(compiled)
-                }
-                BinaryOperation::Equal => Err(AppError::MathError("Equal is not an operation in this context".to_owned())),
+
+        //Pushing a constant or an unknown again is as cheap as loading it
+        if self.remaining[value] > 1 && !matches!(op, Op::Const(_) | Op::X | Op::Y) {
+            let local = self.free.pop().unwrap_or(self.used_locals);
+            if local == self.used_locals {
+                self.used_locals += 1;
             }
-        }
-        Node::NAry { op_type, children } => {
-            let op = match op_type {
-                NAryOperation::Add => Instruction::Add,
-                NAryOperation::Multiply => Instruction::Mul,
-            };
-
-            if children.len() < 2 { 
-                Err(AppError::MathError(format!("A {op_type:?} cannot be of less than two terms"))) 
-            } else {
-                let compiled: error::Result<Vec<Vec<Instruction>>> = children.iter().map(|e| compile_bytecode(e, compile_state)).collect();
-                let mut compiled: Vec<_> = compiled?.into_iter().flatten().collect();
-
-                for _ in 0..children.len()-1 {
-                    compiled.push(op.clone());
-                }
-
-                Ok(compiled)
+            if local >= MAX_LOCALS {
+                return Err(AppError::MathError(format!("The expression needs more than {MAX_LOCALS} locals to be drawn")));
             }
+
+            self.locals[value] = Some(local);
+            self.code.push(Instruction::Save(local));
         }
+        self.release(value);
+
+        Ok(())
     }
-}
 
-fn compile_pow_integer(times: i32, compile_state: &mut CompileState) -> error::Result<Vec<Instruction>> {
-    if times == 0 {
-        Ok(vec![Instruction::Pop, Instruction::Push(1.0)])
-    } else if times == 1 {
-        Ok(vec![])
-    } else if times < 0 {
-        let mut compiled = vec![Instruction::Push(1.0)];
-        compiled.extend(compile_pow_integer(-times, compile_state)?);
-
-        Ok(compiled)
-    } else {
-        let mut compiled = Vec::new();
-        for _ in 0..times-1 {
-            compiled.push(Instruction::Cpy);
-        }
-        for _ in 0..times-1 {
-            compiled.push(Instruction::Mul);
+    /// x*x is computed as x and a copy of it
+    fn binary(&mut self, a: Value, b: Value, instruction: Instruction) -> error::Result<()> {
+        if a == b {
+            self.remaining[a] -= 1;
+            self.emit(a)?;
+            self.code.push(Instruction::Cpy);
+        } else {
+            self.emit(a)?;
+            self.emit(b)?;
         }
 
-        Ok(compiled)
+        self.code.push(instruction);
+        Ok(())
     }
-}
 
-pub fn print_instructions(instructions: &Vec<Instruction>) {
-    for i in instructions {
-        match i {
-            Instruction::Store => println!("store"),
-            Instruction::Push(x) => println!("push {x}"),
-            Instruction::PushX => println!("push_x"),
-            Instruction::PushY => println!("push_y"),
-            Instruction::Cpy => println!("cpy"),
-            Instruction::Pop => println!("pop"),
-            Instruction::Ret => println!("ret"),
-            Instruction::Add => println!("add"),
-            Instruction::Mul => println!("mul"),
-            Instruction::Div => println!("div"),
-            Instruction::Pow => println!("pow"),
-            Instruction::UnaryOperation(u) => println!("{u:?}"),
+    fn release(&mut self, value: Value) {
+        self.remaining[value] -= 1;
+        if let (0, Some(local)) = (self.remaining[value], self.locals[value]) {
+            self.free.push(local);
         }
     }
 }
@@ -183,12 +125,16 @@ impl Instruction {
             Instruction::Cpy =>                 Ok((4, 0.0)),
             Instruction::Pop =>                 Ok((5, 0.0)),
             Instruction::Store =>               Ok((6, 0.0)),
+            Instruction::Require(r) =>     Ok((7, *r as u8 as f64)),
+            Instruction::Load(l) =>        Ok((8, *l as f64)),
+            Instruction::Save(l) =>        Ok((9, *l as f64)),
             
             //Binary operations
             Instruction::Add =>                 Ok((32 | 0, 0.0)),
             Instruction::Mul =>                 Ok((32 | 1, 0.0)),
             Instruction::Div =>                 Ok((32 | 2, 0.0)),
             Instruction::Pow =>                 Ok((32 | 3, 0.0)),
+            Instruction::Sub =>                 Ok((32 | 4, 0.0)),
             
             //Unary operations
             Instruction::UnaryOperation(op) => {
@@ -203,11 +149,72 @@ impl Instruction {
                     UnaryOperation::Ln =>       7,
                     UnaryOperation::Sqrt =>     8,
                     UnaryOperation::Tan =>      9,
-                    UnaryOperation::Fact => Err(AppError::MathError(format!("Factorial isn't implemented in bytecode")))?,
+                    UnaryOperation::Erf =>      10,
+                    UnaryOperation::Erfc =>     11,
+                    UnaryOperation::Fact =>     12,
+                    UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median
+                        | UnaryOperation::Stdev | UnaryOperation::Var => Err(AppError::MathError(format!("{op:?} of a number isn't an instruction")))?,
                 };
 
                 Ok((64 | op_code, 0.0))
             }
         }
     }
-}
\ No newline at end of file
+    /// Decodes the instruction from the opcode and the operand of the texture, the inverse of to_number_pair
+    pub fn from_number_pair(op_code: u8, operand: f64) -> error::Result<Instruction> {
+        //The locals and the requirements are indices
+        let index = || -> error::Result<usize> {
+            if operand.fract() == 0.0 && operand >= 0.0 { Ok(operand as usize) }
+            else { Err(AppError::ParseError(format!("The operand {operand} of the opcode {op_code} isn't an index"))) }
+        };
+
+        match op_code {
+            //Basic operations
+            0 =>        Ok(Instruction::Ret),
+            1 =>        Ok(Instruction::Push(operand)),
+            2 =>        Ok(Instruction::PushX),
+            3 =>        Ok(Instruction::PushY),
+            4 =>        Ok(Instruction::Cpy),
+            5 =>        Ok(Instruction::Pop),
+            6 =>        Ok(Instruction::Store),
+            7 => match index()? {
+                0 =>    Ok(Instruction::Require(Requirement::Positive)),
+                1 =>    Ok(Instruction::Require(Requirement::NonNegative)),
+                2 =>    Ok(Instruction::Require(Requirement::NonZero)),
+                r =>    Err(AppError::ParseError(format!("{r} isn't a requirement of the domain"))),
+            },
+            8 =>        Ok(Instruction::Load(index()?)),
+            9 =>        Ok(Instruction::Save(index()?)),
+
+            //Binary operations
+            0x20 =>     Ok(Instruction::Add),
+            0x21 =>     Ok(Instruction::Mul),
+            0x22 =>     Ok(Instruction::Div),
+            0x23 =>     Ok(Instruction::Pow),
+            0x24 =>     Ok(Instruction::Sub),
+
+            //Unary operations
+            0x40..=0x7f => {
+                let op = match op_code & !64 {
+                    0 =>    UnaryOperation::Minus,
+                    1 =>    UnaryOperation::Sin,
+                    2 =>    UnaryOperation::Cos,
+                    3 =>    UnaryOperation::Floor,
+                    4 =>    UnaryOperation::Abs,
+                    5 =>    UnaryOperation::Ceil,
+                    6 =>    UnaryOperation::Log,
+                    7 =>    UnaryOperation::Ln,
+                    8 =>    UnaryOperation::Sqrt,
+                    9 =>    UnaryOperation::Tan,
+                    10 =>   UnaryOperation::Erf,
+                    11 =>   UnaryOperation::Erfc,
+                    12 =>   UnaryOperation::Fact,
+                    _ => Err(AppError::ParseError(format!("{op_code} isn't the opcode of a unary operation")))?,
+                };
+
+                Ok(Instruction::UnaryOperation(op))
+            }
+            _ => Err(AppError::ParseError(format!("{op_code} isn't an opcode of the bytecode"))),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::{collections::HashMap, f64::consts::{E, PI}};
+
+use serde::Deserialize;
+
+use crate::{error::{self, AppError}, parser::{simplify_tree, BinaryOperation, NAryOperation, Node, UnaryOperation}};
+
+use super::{ast_unknowns, integer_exponent};
+
+/// The integer powers of a single symbol up to this one are written as products
+const MAX_EXPANDED_POWER: i32 = 4;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Rust, C, JavaScript, Python
+}
+
+/// How tightly an expression binds, the operands with a lower one than their operation are put inside parentheses
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    Sum, Product, Unary, Power, Atom
+}
+
+/// Writes the expression as a function of its unknowns in the language, the variables are replaced by their values.
+/// The equations that aren't y=f(x) are written as lhs - rhs, which is zero on the curve
+pub fn generate_code(root: &Node, name: &str, language: Language, variable_map: &HashMap<String, f64>) -> error::Result<String> {
+    let mut root = root.clone();
+    simplify_tree(&mut root, variable_map);
+    simplify_tree(&mut root, variable_map);
+
+    let (x, y) = ast_unknowns(&root)?;
+    let params: Vec<&str> = [(x, "x"), (y, "y")].into_iter().filter(|(used, _)| *used).map(|(_, p)| p).collect();
+
+    let mut state = CodegenState { language, variable_map, scipy: false };
+    let (body, _) = state.expression(&root)?;
+
+    let code = match language {
+        Language::Rust => {
+            let params: Vec<String> = params.iter().map(|p| format!("{p}: f64")).collect();
+            format!("fn {name}({}) -> f64 {{\n    {body}\n}}\n", params.join(", "))
+        }
+        Language::C => {
+            let params: Vec<String> = params.iter().map(|p| format!("double {p}")).collect();
+            format!("#include <math.h>\n\ndouble {name}({}) {{\n    return {body};\n}}\n", params.join(", "))
+        }
+        Language::JavaScript => format!("function {name}({}) {{\n    return {body};\n}}\n", params.join(", ")),
+        Language::Python => {
+            let imports = if state.scipy { "import numpy as np\nfrom scipy import special\n" } else { "import numpy as np\n" };
+            format!("{imports}\n\ndef {name}({}):\n    return {body}\n", params.join(", "))
+        }
+    };
+
+    Ok(code)
+}
+
+struct CodegenState<'a> {
+    language: Language,
+    variable_map: &'a HashMap<String, f64>,
+    /// If the Python code needs the special functions of SciPy
+    scipy: bool,
+}
+
+impl CodegenState<'_> {
+    fn expression(&mut self, root: &Node) -> error::Result<(String, Precedence)> {
+        match root {
+            Node::Constant { value } => Ok(self.constant(*value)),
+            Node::Variable { name } => {
+                let v = self.variable_map.get(name).ok_or(AppError::IoError(format!("There are no variable called {name}")))?;
+                Ok(self.constant(*v))
+            }
+            Node::Unknown { name } => Ok((name.to_owned(), Precedence::Atom)),
+            Node::Unary { op_type, child } => {
+                let child = child.as_ref().ok_or(AppError::MathError(format!("There is nothing to operate on in {op_type:?}")))?;
+                self.unary(op_type, child)
+            }
+            Node::Binary { op_type, lhs, rhs } => {
+                let lhs = lhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the left to operate on in {op_type:?}")))?;
+                let rhs = rhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the right to operate on in {op_type:?}")))?;
+
+                match op_type {
+                    BinaryOperation::Division => {
+                        let num = self.operand(lhs, Precedence::Product)?;
+                        let den = self.operand(rhs, Precedence::Unary)?;
+                        Ok((format!("{num} / {den}"), Precedence::Product))
+                    }
+                    BinaryOperation::Power => self.power(lhs, rhs),
+                    BinaryOperation::Equal => {
+                        let lhs = self.operand(lhs, Precedence::Sum)?;
+                        let rhs = self.operand(rhs, Precedence::Product)?;
+                        Ok((format!("{lhs} - {rhs}"), Precedence::Sum))
+                    }
+                }
+            }
+            Node::NAry { op_type, children } => {
+                if children.len() < 2 {
+                    return Err(AppError::MathError(format!("A {op_type:?} cannot be of less than two terms")));
+                }
+
+                match op_type {
+                    NAryOperation::Add => {
+                        let mut code = self.operand(&children[0], Precedence::Sum)?;
+                        for child in children.iter().skip(1) {
+                            match child.as_ref() {
+                                Node::Unary { op_type: UnaryOperation::Minus, child: Some(c) } => {
+                                    code += &format!(" - {}", self.operand(c, Precedence::Product)?);
+                                }
+                                Node::Constant { value } if *value < 0.0 => code += &format!(" - {}", self.constant(-value).0),
+                                child => code += &format!(" + {}", self.operand(child, Precedence::Sum)?),
+                            }
+                        }
+                        Ok((code, Precedence::Sum))
+                    }
+                    NAryOperation::Multiply => {
+                        let factors: error::Result<Vec<String>> = children.iter().map(|c| self.operand(c, Precedence::Product)).collect();
+                        Ok((factors?.join(" * "), Precedence::Product))
+                    }
+                }
+            }
+            Node::Matrix { .. } => Err(AppError::MathError("A matrix can't be written as code".to_owned())),
+        }
+    }
+
+    /// The code of an operand, inside parentheses if it binds less than the operation
+    fn operand(&mut self, root: &Node, min: Precedence) -> error::Result<String> {
+        let (code, precedence) = self.expression(root)?;
+        Ok(if precedence < min { format!("({code})") } else { code })
+    }
+
+    fn constant(&self, value: f64) -> (String, Precedence) {
+        let abs = value.abs();
+        let code = match self.language {
+            Language::Rust if abs == PI => "std::f64::consts::PI".to_owned(),
+            Language::Rust if abs == E => "std::f64::consts::E".to_owned(),
+            Language::Rust if abs.is_infinite() => "f64::INFINITY".to_owned(),
+            Language::Rust if abs.is_nan() => "f64::NAN".to_owned(),
+            Language::C if abs == PI => "M_PI".to_owned(),
+            Language::C if abs == E => "M_E".to_owned(),
+            Language::C if abs.is_infinite() => "INFINITY".to_owned(),
+            Language::C if abs.is_nan() => "NAN".to_owned(),
+            Language::JavaScript if abs == PI => "Math.PI".to_owned(),
+            Language::JavaScript if abs == E => "Math.E".to_owned(),
+            Language::JavaScript if abs.is_infinite() => "Infinity".to_owned(),
+            Language::JavaScript if abs.is_nan() => "NaN".to_owned(),
+            Language::Python if abs == PI => "np.pi".to_owned(),
+            Language::Python if abs == E => "np.e".to_owned(),
+            Language::Python if abs.is_infinite() => "np.inf".to_owned(),
+            Language::Python if abs.is_nan() => "np.nan".to_owned(),
+            //The debug format always has a decimal point or an exponent, so C doesn't read it as an integer
+            _ => format!("{abs:?}"),
+        };
+
+        if value < 0.0 { (format!("-{code}"), Precedence::Unary) } else { (code, Precedence::Atom) }
+    }
+
+    fn unary(&mut self, op_type: &UnaryOperation, child: &Node) -> error::Result<(String, Precedence)> {
+        let name = match op_type {
+            UnaryOperation::Minus => {
+                let (code, precedence) = self.expression(child)?;
+                //Two minus signs together are a decrement in C and JavaScript, and -a·b is (-a)·b
+                return match precedence {
+                    Precedence::Sum => Ok((format!("-({code})"), Precedence::Unary)),
+                    _ if code.starts_with('-') => Ok((format!("-({code})"), Precedence::Unary)),
+                    Precedence::Product => Ok((format!("-{code}"), Precedence::Product)),
+                    _ => Ok((format!("-{code}"), Precedence::Unary)),
+                };
+            }
+            UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median => return self.expression(child),
+            UnaryOperation::Stdev | UnaryOperation::Var => {
+                return Err(AppError::MathError(format!("The {op_type:?} of a single number isn't defined")));
+            }
+            UnaryOperation::Fact => return self.factorial(child),
+            op_type => self.function_name(op_type)?,
+        };
+
+        self.call(name, child)
+    }
+
+    /// The name of the function in the language, the methods of f64 in Rust
+    fn function_name(&mut self, op_type: &UnaryOperation) -> error::Result<&'static str> {
+        let name = match (self.language, op_type) {
+            (Language::Rust, UnaryOperation::Sin) => "sin",
+            (Language::Rust, UnaryOperation::Cos) => "cos",
+            (Language::Rust, UnaryOperation::Tan) => "tan",
+            (Language::Rust, UnaryOperation::Floor) => "floor",
+            (Language::Rust, UnaryOperation::Ceil) => "ceil",
+            (Language::Rust, UnaryOperation::Abs) => "abs",
+            (Language::Rust, UnaryOperation::Log) => "log10",
+            (Language::Rust, UnaryOperation::Ln) => "ln",
+            (Language::Rust, UnaryOperation::Sqrt) => "sqrt",
+            (Language::C, UnaryOperation::Abs) => "fabs",
+            (Language::C, UnaryOperation::Log) => "log10",
+            (Language::C, UnaryOperation::Ln) => "log",
+            (Language::C, UnaryOperation::Sin) => "sin",
+            (Language::C, UnaryOperation::Cos) => "cos",
+            (Language::C, UnaryOperation::Tan) => "tan",
+            (Language::C, UnaryOperation::Floor) => "floor",
+            (Language::C, UnaryOperation::Ceil) => "ceil",
+            (Language::C, UnaryOperation::Sqrt) => "sqrt",
+            (Language::C, UnaryOperation::Erf) => "erf",
+            (Language::C, UnaryOperation::Erfc) => "erfc",
+            (Language::JavaScript, UnaryOperation::Sin) => "Math.sin",
+            (Language::JavaScript, UnaryOperation::Cos) => "Math.cos",
+            (Language::JavaScript, UnaryOperation::Tan) => "Math.tan",
+            (Language::JavaScript, UnaryOperation::Floor) => "Math.floor",
+            (Language::JavaScript, UnaryOperation::Ceil) => "Math.ceil",
+            (Language::JavaScript, UnaryOperation::Abs) => "Math.abs",
+            (Language::JavaScript, UnaryOperation::Log) => "Math.log10",
+            (Language::JavaScript, UnaryOperation::Ln) => "Math.log",
+            (Language::JavaScript, UnaryOperation::Sqrt) => "Math.sqrt",
+            (Language::Python, UnaryOperation::Sin) => "np.sin",
+            (Language::Python, UnaryOperation::Cos) => "np.cos",
+            (Language::Python, UnaryOperation::Tan) => "np.tan",
+            (Language::Python, UnaryOperation::Floor) => "np.floor",
+            (Language::Python, UnaryOperation::Ceil) => "np.ceil",
+            (Language::Python, UnaryOperation::Abs) => "np.abs",
+            (Language::Python, UnaryOperation::Log) => "np.log10",
+            (Language::Python, UnaryOperation::Ln) => "np.log",
+            (Language::Python, UnaryOperation::Sqrt) => "np.sqrt",
+            (Language::Python, UnaryOperation::Erf) => { self.scipy = true; "special.erf" }
+            (Language::Python, UnaryOperation::Erfc) => { self.scipy = true; "special.erfc" }
+            (language, op_type) => return Err(AppError::MathError(format!("{op_type:?} isn't available in {language:?}"))),
+        };
+
+        Ok(name)
+    }
+
+    /// A call of a function, or of a method of the argument in Rust
+    fn call(&mut self, name: &str, argument: &Node) -> error::Result<(String, Precedence)> {
+        if self.language == Language::Rust {
+            Ok((format!("{}.{name}()", self.receiver(argument)?), Precedence::Atom))
+        } else {
+            Ok((format!("{name}({})", self.expression(argument)?.0), Precedence::Atom))
+        }
+    }
+
+    /// The value a method is called on in Rust, the literals need the type to have methods
+    fn receiver(&mut self, root: &Node) -> error::Result<String> {
+        match self.operand(root, Precedence::Atom)? {
+            code if code.starts_with(|c: char| c.is_ascii_digit()) => Ok(format!("{code}_f64")),
+            code if code.starts_with("(-") && code[2..].starts_with(|c: char| c.is_ascii_digit()) => Ok(format!("{}_f64)", &code[..code.len() - 1])),
+            code => Ok(code),
+        }
+    }
+
+    /// x! is Γ(x+1)
+    fn factorial(&mut self, child: &Node) -> error::Result<(String, Precedence)> {
+        let (argument, _) = self.expression(child)?;
+
+        match self.language {
+            Language::C => Ok((format!("tgamma({argument} + 1.0)"), Precedence::Atom)),
+            Language::Python => {
+                self.scipy = true;
+                Ok((format!("special.gamma({argument} + 1.0)"), Precedence::Atom))
+            }
+            language => Err(AppError::MathError(format!("The factorial isn't available in {language:?}"))),
+        }
+    }
+
+    /// The small integer powers of a symbol are expanded into products, e^x is exp(x) and the rest use the power of the language
+    fn power(&mut self, base: &Node, exponent: &Node) -> error::Result<(String, Precedence)> {
+        let symbol = matches!(base, Node::Unknown { .. } | Node::Variable { .. });
+        match integer_exponent(exponent) {
+            Some(0) => return Ok(self.constant(1.0)),
+            Some(1) => return self.expression(base),
+            Some(n) if symbol && n.abs() <= MAX_EXPANDED_POWER => {
+                let factor = self.expression(base)?.0;
+                let product = vec![factor; n.unsigned_abs() as usize].join(" * ");
+                return if n > 0 {
+                    Ok((product, Precedence::Product))
+                } else {
+                    Ok((format!("{} / ({product})", self.constant(1.0).0), Precedence::Product))
+                };
+            }
+            Some(n) if self.language == Language::Rust => return Ok((format!("{}.powi({n})", self.receiver(base)?), Precedence::Atom)),
+            _ => (),
+        }
+
+        if matches!(base, Node::Constant { value } if *value == E) {
+            let name = match self.language {
+                Language::Rust => "exp",
+                Language::C => "exp",
+                Language::JavaScript => "Math.exp",
+                Language::Python => "np.exp",
+            };
+            return self.call(name, exponent);
+        }
+
+        match self.language {
+            Language::Rust => Ok((format!("{}.powf({})", self.receiver(base)?, self.expression(exponent)?.0), Precedence::Atom)),
+            Language::C => Ok((format!("pow({}, {})", self.expression(base)?.0, self.expression(exponent)?.0), Precedence::Atom)),
+            Language::JavaScript => Ok((format!("Math.pow({}, {})", self.expression(base)?.0, self.expression(exponent)?.0), Precedence::Atom)),
+            //** binds tighter than the minus of its base, and it's right associative
+            Language::Python => {
+                let base = self.operand(base, Precedence::Atom)?;
+                let exponent = self.operand(exponent, Precedence::Unary)?;
+                Ok((format!("{base} ** {exponent}"), Precedence::Power))
+            }
+        }
+    }
+}
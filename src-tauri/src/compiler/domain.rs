@@ -0,0 +1,134 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::parser::{simplify_tree, BinaryOperation, NAryOperation, Node, UnaryOperation};
+
+/// What a part of an expression must be for the expression to be defined, the shader receives it as a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Requirement {
+    Positive = 0,
+    NonNegative = 1,
+    NonZero = 2,
+}
+
+/// A condition of the domain of an expression, like x≥0 for sqrt(x)
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub expr: Node,
+    pub requirement: Requirement,
+}
+
+impl Requirement {
+    pub fn holds(&self, value: f64) -> bool {
+        match self {
+            Self::Positive => value > 0.0,
+            Self::NonNegative => value >= 0.0,
+            Self::NonZero => value != 0.0,
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self.requirement {
+            Requirement::Positive => ">",
+            Requirement::NonNegative => "≥",
+            Requirement::NonZero => "≠",
+        };
+
+        write!(f, "{} {symbol} 0", to_text(&self.expr, 0))
+    }
+}
+
+/// The conditions for an expression to be defined: the arguments of roots and logarithms, the denominators and the bases of powers.
+/// The conditions that always hold are left out
+pub fn domain(root: &Node, variable_map: &HashMap<String, f64>) -> Vec<Condition> {
+    let mut conditions = Vec::new();
+    collect_conditions(root, variable_map, &mut conditions);
+    conditions
+}
+
+fn collect_conditions(root: &Node, variable_map: &HashMap<String, f64>, conditions: &mut Vec<Condition>) {
+    let mut require = |expr: Node, requirement: Requirement| add_condition(Condition { expr, requirement }, variable_map, conditions);
+
+    match root {
+        Node::Unary { op_type, child: Some(child) } => {
+            match op_type {
+                UnaryOperation::Sqrt | UnaryOperation::Fact => require(*child.to_owned(), Requirement::NonNegative),
+                UnaryOperation::Ln | UnaryOperation::Log => require(*child.to_owned(), Requirement::Positive),
+                UnaryOperation::Tan => require(Node::op(UnaryOperation::Cos, *child.to_owned()), Requirement::NonZero),
+                _ => (),
+            }
+
+            collect_conditions(child, variable_map, conditions);
+        }
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => {
+            match op_type {
+                BinaryOperation::Division => require(*rhs.to_owned(), Requirement::NonZero),
+                //The even roots are the powers with a fractional exponent, the odd ones aren't defined by pow either
+                BinaryOperation::Power => match constant(rhs, variable_map) {
+                    Some(e) if e.fract() == 0.0 && e < 0.0 => require(*lhs.to_owned(), Requirement::NonZero),
+                    Some(e) if e.fract() == 0.0 => (),
+                    Some(e) if e > 0.0 => require(*lhs.to_owned(), Requirement::NonNegative),
+                    _ => require(*lhs.to_owned(), Requirement::Positive),
+                },
+                BinaryOperation::Equal => (),
+            }
+
+            collect_conditions(lhs, variable_map, conditions);
+            collect_conditions(rhs, variable_map, conditions);
+        }
+        Node::NAry { children, .. } => children.iter().for_each(|e| collect_conditions(e, variable_map, conditions)),
+        _ => (),
+    }
+}
+
+fn add_condition(condition: Condition, variable_map: &HashMap<String, f64>, conditions: &mut Vec<Condition>) {
+    if constant(&condition.expr, variable_map).is_some_and(|v| condition.requirement.holds(v)) {
+        return;
+    }
+
+    let text = condition.to_string();
+    if conditions.iter().all(|c| c.to_string() != text) {
+        conditions.push(condition);
+    }
+}
+
+fn constant(root: &Node, variable_map: &HashMap<String, f64>) -> Option<f64> {
+    let mut root = root.clone();
+    simplify_tree(&mut root, variable_map);
+    simplify_tree(&mut root, variable_map)
+}
+
+/// Writes the expression in plain text, with parentheses only where the precedence needs them
+fn to_text(root: &Node, precedence: u8) -> String {
+    let wrap = |text: String, own: u8| if own < precedence { format!("({text})") } else { text };
+
+    match root {
+        Node::Constant { value } => wrap(format!("{value}"), if *value < 0.0 { 1 } else { 4 }),
+        Node::Variable { name } | Node::Unknown { name } => name.to_owned(),
+        Node::Unary { op_type: UnaryOperation::Minus, child: Some(child) } => wrap(format!("-{}", to_text(child, 2)), 1),
+        Node::Unary { op_type: UnaryOperation::Fact, child: Some(child) } => format!("{}!", to_text(child, 4)),
+        Node::Unary { op_type, child: Some(child) } => format!("{}({})", format!("{op_type:?}").to_lowercase(), to_text(child, 0)),
+        Node::Binary { op_type: BinaryOperation::Division, lhs: Some(lhs), rhs: Some(rhs) } => {
+            wrap(format!("{}/{}", to_text(lhs, 2), to_text(rhs, 3)), 2)
+        }
+        Node::Binary { op_type: BinaryOperation::Power, lhs: Some(lhs), rhs: Some(rhs) } => {
+            wrap(format!("{}^{}", to_text(lhs, 4), to_text(rhs, 4)), 3)
+        }
+        Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } => {
+            wrap(format!("{} = {}", to_text(lhs, 0), to_text(rhs, 0)), 0)
+        }
+        Node::NAry { op_type: NAryOperation::Add, children } => {
+            let text = children.iter().enumerate().fold(String::new(), |text, (i, child)| match child.as_ref() {
+                Node::Unary { op_type: UnaryOperation::Minus, child: Some(c) } if i > 0 => format!("{text} - {}", to_text(c, 2)),
+                child if i > 0 => format!("{text} + {}", to_text(child, 1)),
+                child => to_text(child, 1),
+            });
+            wrap(text, 1)
+        }
+        Node::NAry { op_type: NAryOperation::Multiply, children } => {
+            wrap(children.iter().map(|e| to_text(e, 2)).collect::<Vec<_>>().join("·"), 2)
+        }
+        _ => format!("{root}"),
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::{error::{self, AppError}, parser::{BinaryOperation, NAryOperation, Node, UnaryOperation}};
+
+use super::{ast_unknowns, domain::{domain, Requirement}, integer_exponent};
+
+/// The result of an instruction, it's the index of the instruction in the function
+pub type Value = usize;
+
+/// An instruction of the intermediate representation. Every value is assigned once and the operands are computed before it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Const(f64),
+    X,
+    Y,
+    Add(Value, Value),
+    Mul(Value, Value),
+    /// The sign of the denominator is masked so the curve isn't joined across it
+    Div(Value, Value),
+    Pow(Value, Value),
+    /// Floor and ceil mask the parity of their result, so the curve isn't joined across their jumps
+    Unary(UnaryOperation, Value),
+}
+
+/// An expression lowered to SSA form, the GLSL string and the bytecode are both emitted from it so they compute the same thing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub instructions: Vec<Op>,
+    /// The value whose sign is the side of the curve, f(x)-y or lhs-rhs
+    pub result: Value,
+    /// The values that must fulfil a requirement for the expression to be defined
+    pub requirements: Vec<(Value, Requirement)>,
+}
+
+/// The instructions with the constants by their bits, so the equal instructions have the same key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Const(u64),
+    X,
+    Y,
+    Add(Value, Value),
+    Mul(Value, Value),
+    Div(Value, Value),
+    Pow(Value, Value),
+    Unary(UnaryOperation, Value),
+}
+
+/// Lowers the drawn expression and the conditions of its domain, then folds the constants and removes the repeated and unused instructions
+pub fn lower(root: &Node, variable_map: &HashMap<String, f64>) -> error::Result<Function> {
+    let unknowns = ast_unknowns(root)?;
+    if unknowns == (false, false) {
+        return Err(AppError::MathError("This equation doesn't have any unknowns".to_owned()));
+    }
+
+    let mut function = Function { instructions: Vec::new(), result: 0, requirements: Vec::new() };
+    function.result = match root {
+        Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } => {
+            let lhs = function.lower_node(lhs, variable_map)?;
+            let rhs = function.lower_node(rhs, variable_map)?;
+            function.sub(lhs, rhs)
+        }
+        _ => {
+            let f = function.lower_node(root, variable_map)?;
+            let unknown = function.push(if unknowns.0 { Op::Y } else { Op::X });
+            function.sub(f, unknown)
+        }
+    };
+
+    for condition in domain(root, variable_map) {
+        let value = function.lower_node(&condition.expr, variable_map)?;
+        function.requirements.push((value, condition.requirement));
+    }
+
+    function.fold_constants();
+    function.eliminate_common_subexpressions();
+    function.eliminate_dead_code();
+    Ok(function)
+}
+
+impl Op {
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Op::Const(_) | Op::X | Op::Y => Vec::new(),
+            Op::Add(a, b) | Op::Mul(a, b) | Op::Div(a, b) | Op::Pow(a, b) => vec![*a, *b],
+            Op::Unary(_, a) => vec![*a],
+        }
+    }
+
+    fn map_operands(&self, f: impl Fn(Value) -> Value) -> Op {
+        match self {
+            Op::Const(_) | Op::X | Op::Y => self.clone(),
+            Op::Add(a, b) => Op::Add(f(*a), f(*b)),
+            Op::Mul(a, b) => Op::Mul(f(*a), f(*b)),
+            Op::Div(a, b) => Op::Div(f(*a), f(*b)),
+            Op::Pow(a, b) => Op::Pow(f(*a), f(*b)),
+            Op::Unary(op, a) => Op::Unary(op.clone(), f(*a)),
+        }
+    }
+
+    fn key(&self) -> Key {
+        match self {
+            Op::Const(c) => Key::Const(c.to_bits()),
+            Op::X => Key::X,
+            Op::Y => Key::Y,
+            Op::Add(a, b) => Key::Add(*a, *b),
+            Op::Mul(a, b) => Key::Mul(*a, *b),
+            Op::Div(a, b) => Key::Div(*a, *b),
+            Op::Pow(a, b) => Key::Pow(*a, *b),
+            Op::Unary(op, a) => Key::Unary(op.clone(), *a),
+        }
+    }
+}
+
+impl Function {
+    /// The times each value is used by the instructions, the result and the requirements
+    pub fn uses(&self) -> Vec<usize> {
+        let mut uses = vec![0; self.instructions.len()];
+        self.instructions.iter().flat_map(Op::operands).for_each(|v| uses[v] += 1);
+        uses[self.result] += 1;
+        self.requirements.iter().for_each(|(v, _)| uses[*v] += 1);
+        uses
+    }
+
+    fn push(&mut self, op: Op) -> Value {
+        self.instructions.push(op);
+        self.instructions.len() - 1
+    }
+
+    fn sub(&mut self, a: Value, b: Value) -> Value {
+        let minus = self.push(Op::Unary(UnaryOperation::Minus, b));
+        self.push(Op::Add(a, minus))
+    }
+
+    fn lower_node(&mut self, root: &Node, variable_map: &HashMap<String, f64>) -> error::Result<Value> {
+        match root {
+            Node::Constant { value } => Ok(self.push(Op::Const(*value))),
+            Node::Variable { name } => {
+                let v = variable_map.get(name).ok_or(AppError::IoError(format!("There are no variable called {name}")))?;
+                Ok(self.push(Op::Const(*v)))
+            }
+            Node::Unknown { name } => match name.as_str() {
+                "x" => Ok(self.push(Op::X)),
+                "y" => Ok(self.push(Op::Y)),
+                _ => Err(AppError::MathError(format!("There aren't any unknowns called: {name}"))),
+            },
+            Node::Unary { op_type, child } => {
+                let child = child.as_ref().ok_or(AppError::MathError(format!("There is nothing to operate on in {op_type:?}")))?;
+                let child = self.lower_node(child, variable_map)?;
+
+                match op_type {
+                    UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median => Ok(child),
+                    UnaryOperation::Stdev | UnaryOperation::Var => Err(AppError::MathError(format!("The {op_type:?} of a single number isn't defined"))),
+                    //The poles of tan are the zeros of the cosine, which is masked like any denominator
+                    UnaryOperation::Tan => {
+                        let sin = self.push(Op::Unary(UnaryOperation::Sin, child));
+                        let cos = self.push(Op::Unary(UnaryOperation::Cos, child));
+                        Ok(self.push(Op::Div(sin, cos)))
+                    }
+                    op_type => Ok(self.push(Op::Unary(op_type.clone(), child))),
+                }
+            }
+            Node::Binary { op_type, lhs, rhs } => {
+                let lhs = lhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the left to operate on in {op_type:?}")))?;
+                let rhs = rhs.as_ref().ok_or(AppError::MathError(format!("There is nothing in the right to operate on in {op_type:?}")))?;
+
+                match op_type {
+                    BinaryOperation::Division => {
+                        let (lhs, rhs) = (self.lower_node(lhs, variable_map)?, self.lower_node(rhs, variable_map)?);
+                        Ok(self.push(Op::Div(lhs, rhs)))
+                    }
+                    BinaryOperation::Power => {
+                        let base = self.lower_node(lhs, variable_map)?;
+                        if let Some(n) = integer_exponent(rhs) {
+                            return Ok(self.pow_integer(base, n));
+                        }
+
+                        let exponent = self.lower_node(rhs, variable_map)?;
+                        Ok(self.push(Op::Pow(base, exponent)))
+                    }
+                    BinaryOperation::Equal => Err(AppError::MathError("Equal is not an operation in this context".to_owned())),
+                }
+            }
+            Node::NAry { op_type, children } => {
+                if children.len() < 2 {
+                    return Err(AppError::MathError(format!("A {op_type:?} cannot be of less than two terms")));
+                }
+
+                let mut value = self.lower_node(&children[0], variable_map)?;
+                for child in children.iter().skip(1) {
+                    let child = self.lower_node(child, variable_map)?;
+                    value = self.push(match op_type {
+                        NAryOperation::Add => Op::Add(value, child),
+                        NAryOperation::Multiply => Op::Mul(value, child),
+                    });
+                }
+
+                Ok(value)
+            }
+            Node::Matrix { .. } => Err(AppError::MathError("A matrix can't be drawn".to_owned())),
+        }
+    }
+
+    /// The integer powers are products, by squaring the base so x^n takes about log2(n) products
+    fn pow_integer(&mut self, base: Value, n: i32) -> Value {
+        if n < 0 {
+            //-i32::MIN doesn't fit in an i32, its absolute value is unsigned
+            let one = self.push(Op::Const(1.0));
+            let den = self.pow_unsigned(base, n.unsigned_abs());
+            return self.push(Op::Div(one, den));
+        }
+
+        self.pow_unsigned(base, n.unsigned_abs())
+    }
+
+    fn pow_unsigned(&mut self, base: Value, n: u32) -> Value {
+        if n == 0 {
+            return self.push(Op::Const(1.0));
+        } else if n == 1 {
+            return base;
+        }
+
+        let half = self.pow_unsigned(base, n / 2);
+        let square = self.push(Op::Mul(half, half));
+        if n % 2 == 1 { self.push(Op::Mul(base, square)) } else { square }
+    }
+
+    /// Replaces the instructions of constant operands by their result, unless it isn't a finite number
+    fn fold_constants(&mut self) {
+        for i in 0..self.instructions.len() {
+            let constant = |v: Value| match self.instructions[v] {
+                Op::Const(c) => Some(c),
+                _ => None,
+            };
+
+            let folded = match &self.instructions[i] {
+                Op::Add(a, b) => constant(*a).zip(constant(*b)).map(|(a, b)| a + b),
+                Op::Mul(a, b) => constant(*a).zip(constant(*b)).map(|(a, b)| a * b),
+                Op::Div(a, b) => constant(*a).zip(constant(*b)).map(|(a, b)| a / b),
+                Op::Pow(a, b) => constant(*a).zip(constant(*b)).map(|(a, b)| a.powf(b)),
+                Op::Unary(op, a) => constant(*a).zip(op.func().ok()).map(|(a, f)| f(a)),
+                _ => None,
+            };
+
+            if let Some(c) = folded.filter(|c| c.is_finite()) {
+                self.instructions[i] = Op::Const(c);
+            }
+        }
+    }
+
+    /// The instructions equal to a previous one are replaced by it, their uses point to the first one
+    fn eliminate_common_subexpressions(&mut self) {
+        let mut first: HashMap<Key, Value> = HashMap::new();
+        let mut replacement: Vec<Value> = Vec::with_capacity(self.instructions.len());
+
+        for i in 0..self.instructions.len() {
+            let op = self.instructions[i].map_operands(|v| replacement[v]);
+            replacement.push(*first.entry(op.key()).or_insert(i));
+            self.instructions[i] = op;
+        }
+
+        self.result = replacement[self.result];
+        self.requirements.iter_mut().for_each(|(v, _)| *v = replacement[*v]);
+    }
+
+    /// Removes the instructions that the result and the requirements don't use, and the requirements that always hold
+    fn eliminate_dead_code(&mut self) {
+        let instructions = &self.instructions;
+        self.requirements.retain(|(v, requirement)| !matches!(instructions[*v], Op::Const(c) if requirement.holds(c)));
+
+        let mut live = vec![false; self.instructions.len()];
+        live[self.result] = true;
+        self.requirements.iter().for_each(|(v, _)| live[*v] = true);
+        for i in (0..self.instructions.len()).rev() {
+            if live[i] {
+                self.instructions[i].operands().into_iter().for_each(|v| live[v] = true);
+            }
+        }
+
+        let mut replacement = vec![0; self.instructions.len()];
+        let mut instructions = Vec::new();
+        for (i, op) in self.instructions.iter().enumerate() {
+            if live[i] {
+                replacement[i] = instructions.len();
+                instructions.push(op.map_operands(|v| replacement[v]));
+            }
+        }
+
+        self.instructions = instructions;
+        self.result = replacement[self.result];
+        self.requirements.iter_mut().for_each(|(v, _)| *v = replacement[*v]);
+    }
+}
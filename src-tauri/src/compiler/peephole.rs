@@ -0,0 +1,86 @@
+use crate::parser::UnaryOperation;
+
+use super::bytecode::Instruction;
+
+/// The largest integer power that is replaced by products
+const MAX_SQUARED_POWER: i32 = 64;
+
+/// Rewrites the short sequences of the bytecode into cheaper ones that compute the same: the constants are folded,
+/// the integer powers are squarings, a minus before an add is a subtraction and the values pushed and popped are removed
+pub fn optimize(program: &[Instruction]) -> Vec<Instruction> {
+    let mut optimized = Vec::with_capacity(program.len());
+    for instruction in program {
+        emit(&mut optimized, instruction.clone());
+    }
+
+    optimized
+}
+
+/// Adds the instruction and rewrites the end of the program while it matches a rule, so the rewrites that enable others are applied too
+fn emit(program: &mut Vec<Instruction>, instruction: Instruction) {
+    program.push(instruction);
+
+    if let Some((matched, replacement)) = rewrite(program) {
+        program.truncate(program.len() - matched);
+        for instruction in replacement {
+            emit(program, instruction);
+        }
+    }
+}
+
+/// The number of instructions at the end that are replaced and what they're replaced by
+fn rewrite(program: &[Instruction]) -> Option<(usize, Vec<Instruction>)> {
+    match program {
+        [.., Instruction::Push(a), Instruction::Push(b), binary] if fold(*a, *b, binary).is_some() => {
+            fold(*a, *b, binary).map(|c| (3, vec![Instruction::Push(c)]))
+        }
+        [.., Instruction::Push(a), Instruction::UnaryOperation(op)] => {
+            op.func().ok().map(|f| f(*a)).filter(|c| c.is_finite()).map(|c| (2, vec![Instruction::Push(c)]))
+        }
+        [.., Instruction::Push(a), Instruction::Cpy] => Some((2, vec![Instruction::Push(*a), Instruction::Push(*a)])),
+        [.., Instruction::UnaryOperation(UnaryOperation::Minus), Instruction::Add] => Some((2, vec![Instruction::Sub])),
+        [.., Instruction::Push(one), Instruction::Mul] if *one == 1.0 => Some((2, Vec::new())),
+        [.., Instruction::Push(_) | Instruction::PushX | Instruction::PushY | Instruction::Cpy | Instruction::Load(_), Instruction::Pop] => {
+            Some((2, Vec::new()))
+        }
+        [.., Instruction::Push(n), Instruction::Pow] if n.fract() == 0.0 && (0.0..=MAX_SQUARED_POWER as f64).contains(n) => {
+            match *n as i32 {
+                0 => Some((2, vec![Instruction::Pop, Instruction::Push(1.0)])),
+                n => Some((2, power_by_squaring(n))),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The result of a binary operation of two constants, unless it isn't a finite number
+fn fold(a: f64, b: f64, instruction: &Instruction) -> Option<f64> {
+    let folded = match instruction {
+        Instruction::Add => a + b,
+        Instruction::Sub => a - b,
+        Instruction::Mul => a * b,
+        Instruction::Div => a / b,
+        Instruction::Pow => a.powf(b),
+        _ => return None,
+    };
+
+    folded.is_finite().then_some(folded)
+}
+
+/// Raises the value at the top of the stack to n with about log2(n) products, the copies keep the base for the odd exponents
+fn power_by_squaring(n: i32) -> Vec<Instruction> {
+    match n {
+        1 => Vec::new(),
+        n if n % 2 == 0 => {
+            let mut code = power_by_squaring(n / 2);
+            code.extend([Instruction::Cpy, Instruction::Mul]);
+            code
+        }
+        n => {
+            let mut code = vec![Instruction::Cpy];
+            code.extend(power_by_squaring(n - 1));
+            code.push(Instruction::Mul);
+            code
+        }
+    }
+}
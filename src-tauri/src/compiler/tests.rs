@@ -1,92 +1,11 @@
-use super::bytecode::Instruction;
-
-const MAX_STACK_SIZE: usize = 1024;
-const MAX_EXPR: usize = 1024;
-
-/// This has been done so I can debug the code that runs GPU-side more easily 
-#[derive(Debug)]
-struct Interpreter {
-    stack: [f64; MAX_STACK_SIZE],
-    /// One past the last element in the stack
-    stack_top: usize,
-    program_counter: usize,
-    current_expr: usize,
-
-    program: Vec<Instruction>,
-}
-
-impl Interpreter {
-    pub fn new(program: Vec<Instruction>) -> Self {
-        Self {
-            stack: [0.0; MAX_STACK_SIZE],
-            program,
-            stack_top: 0,
-            program_counter: 0,
-            current_expr: 0,
-        }
-    }
-
-    pub fn run(&mut self, x: f64, y: f64) -> [f64; MAX_EXPR] {
-        let mut output = [0.0; MAX_EXPR];
-
-        while self.program_counter < self.program.len() {
-            match &self.program[self.program_counter] {
-                Instruction::Push(val) => self.push(*val),
-                Instruction::PushX => self.push(x),
-                Instruction::PushY => self.push(y),
-                Instruction::Cpy => self.push(self.stack[self.stack_top-1]),
-
-                Instruction::Ret => { self.current_expr += 1; },
-                Instruction::Store => {
-                    output[self.current_expr] = self.stack[self.stack_top-1];
-                    self.pop();
-                }
-                Instruction::Pop => {
-                    self.pop();
-                }
-
-                Instruction::Add => self.binary_op(|a, b| a + b),
-                Instruction::Mul => self.binary_op(|a, b| a * b),
-                Instruction::Div => self.binary_op(|a, b| a / b),
-                Instruction::Pow => self.binary_op(|a, b| f64::powf(a, b)),
-                
-                Instruction::UnaryOperation(op) => self.unary_op(op.func().unwrap()),
-            }
-    
-            self.program_counter += 1;
-        }
-
-        output
-    }
-
-    fn push(&mut self, val: f64) {
-        self.stack[self.stack_top] = val;
-        self.stack_top += 1;
-    }
-
-    fn pop(&mut self) -> f64 {
-        let val = self.stack[self.stack_top-1];
-        self.stack_top -= 1;
-        val
-    }
-
-    fn binary_op(&mut self, op: fn(f64, f64) -> f64) {
-        let b = self.pop();
-        let a = self.pop();
-        self.push(op(a, b));
-    }
-
-    fn unary_op(&mut self, op: fn(f64) -> f64) {
-        let a = self.pop();
-        self.push(op(a));
-    }
-}
-
 #[cfg(test)]
 mod test {
-    use crate::{compiler::bytecode::{compile_to_bytecode, Instruction}, error, parser::{parse_latex, UnaryOperation}};
+    use crate::{analysis::evaluate_at, compiler::{assembly::{assemble, disassemble}, bytecode::{compile_to_bytecode, emit_bytecode, Instruction}, codegen::{generate_code, Language}, compile_to_string, domain::domain, ir::{lower, Function, Op}, peephole::optimize, verifier::{verify, MAX_EXPRESSIONS, MAX_LOCALS, MAX_MASKS, MAX_STACK_SIZE}, vm::{Evaluation, Precision, Vm, VmError, MAX_GRID_POINTS}}, error, parser::{parse_latex, BinaryOperation, Node, UnaryOperation}};
 
-    use super::Interpreter;
+    /// The values of the expressions of the program in (x, y), computed in f64
+    fn run(program: &[Instruction], x: f64, y: f64) -> Vec<Evaluation> {
+        Vm::new(Precision::F64).run(program, x, y).unwrap()
+    }
 
     #[test]
     fn basic_operations() {
@@ -101,10 +20,7 @@ mod test {
             Instruction::Ret,
         ];
 
-        let mut int = Interpreter::new(program);
-
-        let res = int.run(0.0, 0.0)[0];
-        println!("{}", int.stack_top);
+        let res = run(&program, 0.0, 0.0)[0].value;
         assert!(res == 22.0);
     }
 
@@ -122,10 +38,7 @@ mod test {
             Instruction::Ret,
         ];
 
-        let mut int = Interpreter::new(program);
-
-        let res = int.run(0.0, 0.0)[0];
-        println!("{}", int.stack_top);
+        let res = run(&program, 0.0, 0.0)[0].value;
         assert!(res == 1.0);
     }
 
@@ -151,12 +64,9 @@ mod test {
             Instruction::Ret,
         ];
 
-        let mut int = Interpreter::new(program);
-
-        let res = int.run(0.0, 0.0);
-        println!("{}", int.stack_top);
-        assert_eq!(res[0], 1.0);
-        assert_eq!(res[1], 22.0);
+        let res = run(&program, 0.0, 0.0);
+        assert_eq!(res[0].value, 1.0);
+        assert_eq!(res[1].value, 22.0);
     }
 
     #[test]
@@ -164,14 +74,317 @@ mod test {
         //In this text there's no need to simplify
         let tree = parse_latex("x^2", &Default::default())?;
         
-        let mut program = compile_to_bytecode(&tree, &Default::default(), 0)?;
+        let mut program = compile_to_bytecode(&tree, &Default::default())?;
         program.push(Instruction::Ret);
 
         for i in 1..100 {
-            let a = Interpreter::new(program.clone()).run(i as f64, 0.0)[0];
+            let a = run(&program, i as f64, 0.0)[0].value;
             assert_eq!(a, (i*i) as f64);
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn probability_functions() -> error::Result<()> {
+        let tree = parse_latex("\\operatorname{erf}\\left(x\\right)+x!", &Default::default())?;
+
+        let mut program = compile_to_bytecode(&tree, &Default::default())?;
+        program.push(Instruction::Ret);
+
+        for i in 0..10 {
+            let a = run(&program, i as f64, 0.0)[0].value;
+            assert_eq!(a, UnaryOperation::erf(i as f64) + UnaryOperation::factorial(i as f64));
+        }
+
+        //The factorial of a negative number is NaN, like in the shader, and out of the domain
+        assert!(UnaryOperation::factorial(-2.5).is_nan());
+        let evaluation = run(&program, -2.5, 0.0)[0];
+        assert!(evaluation.value.is_nan() && !evaluation.defined);
+
+        Ok(())
+    }
+
+    #[test]
+    fn domain_masking() -> error::Result<()> {
+        let tree = parse_latex("\\sqrt{x}+\\ln\\left(x-1\\right)+\\frac{1}{x-2}", &Default::default())?;
+        let mut conditions: Vec<String> = domain(&tree, &Default::default()).iter().map(|c| c.to_string()).collect();
+        conditions.sort();
+        assert_eq!(conditions, ["x - 1 > 0", "x - 2 ≠ 0", "x ≥ 0"]);
+
+        //The undefined points of the bytecode are masked like in the shader
+        let program = compile_to_bytecode(&tree, &Default::default())?;
+        assert!(!run(&program, 0.5, 0.0)[0].defined);
+        assert!(!run(&program, -1.0, 0.0)[0].defined);
+        assert!(run(&program, 4.0, 0.0)[0].defined);
+        assert!(compile_to_string(&tree, &Default::default(), 0)?.contains("fdefined("));
+
+        let conditions = |latex: &str| -> error::Result<Vec<String>> {
+            Ok(domain(&parse_latex(latex, &Default::default())?, &Default::default()).iter().map(|c| c.to_string()).collect())
+        };
+        assert_eq!(conditions("x^{0.5}")?, ["x ≥ 0"]);
+        assert_eq!(conditions("x^{-2}")?, ["x ≠ 0"]);
+        assert_eq!(conditions("\\sqrt{2}x^2+e^x")?, Vec::<String>::new());
+        assert_eq!(conditions("\\tan\\left(2x\\right)")?, ["cos(2·x) ≠ 0"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn floor_jumps() -> error::Result<()> {
+        //The results of floor and ceil are stored and their parity is masked like the sign of the denominators
+        let tree = parse_latex("\\operatorname{floor}\\left(x\\right)+\\frac{1}{\\operatorname{ceil}\\left(x\\right)}", &Default::default())?;
+        let code = compile_to_string(&tree, &Default::default(), 3)?;
+        assert!(code.contains("float var_3_0 = ffloor(x);") && code.contains("ret.y |= int(fodd(var_3_0));"));
+        assert!(code.contains("float var_3_1 = fceil(x);") && code.contains("ret.y |= int(fodd(var_3_1));"));
+        assert!(code.contains("float var_3_2 = var_3_1;") && code.contains("ret.y |= int(fneg(var_3_2));"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn common_subexpressions() -> error::Result<()> {
+        let tree = parse_latex("\\tan\\left(x^{2}+1\\right)+\\left(x^{2}+1\\right)^{3}+\\sqrt{x^{2}+1}", &Default::default())?;
+        let code = compile_to_string(&tree, &Default::default(), 0)?;
+
+        //The repeated x^2+1 is computed once, the argument of tan and the base of the power use the temporary
+        assert_eq!(code.matches("fadd(fmul(x, x), float(1))").count(), 1);
+        assert!(code.contains("float t_0_0 = fadd(fmul(x, x), float(1));"));
+        assert!(code.contains("fdiv( fsin(t_0_0), var_0_0 )") && code.contains("fmul(t_0_0, fmul(t_0_0, t_0_0))"));
+        assert!(code.contains("fdefined(t_0_0, 1)"));
+
+        let tree = parse_latex("\\left(\\sin\\left(x\\right)+1\\right)^{4}", &Default::default())?;
+        let code = compile_to_string(&tree, &Default::default(), 1)?;
+        assert!(code.contains("float t_1_0 = fadd(fsin(x), float(1));") && code.contains("float t_1_1 = fmul(t_1_0, t_1_0);"));
+        assert!(code.contains("fmul(t_1_1, t_1_1)"));
+
+        //The shared -x is a temporary, so the subtractions add it instead of being fused
+        let tree = parse_latex("\\left(1-x\\right)\\left(2-x\\right)", &Default::default())?;
+        let code = compile_to_string(&tree, &Default::default(), 2)?;
+        assert!(code.contains("float t_2_0 = fminus(x);") && code.matches("t_2_0").count() == 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn intermediate_representation() -> error::Result<()> {
+        let lowered = |latex: &str| -> error::Result<Function> { lower(&parse_latex(latex, &Default::default())?, &Default::default()) };
+
+        //2·3 is folded, the second sin(x) is the first one and the unused constants are removed
+        let function = lowered("2\\cdot 3\\sin\\left(x\\right)+\\sin\\left(x\\right)")?;
+        assert_eq!(function.instructions, [
+            Op::Const(6.0), Op::X, Op::Unary(UnaryOperation::Sin, 1), Op::Mul(0, 2), Op::Add(3, 2),
+            Op::Y, Op::Unary(UnaryOperation::Minus, 5), Op::Add(4, 6),
+        ]);
+        assert_eq!(function.result, 7);
+
+        //The requirements that always hold aren't checked
+        assert!(lowered("\\sqrt{4}+\\sqrt{x}")?.requirements.len() == 1);
+
+        //The bytecode computes the same as the tree, with the shared values kept in locals
+        for latex in ["\\tan\\left(x^{2}+1\\right)+\\left(x^{2}+1\\right)^{3}", "x^{-2}+x^{5}", "\\frac{\\sin\\left(x\\right)}{\\sin\\left(x\\right)+2}"] {
+            let tree = parse_latex(latex, &Default::default())?;
+            let program = compile_to_bytecode(&tree, &Default::default())?;
+            assert!(program.iter().all(|i| !matches!(i, Instruction::UnaryOperation(UnaryOperation::Tan))));
+            for x in [0.3, 1.7, 2.5] {
+                let (a, b) = (run(&program, x, 0.0)[0].value, evaluate_at(&tree, x, &Default::default()).unwrap());
+                assert!((a - b).abs() < 1e-9 * b.abs().max(1.0), "{latex} is {a} in {x}, expected {b}");
+            }
+        }
+        let program = compile_to_bytecode(&parse_latex("\\tan\\left(x^{2}+1\\right)", &Default::default())?, &Default::default())?;
+        assert!(program.contains(&Instruction::Save(0)) && program.contains(&Instruction::Load(0)));
+
+        //The opposite of i32::MIN doesn't fit in an i32
+        let power = Node::Binary {
+            op_type: BinaryOperation::Power,
+            lhs: Some(Box::new(Node::Unknown { name: "x".to_owned() })),
+            rhs: Some(Box::new(Node::Constant { value: i32::MIN as f64 })),
+        };
+        let program = compile_to_bytecode(&power, &Default::default())?;
+        assert_eq!(run(&program, 1.0, 0.0)[0].value, 1.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn peephole_optimizer() -> error::Result<()> {
+        use Instruction::*;
+        let sqrt = || Instruction::UnaryOperation(crate::parser::UnaryOperation::Sqrt);
+        let minus = || Instruction::UnaryOperation(crate::parser::UnaryOperation::Minus);
+
+        let same = |a: f64, b: f64| a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() < 1e-9 * a.abs().max(1.0);
+        let assert_equivalent = |program: &[Instruction], optimized: &[Instruction]| {
+            for (x, y) in [(0.0, 0.0), (0.3, -1.2), (1.7, 2.0), (-2.5, 0.5), (4.0, -3.0)] {
+                let (a, b) = (run(program, x, y), run(optimized, x, y));
+                let equal = a.iter().zip(&b).all(|(a, b)| same(a.value, b.value) && a.defined == b.defined && a.masks == b.masks);
+                assert!(a.len() == b.len() && equal, "{program:?} isn't {optimized:?} in ({x}, {y})");
+            }
+        };
+
+        //The naive sequences and what they become
+        let cases = [
+            (vec![Push(2.0), Push(3.0), Add, PushX, Mul], vec![Push(5.0), PushX, Mul]),
+            (vec![Push(4.0), sqrt(), Push(2.0), Pow], vec![Push(4.0)]),
+            (vec![PushX, Push(0.0), Pow], vec![Push(1.0)]),
+            (vec![PushX, Push(2.0), Pow], vec![PushX, Cpy, Mul]),
+            (vec![PushX, Push(5.0), Pow], vec![PushX, Cpy, Cpy, Mul, Cpy, Mul, Mul]),
+            (vec![PushX, PushY, minus(), Add], vec![PushX, PushY, Sub]),
+            (vec![PushX, Push(1.0), Mul, PushY, Cpy, Pop, Add], vec![PushX, PushY, Add]),
+            (vec![Push(-1.0), sqrt(), PushX, Push(0.5), Pow, Add], vec![Push(-1.0), sqrt(), PushX, Push(0.5), Pow, Add]),
+        ];
+        for (program, expected) in cases {
+            let program: Vec<Instruction> = program.into_iter().chain([Store, Ret]).collect();
+            let optimized = optimize(&program);
+            assert_eq!(optimized, expected.into_iter().chain([Store, Ret]).collect::<Vec<_>>());
+            assert_equivalent(&program, &optimized);
+        }
+
+        //The compiled expressions compute the same with and without the optimizations
+        let expressions = [
+            "x^{2}+y^{2}=4", "\\sin\\left(x\\right)^{3}-\\frac{1}{x-y}", "\\left(x+1\\right)^{7}-2^{3}y", "\\sqrt{x}+\\ln\\left(y+5\\right)",
+            "\\tan\\left(x\\cdot 2\\right)+\\operatorname{floor}\\left(x\\right)y", "x^{y}-\\left(2\\cdot 3+1\\right)x^{-3}",
+        ];
+        for latex in expressions {
+            let function = lower(&parse_latex(latex, &Default::default())?, &Default::default())?;
+            let program = emit_bytecode(&function)?;
+            let optimized = optimize(&program);
+            assert!(optimized.len() <= program.len() && !optimized.windows(2).any(|w| w == [minus(), Add]));
+            assert_equivalent(&program, &optimized);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn bytecode_verifier() -> error::Result<()> {
+        use Instruction::*;
+
+        assert_eq!(verify(&[PushX, Cpy, Mul, PushY, Sub, Store, Ret])?, 2);
+        assert_eq!(verify(&[PushX, UnaryOperation(crate::parser::UnaryOperation::Tan), Store, Ret])?, 2);
+        assert_eq!(verify(&compile_to_bytecode(&parse_latex("\\frac{x^{2}+y^{2}}{x-1}=4", &Default::default())?, &Default::default())?)?, 3);
+
+        //Programs that the shader can't run
+        let invalid = [
+            vec![PushX, Add, Store, Ret],
+            vec![PushX, Store],
+            vec![Load(0), Store, Ret],
+            vec![PushX, Save(MAX_LOCALS), Store, Ret],
+            vec![Push(1e300), Store, Ret],
+            vec![PushX, UnaryOperation(crate::parser::UnaryOperation::Det), Store, Ret],
+            [vec![PushX; MAX_STACK_SIZE + 1], vec![Store, Ret]].concat(),
+            [vec![PushX], vec![vec![PushX, Div]; MAX_MASKS + 1].concat(), vec![Store, Ret]].concat(),
+            vec![vec![PushX, Store, Ret]; MAX_EXPRESSIONS + 1].concat(),
+        ];
+        for program in invalid {
+            assert!(verify(&program).is_err(), "{program:?} should be rejected");
+        }
+
+        //The locals are saved in each expression
+        assert!(verify(&[PushX, Save(0), Store, Ret, Load(0), Store, Ret]).is_err());
+        assert!(compile_to_string(&parse_latex("x^{2}", &Default::default())?, &Default::default(), MAX_EXPRESSIONS).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn virtual_machine() -> error::Result<()> {
+        use Instruction::*;
+
+        //The masks are the sign of the denominator and the parity of floor, in the order they are computed
+        let program = compile_to_bytecode(&parse_latex("\\frac{1}{x}+\\operatorname{floor}\\left(y\\right)", &Default::default())?, &Default::default())?;
+        assert_eq!(run(&program, -2.0, 3.5)[0].masks, 0b11);
+        assert_eq!(run(&program, 2.0, 2.5)[0].masks, 0b00);
+
+        //The f32 arithmetic rounds like the shader does
+        let program = [Push(0.1), PushX, Add, Store, Ret];
+        assert_eq!(run(&program, 0.2, 0.0)[0].value, 0.1 + 0.2);
+        assert_eq!(Vm::new(Precision::F32).run(&program, 0.2, 0.0).unwrap()[0].value, (0.1f32 + 0.2f32) as f64);
+
+        //The grid is evaluated row by row
+        let program = [PushX, PushY, Sub, Store, Ret];
+        let grid = Vm::new(Precision::F64).run_grid(&program, &[0.0, 1.0, 2.0], &[10.0, 20.0]).unwrap();
+        let values: Vec<f64> = grid.iter().map(|e| e[0].value).collect();
+        assert_eq!(values, [-10.0, -9.0, -8.0, -20.0, -19.0, -18.0]);
+        let row = vec![0.0; MAX_GRID_POINTS / 2 + 1];
+        assert_eq!(Vm::new(Precision::F64).run_grid(&program, &row, &[1.0, 2.0]), Err(VmError::TooManyPoints));
+
+        //The programs that the shader can't run are errors instead of panics
+        let errors = [
+            (vec![PushX, Add, Store, Ret], VmError::StackUnderflow { pc: 1 }),
+            (vec![Load(0), Store, Ret], VmError::UnsavedLocal { pc: 0, local: 0 }),
+            (vec![PushX, Save(MAX_LOCALS), Store, Ret], VmError::InvalidLocal { pc: 1, local: MAX_LOCALS }),
+            (vec![PushX, UnaryOperation(crate::parser::UnaryOperation::Det), Store, Ret], VmError::InvalidOperation { pc: 1, op: crate::parser::UnaryOperation::Det }),
+            ([vec![PushX; MAX_STACK_SIZE + 1], vec![Store, Ret]].concat(), VmError::StackOverflow { pc: MAX_STACK_SIZE }),
+            (vec![vec![PushX, Store, Ret]; MAX_EXPRESSIONS + 1].concat(), VmError::TooManyExpressions),
+        ];
+        for (program, error) in errors {
+            assert_eq!(Vm::new(Precision::F64).run(&program, 1.0, 1.0), Err(error));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn assembly_round_trip() -> error::Result<()> {
+        use Instruction::*;
+
+        let source = "expr_0:\n    push_x\n    save 0\n    load 0\n    mul\n    push 0.1\n    sub\n    store\n    push_x\n    require non_zero\n    ret\nexpr_1:\n    push_y\n    sqrt\n    store\n    ret\n";
+        let program = assemble(source)?;
+        assert_eq!(program[..5], [PushX, Save(0), Load(0), Mul, Push(0.1)]);
+        assert_eq!(disassemble(&program)?, source);
+
+        //The comments, the labels and the indentation are optional
+        assert_eq!(assemble("; x+1\npush_x\n push 1 ; one\nadd\nstore\nret")?, [PushX, Push(1.0), Add, Store, Ret]);
+        for invalid in ["push", "push x", "load -1", "require even", "add 2", "det", "push_z"] {
+            assert!(assemble(invalid).is_err(), "{invalid} should be rejected");
+        }
+
+        //The compiled expressions are the same after being printed and read, or encoded and decoded
+        for latex in ["\\frac{\\sin\\left(x\\right)}{x-1}+\\sqrt{y}", "\\tan\\left(x^{2}+1\\right)+\\left(x^{2}+1\\right)^{3}", "\\operatorname{floor}\\left(x\\right)^{-2.5}=\\ln\\left(y\\right)"] {
+            let program = compile_to_bytecode(&parse_latex(latex, &Default::default())?, &Default::default())?;
+            assert_eq!(assemble(&disassemble(&program)?)?, program);
+
+            let decoded: error::Result<Vec<Instruction>> = program.iter()
+                .map(|i| i.to_number_pair().and_then(|(op_code, operand)| Instruction::from_number_pair(op_code, operand)))
+                .collect();
+            assert_eq!(decoded?, program);
+        }
+
+        //Every opcode is decoded into the instruction that encodes to it
+        for op_code in 0..=u8::MAX {
+            if let Ok(instruction) = Instruction::from_number_pair(op_code, 1.0) {
+                assert_eq!(instruction.to_number_pair()?.0, op_code);
+            }
+        }
+        assert!(Instruction::from_number_pair(7, 3.0).is_err() && Instruction::from_number_pair(8, 0.5).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_generation() -> error::Result<()> {
+        let tree = parse_latex("\\frac{x^{3}-2}{\\left(x+1\\right)^{5}}+\\sin\\left(-\\pi x\\right)e^{x}+x^{-2}", &Default::default())?;
+        let code = |language| generate_code(&tree, "f", language, &Default::default());
+
+        assert_eq!(code(Language::Rust)?, "fn f(x: f64) -> f64 {\n    (x * x * x - 2.0) / (x + 1.0).powi(5) + (-x * std::f64::consts::PI).sin() * x.exp() + 1.0 / (x * x)\n}\n");
+        assert_eq!(code(Language::C)?, "#include <math.h>\n\ndouble f(double x) {\n    return (x * x * x - 2.0) / pow(x + 1.0, 5.0) + sin(-x * M_PI) * exp(x) + 1.0 / (x * x);\n}\n");
+        assert_eq!(code(Language::JavaScript)?, "function f(x) {\n    return (x * x * x - 2.0) / Math.pow(x + 1.0, 5.0) + Math.sin(-x * Math.PI) * Math.exp(x) + 1.0 / (x * x);\n}\n");
+        assert_eq!(code(Language::Python)?, "import numpy as np\n\n\ndef f(x):\n    return (x * x * x - 2.0) / (x + 1.0) ** 5.0 + np.sin(-x * np.pi) * np.exp(x) + 1.0 / (x * x)\n");
+
+        let expression = |latex: &str, language| -> error::Result<String> {
+            let code = generate_code(&parse_latex(latex, &Default::default())?, "f", language, &Default::default())?;
+            Ok(code.lines().rev().find(|l| !l.trim().is_empty() && l.trim() != "}").unwrap_or_default().trim().to_owned())
+        };
+        assert_eq!(expression("\\frac{y}{2x}", Language::C)?, "return y / (x * 2.0);");
+        assert_eq!(expression("-\\left(x-y\\right)^{1.5}", Language::Python)?, "return -(x - y) ** 1.5");
+        assert_eq!(expression("\\left(-y\\right)^{x}", Language::Rust)?, "(-y).powf(x)");
+        assert_eq!(expression("2^{\\sqrt{x}}", Language::Rust)?, "2.0_f64.powf(x.sqrt())");
+        assert_eq!(expression("x^{2}+y^{2}=1", Language::JavaScript)?, "return x * x + y * y - 1.0;");
+        assert_eq!(expression("x!", Language::C)?, "return tgamma(x + 1.0);");
+        assert!(generate_code(&parse_latex("\\operatorname{erf}\\left(x\\right)", &Default::default())?, "f", Language::Python, &Default::default())?
+            .starts_with("import numpy as np\nfrom scipy import special\n"));
+        assert!(expression("\\operatorname{erf}\\left(x\\right)", Language::JavaScript).is_err());
+
+        Ok(())
+    }
+}
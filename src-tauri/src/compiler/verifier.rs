@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::{error::{self, AppError}, parser::UnaryOperation};
+
+use super::bytecode::Instruction;
+
+/// The size of the stack of the shader interpreter
+pub const MAX_STACK_SIZE: usize = 128;
+/// The expressions that can be drawn at the same time
+pub const MAX_EXPRESSIONS: usize = 32;
+/// The locals of the shader, where the values used more than once are kept
+pub const MAX_LOCALS: usize = 32;
+/// The denominators, floors and ceils of an expression, each one is a bit of an int in the shader
+pub const MAX_MASKS: usize = 32;
+
+/// The limits of the shaders, the frontend writes them in the shader source so they are only defined here
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ShaderLimits {
+    pub max_stack_size: usize,
+    pub max_expressions: usize,
+    pub max_locals: usize,
+    pub max_masks: usize,
+}
+
+pub const SHADER_LIMITS: ShaderLimits = ShaderLimits {
+    max_stack_size: MAX_STACK_SIZE,
+    max_expressions: MAX_EXPRESSIONS,
+    max_locals: MAX_LOCALS,
+    max_masks: MAX_MASKS,
+};
+
+/// Checks the program as the shader runs it: the stack never underflows nor grows past its size, the locals are saved
+/// before they are loaded, the operands fit in the texture and every expression ends with a Ret.
+/// It's the maximum depth of the stack
+pub fn verify(program: &[Instruction]) -> error::Result<usize> {
+    if program.last() != Some(&Instruction::Ret) {
+        return Err(AppError::MathError("The bytecode must end with a ret".to_owned()));
+    }
+
+    let mut max_depth = 0;
+    let mut depth = 0;
+    let mut masks = 0;
+    let mut saved = [false; MAX_LOCALS];
+    let mut expressions = 0;
+    for (pc, instruction) in program.iter().enumerate() {
+        let (pops, pushes) = match instruction {
+            Instruction::Push(c) if !(*c as f32).is_finite() => {
+                return Err(AppError::MathError(format!("The constant {c} in {pc} doesn't fit in the shader")));
+            }
+            Instruction::Load(local) | Instruction::Save(local) if *local >= MAX_LOCALS => {
+                return Err(AppError::MathError(format!("The local {local} in {pc} is out of the {MAX_LOCALS} locals of the shader")));
+            }
+            Instruction::Load(local) if !saved[*local] => {
+                return Err(AppError::MathError(format!("The local {local} is loaded in {pc} before it's saved")));
+            }
+            Instruction::Push(_) | Instruction::PushX | Instruction::PushY | Instruction::Load(_) => (0, 1),
+            Instruction::Save(local) => {
+                saved[*local] = true;
+                (1, 1)
+            }
+            Instruction::Cpy => (1, 2),
+            Instruction::Pop | Instruction::Require(_) => (1, 0),
+            //The shader keeps the stored value in the stack
+            Instruction::Store => (1, 1),
+            Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Pow => (2, 1),
+            Instruction::Div => {
+                masks += 1;
+                (2, 1)
+            }
+            Instruction::UnaryOperation(op) => {
+                instruction.to_number_pair()?;
+                match op {
+                    UnaryOperation::Floor | UnaryOperation::Ceil => masks += 1,
+                    //The sine and the cosine are pushed before dividing them
+                    UnaryOperation::Tan => {
+                        masks += 1;
+                        max_depth = max_depth.max(depth + 1);
+                    }
+                    _ => (),
+                }
+                (1, 1)
+            }
+            Instruction::Ret => {
+                expressions += 1;
+                (depth, masks, saved) = (0, 0, [false; MAX_LOCALS]);
+                (0, 0)
+            }
+        };
+
+        if depth < pops {
+            return Err(AppError::MathError(format!("The bytecode pops from an empty stack in {pc}")));
+        }
+        depth = depth - pops + pushes;
+        max_depth = max_depth.max(depth);
+
+        if max_depth > MAX_STACK_SIZE {
+            return Err(AppError::MathError(format!("The expression needs a stack of {max_depth}, more than the {MAX_STACK_SIZE} of the shader")));
+        }
+        if masks > MAX_MASKS {
+            return Err(AppError::MathError(format!("A function can't have more than {MAX_MASKS} denominators, floors and ceils")));
+        }
+    }
+
+    if expressions > MAX_EXPRESSIONS {
+        return Err(AppError::MathError(format!("The bytecode has {expressions} expressions, more than the {MAX_EXPRESSIONS} of the shader")));
+    }
+
+    Ok(max_depth)
+}
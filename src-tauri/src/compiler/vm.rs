@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, parser::UnaryOperation};
+
+use super::{bytecode::Instruction, verifier::{MAX_EXPRESSIONS, MAX_LOCALS, MAX_STACK_SIZE}};
+
+/// The points of the biggest grid, about a canvas of 1024x1024 pixels
+pub const MAX_GRID_POINTS: usize = 1 << 20;
+
+/// The arithmetic of the virtual machine, the shader computes in f32
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    F32,
+    F64,
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq)]
+pub enum VmError {
+    #[error("The bytecode pops from an empty stack in {pc}")]
+    StackUnderflow { pc: usize },
+    #[error("The bytecode needs more than the {MAX_STACK_SIZE} values of the stack in {pc}")]
+    StackOverflow { pc: usize },
+    #[error("The local {local} in {pc} isn't one of the {MAX_LOCALS} locals")]
+    InvalidLocal { pc: usize, local: usize },
+    #[error("The local {local} is loaded in {pc} before it's saved")]
+    UnsavedLocal { pc: usize, local: usize },
+    #[error("{op:?} in {pc} isn't an operation of the bytecode")]
+    InvalidOperation { pc: usize, op: UnaryOperation },
+    #[error("The bytecode has more than {MAX_EXPRESSIONS} expressions")]
+    TooManyExpressions,
+    #[error("A grid can't have more than {MAX_GRID_POINTS} points")]
+    TooManyPoints,
+}
+
+impl From<VmError> for AppError {
+    fn from(error: VmError) -> Self {
+        AppError::MathError(error.to_string())
+    }
+}
+
+/// What the shader computes for an expression in a point
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    /// The stored value, its sign is the side of the curve
+    pub value: f64,
+    /// The signs of the denominators and the parities of floor and ceil, a bit each in the order they are computed
+    pub masks: u32,
+    /// If all the conditions of the domain hold
+    pub defined: bool,
+}
+
+/// Runs the bytecode in the CPU like the shader interpreter does, with its limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vm {
+    precision: Precision,
+}
+
+impl Vm {
+    pub fn new(precision: Precision) -> Self {
+        Self { precision }
+    }
+
+    /// The evaluation of every expression of the program in (x, y)
+    pub fn run(&self, program: &[Instruction], x: f64, y: f64) -> Result<Vec<Evaluation>, VmError> {
+        let mut stack: Vec<f64> = Vec::with_capacity(MAX_STACK_SIZE);
+        let mut locals = [None; MAX_LOCALS];
+        let mut current = Evaluation { value: 0.0, masks: 0, defined: true };
+        let mut evaluations = Vec::new();
+
+        for (pc, instruction) in program.iter().enumerate() {
+            let mut pop = || stack.pop().ok_or(VmError::StackUnderflow { pc });
+
+            let result = match instruction {
+                Instruction::Push(c) => Some(*c),
+                Instruction::PushX => Some(x),
+                Instruction::PushY => Some(y),
+                Instruction::Cpy => {
+                    let a = pop()?;
+                    stack.push(a);
+                    Some(a)
+                }
+                Instruction::Pop => {
+                    pop()?;
+                    None
+                }
+                //The stored value stays in the stack
+                Instruction::Store => {
+                    current.value = *stack.last().ok_or(VmError::StackUnderflow { pc })?;
+                    None
+                }
+                Instruction::Require(requirement) => {
+                    current.defined &= requirement.holds(pop()?);
+                    None
+                }
+                Instruction::Load(local) => {
+                    let slot = locals.get(*local).ok_or(VmError::InvalidLocal { pc, local: *local })?;
+                    Some(slot.ok_or(VmError::UnsavedLocal { pc, local: *local })?)
+                }
+                Instruction::Save(local) => {
+                    let a = *stack.last().ok_or(VmError::StackUnderflow { pc })?;
+                    *locals.get_mut(*local).ok_or(VmError::InvalidLocal { pc, local: *local })? = Some(a);
+                    None
+                }
+                Instruction::Ret => {
+                    if evaluations.len() == MAX_EXPRESSIONS {
+                        return Err(VmError::TooManyExpressions);
+                    }
+                    evaluations.push(current);
+
+                    current = Evaluation { value: 0.0, masks: 0, defined: true };
+                    stack.clear();
+                    locals = [None; MAX_LOCALS];
+                    None
+                }
+                Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div | Instruction::Pow => {
+                    let b = pop()?;
+                    let a = pop()?;
+                    Some(match instruction {
+                        Instruction::Add => a + b,
+                        Instruction::Sub => a - b,
+                        Instruction::Mul => a * b,
+                        Instruction::Pow => a.powf(b),
+                        _ => {
+                            current.masks = current.masks << 1 | (b < 0.0) as u32;
+                            a / b
+                        }
+                    })
+                }
+                Instruction::UnaryOperation(op) => {
+                    if instruction.to_number_pair().is_err() {
+                        return Err(VmError::InvalidOperation { pc, op: op.clone() });
+                    }
+
+                    let a = pop()?;
+                    let f = op.func().map_err(|_| VmError::InvalidOperation { pc, op: op.clone() })?;
+                    let value = self.round(f(a));
+                    match op {
+                        //The tangent divides the sine by the cosine, whose sign is masked
+                        UnaryOperation::Tan => current.masks = current.masks << 1 | (self.round(a.cos()) < 0.0) as u32,
+                        UnaryOperation::Floor | UnaryOperation::Ceil => current.masks = current.masks << 1 | (value.rem_euclid(2.0) >= 1.0) as u32,
+                        _ => (),
+                    }
+                    Some(value)
+                }
+            };
+
+            if let Some(value) = result {
+                if stack.len() == MAX_STACK_SIZE {
+                    return Err(VmError::StackOverflow { pc });
+                }
+                stack.push(self.round(value));
+            }
+        }
+
+        Ok(evaluations)
+    }
+
+    /// Evaluates the program in every point of the grid, row by row: the point i is (xs[i % xs.len()], ys[i / xs.len()])
+    pub fn run_grid(&self, program: &[Instruction], xs: &[f64], ys: &[f64]) -> Result<Vec<Vec<Evaluation>>, VmError> {
+        match xs.len().checked_mul(ys.len()) {
+            Some(points) if points <= MAX_GRID_POINTS => (),
+            _ => return Err(VmError::TooManyPoints),
+        }
+
+        ys.iter()
+            .flat_map(|y| xs.iter().map(move |x| (*x, *y)))
+            .map(|(x, y)| self.run(program, x, y))
+            .collect()
+    }
+
+    /// The values are rounded after every operation when the arithmetic is f32
+    fn round(&self, value: f64) -> f64 {
+        match self.precision {
+            Precision::F32 => value as f32 as f64,
+            Precision::F64 => value,
+        }
+    }
+}
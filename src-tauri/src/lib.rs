@@ -1,67 +1,309 @@
 pub mod error;
 mod parser;
 mod compiler;
+mod analysis;
 
 use std::{str, collections::HashMap};
-use compiler::bytecode::{compile_to_bytecode, Instruction};
+use compiler::{assembly::{assemble, disassemble}, bytecode::{compile_to_bytecode, Instruction}, verifier::verify};
 use log::{info, warn};
 use parser::Node;
 use serde::{Deserialize, Serialize};
 
-use crate::{compiler::{ast_unknowns, compile_to_string}, error::AppError, parser::{parse_latex, simplify_tree}};
+use crate::{analysis::{check_range, discontinuities, evaluate_at, explicit_function, points, roots, table}, compiler::{ast_unknowns, codegen, compile_to_string, domain::{domain, Condition}}, error::AppError, parser::{derive_function, fit_model, AST_VERSION, fitted_curve, parse_latex, parse_mathml, to_content_mathml, to_latex, to_presentation_mathml, Limit, resolve_values, simplify_tree, solve_system, solve_variable, taylor_series_latex, transform_curve, PolynomialCommand}};
+
+pub use analysis::Point;
+pub use compiler::codegen::Language;
+pub use compiler::verifier::ShaderLimits;
+pub use compiler::vm::{Evaluation, Precision, Vm, VmError};
+pub use analysis::discontinuities::Discontinuity;
+pub use analysis::interval::{drawn_interval, evaluate_bytecode_interval, evaluate_interval, Interval};
+pub use analysis::points::PointOfInterest;
+pub use analysis::table::TableRow;
+pub use parser::{Fit, Matrix, Value};
 
 #[derive(Debug, Default)]
 pub struct CompilerState {
     variables: HashMap<String, f64>,
     functions: HashMap<String, Box<Node>>,
+    /// The simplified trees of the expressions that are drawn, by their index
+    expressions: HashMap<usize, Box<Node>>,
+    matrices: HashMap<String, Matrix>,
+    /// The columns of the imported data tables
+    lists: HashMap<String, Vec<f64>>,
+}
+
+/// An expression drawn by the frontend, by its index, or a function by its name
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExpressionRef {
+    Index(usize),
+    Function(String),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Response {
     code: String,
     bytecode: Vec<(u8, f64)>,
-    num: Option<f64>
+    num: Option<f64>,
+    /// The value of the expression when it's a matrix, it can't be drawn
+    matrix: Option<Matrix>,
+    /// The value of the expression when it's a list, neither can it be drawn
+    list: Option<Vec<f64>>,
+    /// The conditions for the drawn expression to be defined, like x ≥ 0
+    domain: Vec<String>,
+}
+
+/// The fitted parameters and the compiled curve of the model, if it only uses one list
+#[derive(Serialize, Debug)]
+pub struct FitResponse {
+    #[serde(flatten)]
+    fit: Fit,
+    curve: Option<Response>,
+}
+
+/// The trees the parser reads from an expression, before and after simplifying it, with the version of their JSON representation
+#[derive(Serialize, Debug)]
+pub struct SyntaxTree {
+    version: u32,
+    parsed: Node,
+    simplified: Node,
+}
+
+/// The columns of a CSV file, imported as the lists x_n and y_n
+#[derive(Serialize, Debug)]
+pub struct DataTable {
+    names: (String, String),
+    points: Vec<Point>,
 }
 
-pub fn process(eq: &str, state: &CompilerState, expr_idx: usize) -> error::Result<Response> {
+pub fn process(eq: &str, state: &mut CompilerState, expr_idx: usize) -> error::Result<Response> {
     info!("{eq}");
+    state.expressions.remove(&expr_idx);
+
+    if let Some(limit) = Limit::parse(eq, &state.functions, &state.variables)? {
+        let n = limit.evaluate(&state.variables).inspect_err(|e| warn!("{e:?}"))?;
+        info!("Limit {eq} evaluates to {n}");
+        return Ok(Response { code: String::new(), bytecode: Vec::new(), num: Some(n), matrix: None, list: None, domain: Vec::new() });
+    }
 
     let mut root = parse_latex(eq, &state.functions).or_else(|e| { 
         warn!("{e:?}"); Err(e) 
     })?;
     root.print_tree();
 
+    match resolve_values(&mut root, &state.variables, &state.matrices, &state.lists)? {
+        Some(Value::Matrix(m)) => {
+            info!("Expression {eq} evaluates to the matrix {}", m.to_latex());
+            return Ok(Response { code: String::new(), bytecode: Vec::new(), num: None, matrix: Some(m), list: None, domain: Vec::new() });
+        }
+        Some(Value::List(l)) => {
+            info!("Expression {eq} evaluates to the list {l:?}");
+            return Ok(Response { code: String::new(), bytecode: Vec::new(), num: None, matrix: None, list: Some(l), domain: Vec::new() });
+        }
+        _ => (),
+    }
+
     match process_ast(&mut root, &state.variables, expr_idx)? {
-        Response { bytecode, code, num: Some(n) } =>  {
+        Response { bytecode, code, num: Some(n), domain, .. } =>  {
             info!("Expression {eq} evaluates to {n}");
-            Ok(Response { bytecode, code, num: Some(n) })
+            Ok(Response { bytecode, code, num: Some(n), matrix: None, list: None, domain })
         }
-        Response { bytecode, code, num: None } => {
+        Response { bytecode, code, num: None, domain, .. } => {
             info!("Expression {eq} has been compiled to {code}");
-            Ok(Response { bytecode, code, num: None })
+            state.expressions.insert(expr_idx, Box::new(root));
+            Ok(Response { bytecode, code, num: None, matrix: None, list: None, domain })
         }
     }
 }
 
-pub fn add_variable(name: &str, content: &str, state: &mut CompilerState) -> error::Result<f64> {
+pub fn add_variable(name: &str, content: &str, state: &mut CompilerState) -> error::Result<Value> {
     state.variables.remove(name);
+    state.matrices.remove(name);
+    state.lists.remove(name);
 
-    let mut root = parse_latex(content, &state.functions).or_else(|e| { 
-        warn!("{e:?}"); Err(e) 
-    })?;
-    
-    simplify_tree(&mut root, &state.variables); //Somehow if I don't apply the effect 2 times the simplifier fails in some cases
-    let val = simplify_tree(&mut root, &state.variables).ok_or_else(|| {
+    let val = evaluate_variable(content, state).inspect_err(|_| {
         warn!("The variable {name} couldn't be evaluated to a value: {content}");
-        root.print_tree();
-        AppError::MathError(format!("The variable must evaluate to a certain value"))
     })?;
     
-    state.variables.insert(name.to_owned(), val);
+    match &val {
+        Value::Scalar(n) => { state.variables.insert(name.to_owned(), *n); }
+        Value::Matrix(m) => { state.matrices.insert(name.to_owned(), m.clone()); }
+        Value::List(l) => { state.lists.insert(name.to_owned(), l.clone()); }
+    }
 
     Ok(val)
 }
 
+/// Defines the variable with an equation like a^2=9, its value is the first solution and all of them are returned
+pub fn solve_for_variable(name: &str, eq: &str, state: &mut CompilerState) -> error::Result<Vec<f64>> {
+    state.variables.remove(name);
+
+    let root = parse_latex(eq, &state.functions).inspect_err(|e| warn!("{e:?}"))?;
+    let solutions = solve_variable(&root, name, &state.variables).inspect_err(|_| {
+        warn!("The equation {eq} couldn't be solved for {name}");
+    })?;
+    info!("{name} = {solutions:?}");
+
+    state.variables.insert(name.to_owned(), solutions[0]);
+
+    Ok(solutions)
+}
+
+/// Solves a system of equations for the variables and defines them with the solution
+pub fn solve_for_variables(names: &[String], equations: &[String], state: &mut CompilerState) -> error::Result<HashMap<String, f64>> {
+    for name in names {
+        state.variables.remove(name);
+    }
+
+    let roots: error::Result<Vec<Node>> = equations.iter()
+        .map(|eq| parse_latex(eq, &state.functions).inspect_err(|e| warn!("{e:?}")))
+        .collect();
+    let values = solve_system(&roots?, names, &state.variables).inspect_err(|_| {
+        warn!("The system {equations:?} couldn't be solved for {names:?}");
+    })?;
+
+    let solution: HashMap<String, f64> = names.iter().cloned().zip(values).collect();
+    info!("{solution:?}");
+    state.variables.extend(solution.clone());
+
+    Ok(solution)
+}
+
+/// Fits the parameters of a model written as y_1 \sim a x_1 + b, which are defined as variables.
+/// If the model depends on a single list the fitted curve is drawn in its place
+pub fn fit_parameters(names: &[String], eq: &str, state: &mut CompilerState, expr_idx: usize) -> error::Result<FitResponse> {
+    names.iter().for_each(|name| { state.variables.remove(name); });
+    state.expressions.remove(&expr_idx);
+
+    let (observed, model) = eq.split_once("\\sim")
+        .ok_or_else(|| AppError::ParseError("A model must be written as y_1 \\sim f(x_1)".to_owned()))?;
+    let observed = parse_latex(observed, &state.functions).inspect_err(|e| warn!("{e:?}"))?;
+    let model = parse_latex(model, &state.functions).inspect_err(|e| warn!("{e:?}"))?;
+
+    let fit = fit_model(&observed, &model, names, &state.variables, &state.lists)?;
+    info!("{eq} has been fitted with {:?} and R^2 = {}", fit.parameters, fit.r_squared);
+    state.variables.extend(fit.parameters.iter().cloned());
+
+    let curve = match fitted_curve(&model, &state.lists) {
+        Some(mut curve) => {
+            let response = process_ast(&mut curve, &state.variables, expr_idx)?;
+            if response.num.is_none() {
+                state.expressions.insert(expr_idx, Box::new(curve));
+            }
+            Some(response)
+        }
+        None => None,
+    };
+
+    Ok(FitResponse { fit, curve })
+}
+
+/// The Taylor polynomial of degree n of a function around a constant, in LaTeX so it can be shown to the user
+pub fn taylor_series(name: &str, center: &str, degree: usize, state: &CompilerState) -> error::Result<String> {
+    let func = state.functions.get(name)
+        .ok_or_else(|| AppError::IoError(format!("The aren't any functions called {name}")))?;
+    let center = evaluate_constant(center, state)?;
+    info!("T_{degree}({name}, {center})");
+
+    taylor_series_latex(func, center, degree, &state.variables)
+}
+
+pub fn syntax_tree(eq: &str, state: &CompilerState) -> error::Result<SyntaxTree> {
+    let parsed = parse_latex(eq, &state.functions)?;
+
+    let mut simplified = parsed.clone();
+    simplify_tree(&mut simplified, &state.variables);
+    simplify_tree(&mut simplified, &state.variables);
+
+    Ok(SyntaxTree { version: AST_VERSION, parsed, simplified })
+}
+
+/// The simplified expression as it's drawn or its n-th derivative in LaTeX, so it can be shown to the user
+pub fn expression_latex(expr: &ExpressionRef, derivative: usize, state: &CompilerState) -> error::Result<String> {
+    let mut f = if derivative == 0 { drawn_expression(expr, state)? } else { resolve_expression(expr, state)? };
+    for _ in 0..derivative {
+        f = *derive_function(&f)?;
+    }
+
+    simplify_tree(&mut f, &state.variables);
+    simplify_tree(&mut f, &state.variables);
+    Ok(to_latex(&f))
+}
+
+/// The limits of the shaders, so the frontend doesn't define them again
+pub fn shader_limits() -> ShaderLimits {
+    compiler::verifier::SHADER_LIMITS
+}
+
+/// Reads a formula in Presentation MathML and writes it in LaTeX, so it can be put in an expression
+pub fn import_mathml(mathml: &str) -> error::Result<String> {
+    let root = parse_mathml(mathml).inspect_err(|e| warn!("{e:?}"))?;
+    Ok(to_latex(&root))
+}
+
+/// The simplified expression as it's drawn in Presentation MathML, or in Content MathML when the meaning is needed instead of the layout
+pub fn export_mathml(expr: &ExpressionRef, content: bool, state: &CompilerState) -> error::Result<String> {
+    let mut f = drawn_expression(expr, state)?;
+    simplify_tree(&mut f, &state.variables);
+    simplify_tree(&mut f, &state.variables);
+
+    Ok(if content { to_content_mathml(&f) } else { to_presentation_mathml(&f) })
+}
+
+/// Runs the bytecode of an expression in the CPU over a grid of points, row by row, with the precision of the GPU or of f64
+pub fn evaluate_grid(expr: &ExpressionRef, xs: &[f64], ys: &[f64], precision: Precision, state: &CompilerState) -> error::Result<Vec<Evaluation>> {
+    let f = resolve_drawn_expression(expr, state)?;
+    let program = compile_to_bytecode(&f, &state.variables)?;
+    info!("Evaluating {expr:?} in a grid of {}x{}", xs.len(), ys.len());
+
+    let grid = Vm::new(precision).run_grid(&program, xs, ys)?;
+    Ok(grid.into_iter().filter_map(|evaluations| evaluations.first().copied()).collect())
+}
+
+/// Writes the bytecode of an expression, as the frontend sends it to the shader, as assembly
+pub fn disassemble_bytecode(bytecode: &[(u8, f64)]) -> error::Result<String> {
+    disassemble(&decode_bytecode(bytecode)?)
+}
+
+/// Reads a program in assembly and encodes it like the bytecode of the expressions
+pub fn assemble_bytecode(source: &str) -> error::Result<Vec<(u8, f64)>> {
+    let program = assemble(source).inspect_err(|e| warn!("{e:?}"))?;
+    verify(&program)?;
+    program.iter().map(Instruction::to_number_pair).collect()
+}
+
+/// Runs a bytecode captured from the frontend in the CPU, the evaluations of every expression in each point of the grid
+pub fn replay_bytecode(bytecode: &[(u8, f64)], xs: &[f64], ys: &[f64], precision: Precision) -> error::Result<Vec<Vec<Evaluation>>> {
+    let program = decode_bytecode(bytecode)?;
+    Ok(Vm::new(precision).run_grid(&program, xs, ys)?)
+}
+
+fn decode_bytecode(bytecode: &[(u8, f64)]) -> error::Result<Vec<Instruction>> {
+    bytecode.iter().map(|(op_code, operand)| Instruction::from_number_pair(*op_code, *operand)).collect()
+}
+
+/// Writes a drawn expression or a function as a function of Rust, C, JavaScript or Python. The implicit equations are written as lhs - rhs
+pub fn generate_code(expr: &ExpressionRef, language: Language, state: &CompilerState) -> error::Result<String> {
+    let f = resolve_drawn_expression(expr, state)?;
+    let name = match expr {
+        ExpressionRef::Index(i) => format!("f{i}"),
+        ExpressionRef::Function(name) => name.to_owned(),
+    };
+    info!("Generating the code of {expr:?} in {language:?}");
+
+    codegen::generate_code(&f, &name, language, &state.variables)
+}
+
+/// Runs \operatorname{expand}, \operatorname{factor} or \operatorname{solve} on a polynomial and returns the result in LaTeX
+pub fn polynomial_command(eq: &str, state: &CompilerState) -> error::Result<String> {
+    let (command, content) = PolynomialCommand::parse(eq)
+        .ok_or_else(|| AppError::ParseError(format!("{eq} isn't a polynomial command")))?;
+    info!("{command:?} {content}");
+
+    let root = parse_latex(content, &state.functions).inspect_err(|e| warn!("{e:?}"))?;
+    command.run(&root, &state.variables)
+}
+
 pub fn add_function(name: &str, content: &str, state: &mut CompilerState, expr_idx: usize) -> error::Result<Response> {
     let fn_name =  name.chars().nth(0)
         .ok_or_else(|| AppError::ParseError("This function doesn't have name".to_owned()))?;
@@ -70,31 +312,140 @@ pub fn add_function(name: &str, content: &str, state: &mut CompilerState, expr_i
     info!("{}({}) = {content}", fn_name, unknown);
 
     state.functions.remove(&fn_name.to_string());
+    state.expressions.remove(&expr_idx);
 
     let mut root = parse_latex(content, &state.functions).or_else(|e| { 
         warn!("{e:?}"); Err(e) 
     })?;
+    if resolve_values(&mut root, &state.variables, &state.matrices, &state.lists)?.is_some() {
+        return Err(AppError::MathError(format!("The function {fn_name} can't be a matrix nor a list")));
+    }
     let (x, y) = ast_unknowns(&root)?;
     if !((x && unknown == 'x') || (y && unknown == 'y')) {
         return Err(AppError::ParseError(format!("The function {fn_name} does not match its unknowns")));
     }
 
     let response = match process_ast(&mut root, &state.variables, expr_idx)? {
-        Response { bytecode, code, num: Some(n) } =>  {
+        Response { bytecode, code, num: Some(n), domain, .. } =>  {
             info!("Expression {content} evaluates to {n}");
-            Ok(Response { bytecode, code, num: Some(n) })
+            Ok(Response { bytecode, code, num: Some(n), matrix: None, list: None, domain })
         }
-        Response { bytecode, code, num: None } => {
+        Response { bytecode, code, num: None, domain, .. } => {
             info!("Expression {content} has been compiled to {code}");
-            Ok(Response { bytecode, code, num: None })
+            Ok(Response { bytecode, code, num: None, matrix: None, list: None, domain })
         }
     };
 
+    if let Ok(Response { num: None, .. }) = response {
+        state.expressions.insert(expr_idx, Box::new(root.clone()));
+    }
     state.functions.insert(fn_name.to_string(), Box::new(root));
 
     response
 }
 
+pub fn find_roots(expr: &ExpressionRef, from: f64, to: f64, state: &CompilerState) -> error::Result<Vec<Point>> {
+    check_range(from, to)?;
+    let f = resolve_expression(expr, state)?;
+
+    let roots = roots::find_roots(|x| evaluate_at(&f, x, &state.variables), from, to);
+    info!("{expr:?} has {} roots in [{from}, {to}]", roots.len());
+
+    Ok(roots.into_iter().map(|x| Point { x, y: 0.0 }).collect())
+}
+
+pub fn find_intersections(a: &ExpressionRef, b: &ExpressionRef, from: f64, to: f64, state: &CompilerState) -> error::Result<Vec<Point>> {
+    check_range(from, to)?;
+    let f = resolve_expression(a, state)?;
+    let g = resolve_expression(b, state)?;
+
+    let difference = |x| Some(evaluate_at(&f, x, &state.variables)? - evaluate_at(&g, x, &state.variables)?);
+    let roots = roots::find_roots(difference, from, to);
+    info!("{a:?} and {b:?} intersect {} times in [{from}, {to}]", roots.len());
+
+    Ok(roots.into_iter()
+        .filter_map(|x| evaluate_at(&f, x, &state.variables).map(|y| Point { x, y }))
+        .collect())
+}
+
+pub fn points_of_interest(expr: &ExpressionRef, from: f64, to: f64, state: &CompilerState) -> error::Result<Vec<PointOfInterest>> {
+    check_range(from, to)?;
+    let f = resolve_expression(expr, state)?;
+
+    let points = points::points_of_interest(&f, from, to, &state.variables)?;
+    info!("{expr:?} has {} points of interest in [{from}, {to}]", points.len());
+
+    Ok(points)
+}
+
+pub fn discontinuities(expr: &ExpressionRef, from: f64, to: f64, state: &CompilerState) -> error::Result<Vec<Discontinuity>> {
+    check_range(from, to)?;
+    let f = resolve_expression(expr, state)?;
+
+    let discontinuities = discontinuities::discontinuities(&f, from, to, &state.variables);
+    info!("{expr:?} has {} discontinuities in [{from}, {to}]", discontinuities.len());
+
+    Ok(discontinuities)
+}
+
+pub fn table_of_values(expr: &ExpressionRef, from: f64, to: f64, step: f64, state: &CompilerState) -> error::Result<Vec<TableRow>> {
+    check_range(from, to)?;
+    let f = resolve_expression(expr, state)?;
+
+    let rows = table::table_of_values(&f, from, to, step, &state.variables)?;
+    info!("The table of {expr:?} in [{from}, {to}] has {} rows", rows.len());
+
+    Ok(rows)
+}
+
+/// Writes the table of values of an expression as a CSV file
+pub fn export_table(expr: &ExpressionRef, from: f64, to: f64, step: f64, path: &str, state: &CompilerState) -> error::Result<()> {
+    let rows = table_of_values(expr, from, to, step, state)?;
+    std::fs::write(path, table::to_csv(&rows))
+        .map_err(|e| AppError::IoError(format!("Couldn't write the table in {path}: {e}")))
+}
+
+/// Draws the image of an expression by the linear transformation of a 2x2 matrix, in the place of the expression expr_idx
+pub fn transform_expression(expr: &ExpressionRef, matrix: &str, state: &mut CompilerState, expr_idx: usize) -> error::Result<Response> {
+    let m = state.matrices.get(matrix)
+        .ok_or_else(|| AppError::IoError(format!("There aren't any matrices called {matrix}")))?;
+    let root = match expr {
+        ExpressionRef::Index(i) => state.expressions.get(i)
+            .ok_or_else(|| AppError::IoError(format!("There isn't any drawn expression with index {i}")))?,
+        ExpressionRef::Function(name) => state.functions.get(name)
+            .ok_or_else(|| AppError::IoError(format!("The aren't any functions called {name}")))?,
+    };
+
+    let mut curve = transform_curve(root, m)?;
+    info!("{expr:?} is transformed by {matrix}");
+
+    state.expressions.remove(&expr_idx);
+    let response = process_ast(&mut curve, &state.variables, expr_idx)?;
+    if response.num.is_none() {
+        state.expressions.insert(expr_idx, Box::new(curve));
+    }
+
+    Ok(response)
+}
+
+/// Reads a CSV file with two columns, which are stored as the lists x_n and y_n of the table n
+pub fn import_csv(path: &str, table: usize, state: &mut CompilerState) -> error::Result<DataTable> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::IoError(format!("Couldn't read the file {path}: {e}")))?;
+
+    let columns = table::from_csv(&content)?;
+    let [x, y]: [Vec<f64>; 2] = columns.try_into()
+        .map_err(|c: Vec<_>| AppError::ParseError(format!("A data table must have two columns, x and y, not {}", c.len())))?;
+    info!("The table {table} has been imported from {path} with {} rows", x.len());
+
+    let names = (format!("x_{table}"), format!("y_{table}"));
+    let points = x.iter().zip(&y).map(|(&x, &y)| Point { x, y }).collect();
+    state.lists.insert(names.0.clone(), x);
+    state.lists.insert(names.1.clone(), y);
+
+    Ok(DataTable { names, points })
+}
+
 pub fn delete_function(name: &str, state: &mut CompilerState) -> error::Result<()> {
     state.functions.remove(&name.to_string());
     Ok(())
@@ -102,6 +453,8 @@ pub fn delete_function(name: &str, state: &mut CompilerState) -> error::Result<(
 
 pub fn delete_variable(name: &str, state: &mut CompilerState) -> error::Result<()> {
     state.variables.remove(&name.to_string());
+    state.matrices.remove(name);
+    state.lists.remove(name);
     Ok(())
 }
 
@@ -115,16 +468,79 @@ fn process_ast(root: &mut Node, variable_map: &HashMap<String, f64>, expr_idx: u
             code: String::new(),
             bytecode: Vec::new(),
             num: numeric_value,
+            matrix: None,
+            list: None,
+            domain: Vec::new(),
         } )   
     } else {
         let compiled = compile_to_string(&root, variable_map, expr_idx)?;
-        let bytecode = compile_to_bytecode(root, variable_map, expr_idx)?;
+        let bytecode = compile_to_bytecode(root, variable_map)?;
         let bytecode: error::Result<Vec<_>> = bytecode.iter().map(Instruction::to_number_pair).collect();
 
         Ok( Response {
             bytecode: bytecode?,
             code: compiled,
-            num: None
+            num: None,
+            matrix: None,
+            list: None,
+            domain: domain(root, variable_map).iter().map(Condition::to_string).collect(),
         } )
     }
-}
\ No newline at end of file
+}
+
+fn evaluate_constant(content: &str, state: &CompilerState) -> error::Result<f64> {
+    match evaluate_variable(content, state)? {
+        Value::Scalar(n) => Ok(n),
+        Value::Matrix(_) => Err(AppError::MathError("This must be a number, not a matrix".to_owned())),
+        Value::List(_) => Err(AppError::MathError("This must be a number, not a list".to_owned())),
+    }
+}
+
+fn evaluate_variable(content: &str, state: &CompilerState) -> error::Result<Value> {
+    let mut root = parse_latex(content, &state.functions).or_else(|e| { 
+        warn!("{e:?}"); Err(e) 
+    })?;
+
+    if let Some(value) = resolve_values(&mut root, &state.variables, &state.matrices, &state.lists)? {
+        return Ok(value);
+    }
+    
+    simplify_tree(&mut root, &state.variables); //Somehow if I don't apply the effect 2 times the simplifier fails in some cases
+    simplify_tree(&mut root, &state.variables).map(Value::Scalar).ok_or_else(|| {
+        root.print_tree();
+        AppError::MathError(format!("The variable must evaluate to a certain value"))
+    })
+}
+
+/// Gets a drawn expression as it's drawn, with its equal sign, or a function
+fn drawn_expression(expr: &ExpressionRef, state: &CompilerState) -> error::Result<Node> {
+    match expr {
+        ExpressionRef::Index(i) => {
+            state.expressions.get(i)
+                .map(|root| *root.to_owned())
+                .ok_or_else(|| AppError::IoError(format!("There isn't any drawn expression with index {i}")))
+        }
+        ExpressionRef::Function(name) => {
+            state.functions.get(name)
+                .map(|f| *f.to_owned())
+                .ok_or_else(|| AppError::IoError(format!("The aren't any functions called {name}")))
+        }
+    }
+}
+
+/// Gets f(x) of a drawn expression or a function
+fn resolve_expression(expr: &ExpressionRef, state: &CompilerState) -> error::Result<Node> {
+    let root = drawn_expression(expr, state)?;
+    match expr {
+        ExpressionRef::Index(_) => explicit_function(&root),
+        ExpressionRef::Function(_) => Ok(root),
+    }
+}
+
+/// Like resolve_expression, but the drawn expressions that aren't explicit are kept as they are drawn
+fn resolve_drawn_expression(expr: &ExpressionRef, state: &CompilerState) -> error::Result<Node> {
+    resolve_expression(expr, state).or_else(|e| match expr {
+        ExpressionRef::Index(_) => drawn_expression(expr, state),
+        ExpressionRef::Function(_) => Err(e),
+    })
+}
@@ -1,26 +1,38 @@
 //! Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::sync::Mutex;
+use std::{collections::HashMap, sync::Mutex};
 
 use log::info;
 use tauri::State;
-use webgl_calculator::{error::{self, AppError}, CompilerState, Response};
+use webgl_calculator::{error::{self, AppError}, CompilerState, DataTable, Discontinuity, Evaluation, ExpressionRef, FitResponse, Language, Point, PointOfInterest, Precision, Response, ShaderLimits, SyntaxTree, TableRow, Value};
 
 type TauriState = Mutex<CompilerState>;
 
 #[tauri::command]
 fn process(eq: &str, state: State<TauriState>, expr_idx: usize) -> error::Result<Response> {
-    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
-    webgl_calculator::process(eq, &state, expr_idx)
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::process(eq, &mut state, expr_idx)
 }
 
 #[tauri::command]
-fn add_variable(name: &str, content: &str, state: State<TauriState>) -> error::Result<f64> {
+fn add_variable(name: &str, content: &str, state: State<TauriState>) -> error::Result<Value> {
     let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
     webgl_calculator::add_variable(name, content, &mut state)
 }
 
+#[tauri::command]
+fn solve_for_variable(name: &str, eq: &str, state: State<TauriState>) -> error::Result<Vec<f64>> {
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::solve_for_variable(name, eq, &mut state)
+}
+
+#[tauri::command]
+fn solve_for_variables(names: Vec<String>, equations: Vec<String>, state: State<TauriState>) -> error::Result<HashMap<String, f64>> {
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::solve_for_variables(&names, &equations, &mut state)
+}
+
 #[tauri::command]
 fn add_function(name: &str, content: &str, state: State<TauriState>, expr_idx: usize) -> error::Result<Response> {
     let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
@@ -39,6 +51,127 @@ fn delete_variable(name: &str, state: State<TauriState>) -> error::Result<()> {
     webgl_calculator::delete_variable(name, &mut state)
 }
 
+#[tauri::command]
+fn fit_parameters(names: Vec<String>, eq: &str, state: State<TauriState>, expr_idx: usize) -> error::Result<FitResponse> {
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::fit_parameters(&names, eq, &mut state, expr_idx)
+}
+
+#[tauri::command]
+fn taylor_series(name: &str, center: &str, degree: usize, state: State<TauriState>) -> error::Result<String> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::taylor_series(name, center, degree, &state)
+}
+
+#[tauri::command]
+fn syntax_tree(eq: &str, state: State<TauriState>) -> error::Result<SyntaxTree> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::syntax_tree(eq, &state)
+}
+
+#[tauri::command]
+fn generate_code(expr: ExpressionRef, language: Language, state: State<TauriState>) -> error::Result<String> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::generate_code(&expr, language, &state)
+}
+
+#[tauri::command]
+fn evaluate_grid(expr: ExpressionRef, xs: Vec<f64>, ys: Vec<f64>, precision: Precision, state: State<TauriState>) -> error::Result<Vec<Evaluation>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::evaluate_grid(&expr, &xs, &ys, precision, &state)
+}
+
+#[tauri::command]
+fn disassemble_bytecode(bytecode: Vec<(u8, f64)>) -> error::Result<String> {
+    webgl_calculator::disassemble_bytecode(&bytecode)
+}
+
+#[tauri::command]
+fn assemble_bytecode(source: &str) -> error::Result<Vec<(u8, f64)>> {
+    webgl_calculator::assemble_bytecode(source)
+}
+
+#[tauri::command]
+fn replay_bytecode(bytecode: Vec<(u8, f64)>, xs: Vec<f64>, ys: Vec<f64>, precision: Precision) -> error::Result<Vec<Vec<Evaluation>>> {
+    webgl_calculator::replay_bytecode(&bytecode, &xs, &ys, precision)
+}
+
+#[tauri::command]
+fn expression_latex(expr: ExpressionRef, derivative: usize, state: State<TauriState>) -> error::Result<String> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::expression_latex(&expr, derivative, &state)
+}
+
+#[tauri::command]
+fn shader_limits() -> ShaderLimits {
+    webgl_calculator::shader_limits()
+}
+
+#[tauri::command]
+fn import_mathml(mathml: &str) -> error::Result<String> {
+    webgl_calculator::import_mathml(mathml)
+}
+
+#[tauri::command]
+fn export_mathml(expr: ExpressionRef, content: bool, state: State<TauriState>) -> error::Result<String> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::export_mathml(&expr, content, &state)
+}
+
+#[tauri::command]
+fn find_roots(expr: ExpressionRef, from: f64, to: f64, state: State<TauriState>) -> error::Result<Vec<Point>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::find_roots(&expr, from, to, &state)
+}
+
+#[tauri::command]
+fn find_intersections(a: ExpressionRef, b: ExpressionRef, from: f64, to: f64, state: State<TauriState>) -> error::Result<Vec<Point>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::find_intersections(&a, &b, from, to, &state)
+}
+
+#[tauri::command]
+fn points_of_interest(expr: ExpressionRef, from: f64, to: f64, state: State<TauriState>) -> error::Result<Vec<PointOfInterest>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::points_of_interest(&expr, from, to, &state)
+}
+
+#[tauri::command]
+fn discontinuities(expr: ExpressionRef, from: f64, to: f64, state: State<TauriState>) -> error::Result<Vec<Discontinuity>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::discontinuities(&expr, from, to, &state)
+}
+
+#[tauri::command]
+fn polynomial_command(eq: &str, state: State<TauriState>) -> error::Result<String> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::polynomial_command(eq, &state)
+}
+
+#[tauri::command]
+fn table_of_values(expr: ExpressionRef, from: f64, to: f64, step: f64, state: State<TauriState>) -> error::Result<Vec<TableRow>> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::table_of_values(&expr, from, to, step, &state)
+}
+
+#[tauri::command]
+fn export_table(expr: ExpressionRef, from: f64, to: f64, step: f64, path: &str, state: State<TauriState>) -> error::Result<()> {
+    let state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::export_table(&expr, from, to, step, path, &state)
+}
+
+#[tauri::command]
+fn import_csv(path: &str, table: usize, state: State<TauriState>) -> error::Result<DataTable> {
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::import_csv(path, table, &mut state)
+}
+
+#[tauri::command]
+fn transform_expression(expr: ExpressionRef, matrix: &str, state: State<TauriState>, expr_idx: usize) -> error::Result<Response> {
+    let mut state = state.lock().map_err(|_| AppError::IoError("Couldn't read the variables and functions table".to_owned()))?;
+    webgl_calculator::transform_expression(&expr, matrix, &mut state, expr_idx)
+}
+
 fn main() {
     std::env::set_var("RUST_LOG", "info");
     env_logger::init();
@@ -47,7 +180,7 @@ fn main() {
 
     tauri::Builder::default()
         .manage(TauriState::default() )
-        .invoke_handler(tauri::generate_handler![process, add_variable, add_function, delete_function, delete_variable])
+        .invoke_handler(tauri::generate_handler![process, add_variable, solve_for_variable, solve_for_variables, add_function, delete_function, delete_variable, taylor_series, syntax_tree, expression_latex, shader_limits, import_mathml, export_mathml, generate_code, evaluate_grid, disassemble_bytecode, assemble_bytecode, replay_bytecode, find_roots, find_intersections, points_of_interest, discontinuities, polynomial_command, transform_expression, table_of_values, export_table, import_csv, fit_parameters])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
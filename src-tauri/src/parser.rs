@@ -1,20 +1,47 @@
 use std::{collections::HashMap, iter::Peekable};
 
 use crate::error::{self, AppError};
-use tex_parser::ast::{CharTokens, Pos, Token};
+use tex_parser::ast::{CharTokens, Environment, Pos, SpecialMacro, Token};
 
-use self::{arithmetic::get_terms, operations::{get_op_type, Constants, OpType}, simplifier::{derive_function, substitute_func}};
+use self::{arithmetic::{get_terms, split_arguments}, distributions::distribution, operations::{get_op_type, Constants, OpType}, series::taylor_polynomial};
 
 mod arithmetic;
 mod ast;
+mod distributions;
+mod latex;
+mod limit;
+mod list;
+mod mathml;
+mod matrix;
 mod operations;
+mod polynomial;
+mod rational;
+mod regression;
+mod series;
 mod simplifier;
+mod solver;
+mod system;
+mod value;
+#[cfg(test)]
+mod tests;
 
 pub use simplifier::simplify_tree;
-pub use ast::Node;
+pub use simplifier::derive_function;
+pub use simplifier::substitute_func;
+pub use ast::{Node, AST_VERSION};
 pub use operations::UnaryOperation;
 pub use operations::BinaryOperation;
 pub use operations::NAryOperation;
+pub use series::taylor_series_latex;
+pub use latex::to_latex;
+pub use mathml::{parse_mathml, to_content_mathml, to_presentation_mathml};
+pub use limit::{Approach, Limit, Side};
+pub use matrix::{transform_curve, Matrix};
+pub use value::{resolve_values, Value};
+pub use polynomial::PolynomialCommand;
+pub use regression::{fit_model, fitted_curve, Fit};
+pub use solver::solve_variable;
+pub use system::solve_system;
 
 //This is used because the '^' is not a Punctuation symbol in the tex_parser library and I can't change it, so I use '!' which isn't used anywhere else in my program
 const EXP_SYMBOL: char = '?';
@@ -107,6 +134,7 @@ fn build_factor<'a, I: Iterator<Item = &'a Token>>(mut tokens: Peekable<I>, func
 
     let node = match token {
         Token::Group(group) => build_tree(&group.tokens, func_map),
+        Token::SpecialMacro(SpecialMacro::Environment(env)) => build_matrix(env, func_map),
         Token::Number(n) => Ok(Node::Constant { value: n.parse().map_err(|_| AppError::ParseError(format!("Couldn't parse number {}",n.content)))? }),
         Token::Macro(mac) => {
             match get_op_type(&mac.name.content)? {
@@ -132,11 +160,36 @@ fn build_factor<'a, I: Iterator<Item = &'a Token>>(mut tokens: Peekable<I>, func
                     })
                 },
                 OpType::Constant(cte) => Ok(Node::Constant { value: cte.value() }),
+                OpType::Distribution(dist) => {
+                    let args = match tokens.next() {
+                        Some(Token::Group(group)) => split_arguments(&group.tokens).into_iter()
+                            .map(|arg| build_tree(arg, func_map))
+                            .collect::<error::Result<Vec<_>>>()?,
+                        _ => return Err(AppError::ParseError(format!("The arguments of {} must be inside parentheses", mac.name.content))),
+                    };
+
+                    distribution(dist, args)
+                },
                 _ => Err(AppError::ParseError(format!("This doesn't make sense inside a factor: {}", mac.name.content))),
             }
         },
         Token::CharTokens(tok) => {
-            if tok.content == "e" {
+            let subscripted = matches!(tokens.peek(), Some(Token::CharTokens(CharTokens { content, .. })) if content == "_");
+
+            if subscripted {
+                tokens.next();
+                let subscript = tokens.next();
+
+                //T_{n}{f,a} is a Taylor polynomial, a T with only a subscript is a variable
+                if tok.content == "T" && matches!(tokens.peek(), Some(Token::Group(_))) {
+                    let (taylor, tks) = build_taylor(subscript, tokens, func_map)?;
+                    tokens = tks;
+
+                    Ok( taylor )
+                } else {
+                    Ok( Node::Variable { name: build_subscript(&tok.content, subscript)? } )
+                }
+            } else if tok.content == "e" {
                 Ok(Node::Constant { value: Constants::E.value() })
             } else if tok.content == "x" || tok.content == "y" {
                 Ok( Node::Unknown { name: tok.content.to_owned() } )
@@ -197,6 +250,82 @@ fn build_factor<'a, I: Iterator<Item = &'a Token>>(mut tokens: Peekable<I>, func
     Ok((next_node?, tokens))
 }
 
+/// Builds the matrix of a pmatrix or bmatrix environment, its rows are split by \\ and its columns by &
+fn build_matrix(env: &Environment, func_map: &HashMap<String, Box<Node>>) -> error::Result<Node> {
+    if env.name.content != "pmatrix" && env.name.content != "bmatrix" {
+        return Err(AppError::ParseError(format!("The environment {} isn't supported", env.name.content)));
+    }
+
+    let body = split_char_tokens(filter_token_stream(env.body.clone()));
+    let rows: error::Result<Vec<Vec<Node>>> = body
+        .split(|e| matches!(e.macro_(), Some(m) if m.name.content == "\\"))
+        .filter(|row| !row.is_empty())
+        .map(|row| row.split(|e| matches!(e, Token::AlignmentTab(_)))
+            .map(|cell| build_tree(cell, func_map))
+            .collect())
+        .collect();
+    let rows = rows?;
+
+    let cols = rows.first().map_or(0, Vec::len);
+    if cols == 0 || rows.iter().any(|r| r.len() != cols) {
+        return Err(AppError::ParseError("Every row of a matrix must have the same number of elements".to_owned()));
+    }
+
+    Ok(Node::Matrix { rows })
+}
+
+/// Reads the name of a variable with a subscript, like x_{1} or a_2, from the token after the '_'
+fn build_subscript(name: &str, subscript: Option<&Token>) -> error::Result<String> {
+    let subscript = match subscript {
+        Some(Token::Group(group)) => group.tokens.iter()
+            .map(|e| match e {
+                Token::Number(n) => Some(n.content.to_owned()),
+                Token::CharTokens(c) => Some(c.content.to_owned()),
+                _ => None,
+            })
+            .collect::<Option<String>>(),
+        Some(Token::Number(n)) => Some(n.content.to_owned()),
+        Some(Token::CharTokens(c)) if c.content != "_" => Some(c.content.to_owned()),
+        _ => None,
+    };
+
+    match subscript {
+        Some(subscript) if !subscript.is_empty() => Ok(format!("{name}_{subscript}")),
+        _ => Err(AppError::ParseError(format!("The subscript of {name} must be a number or a name"))),
+    }
+}
+
+/// Builds the Taylor polynomial written as T_{n}(f, a) from the degree after the '_'
+fn build_taylor<'a, I: Iterator<Item = &'a Token>>(degree: Option<&Token>, mut tokens: Peekable<I>, func_map: &HashMap<String, Box<Node>>) -> error::Result<(Node, Peekable<I>)> {
+    let mut degree = match degree {
+        Some(Token::Group(group)) => build_tree(&group.tokens, func_map)?,
+        Some(Token::Number(n)) => Node::Constant { value: n.parse().map_err(|_| AppError::ParseError(format!("Couldn't parse number {}",n.content)))? },
+        Some(Token::CharTokens(c)) => Node::Constant { value: c.content.parse().map_err(|_| AppError::ParseError(format!("{} isn't the degree of a Taylor polynomial", c.content)))? },
+        _ => return Err(AppError::ParseError("The Taylor polynomial needs a degree".to_owned())),
+    };
+
+    let degree = simplify_tree(&mut degree, &HashMap::new())
+        .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+        .ok_or_else(|| AppError::MathError("The degree of a Taylor polynomial must be a natural number".to_owned()))?;
+
+    let args = match tokens.next() {
+        Some(Token::Group(group)) => split_arguments(&group.tokens),
+        _ => return Err(AppError::ParseError("The Taylor polynomial must be written as T_{n}(f, a)".to_owned())),
+    };
+    if args.len() != 2 {
+        return Err(AppError::ParseError("The Taylor polynomial must be written as T_{n}(f, a)".to_owned()));
+    }
+
+    let func = match args[0] {
+        [Token::CharTokens(c)] => func_map.get(&c.content)
+            .ok_or_else(|| AppError::IoError(format!("The aren't any functions called {}", c.content)))?,
+        _ => return Err(AppError::ParseError("The first argument of the Taylor polynomial must be a function".to_owned())),
+    };
+    let center = build_tree(args[1], func_map)?;
+
+    Ok((taylor_polynomial(func, &center, degree as usize)?, tokens))
+}
+
 fn tokenize_string(eq: &str) -> error::Result<Vec<Token>>{
     let eq = sanitize_string(eq.to_string())?;
 
@@ -204,7 +333,11 @@ fn tokenize_string(eq: &str) -> error::Result<Vec<Token>>{
             .map_err(|e| AppError::ParseError(e.to_string()))?;
 
     let filtered_stream = filter_token_stream(latex_doc.content);
-    let split_stream = filtered_stream.into_iter()
+    Ok( split_char_tokens(filtered_stream) )
+}
+
+fn split_char_tokens(tokens: Vec<Token>) -> Vec<Token> {
+    tokens.into_iter()
         .fold(Vec::new(), |mut v, e| {
             match e.char_tokens() {
                 Some(CharTokens{content, ..}) => {
@@ -216,9 +349,15 @@ fn tokenize_string(eq: &str) -> error::Result<Vec<Token>>{
             }
 
             v
-        });
-
-    Ok( split_stream )
+        })
+        .into_iter()
+        .map(|mut e| {
+            if let Some(g) = e.group_mut() {
+                g.tokens = split_char_tokens(std::mem::take(&mut g.tokens));
+            }
+            e
+        })
+        .collect()
 }
 
 fn filter_token_stream(tokens: Vec<Token>) -> Vec<Token> {
@@ -242,7 +381,7 @@ fn sanitize_string(mut eq: String) -> error::Result<String> {
     
     //Replace \operatorname{name} with \name for simplicity
     while let Some(i) = eq.find("operatorname{") {
-        if let Some(j) = eq.find("}") {
+        if let Some(j) = eq[i..].find('}').map(|j| i + j) {
             let name= &eq[(i + "operatorname{".len())..j];
             let owned = eq[..i].to_owned();
             eq = owned + name + &eq[j+1..];
@@ -32,4 +32,9 @@ pub(super) fn get_terms(tokens: &[Token]) -> error::Result<Vec<Term>> {
         .filter(|e| !e.range.is_empty())
         .collect()
     )
-}
\ No newline at end of file
+}
+/// Splits the arguments of a function call by the commas of the top level
+pub(super) fn split_arguments(tokens: &[Token]) -> Vec<&[Token]> {
+    tokens.split(|e| matches!(e.punctuation(), Some(p) if p.ch == ','))
+        .collect()
+}
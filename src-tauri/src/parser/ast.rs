@@ -1,8 +1,16 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
+use crate::error::{self, AppError};
+
 use super::operations::{BinaryOperation, NAryOperation, UnaryOperation};
 
-#[derive(Debug, Clone)]
+/// The version of the JSON representation of the trees, it must change with any change of the variants or their fields
+pub const AST_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Node {
     Constant {
         value: f64
@@ -29,6 +37,9 @@ pub enum Node {
     Unknown {
         name: String
     },
+    Matrix {
+        rows: Vec<Vec<Node>>,
+    },
 }
 
 impl Display for Node {
@@ -37,17 +48,55 @@ impl Display for Node {
             Node::NAry { op_type,.. } => write!(f, "N-ary {{ {:?} }}", op_type),
             Node::Binary { op_type, .. } => write!(f, "Binary {{ {:?} }}", op_type),
             Node::Unary { op_type, .. } => write!(f, "Unary {{ {:?} }}", op_type),
+            Node::Matrix { rows } => write!(f, "Matrix {{ {}x{} }}", rows.len(), rows.first().map_or(0, Vec::len)),
             _ => write!(f, "{:?}", self)
         }
     }
 }
 
+/// A tree written in JSON with the version of its representation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionedTree {
+    pub version: u32,
+    pub root: Node,
+}
+
 impl Node {
+    pub fn to_json(&self) -> error::Result<String> {
+        serde_json::to_string(&VersionedTree { version: AST_VERSION, root: self.clone() })
+            .map_err(|e| AppError::IoError(format!("The tree couldn't be written in JSON: {e}")))
+    }
+
+    /// Reads a tree written by to_json, it must have the same version
+    pub fn from_json(json: &str) -> error::Result<Self> {
+        let tree: VersionedTree = serde_json::from_str(json)
+            .map_err(|e| AppError::ParseError(format!("The JSON isn't a valid tree: {e}")))?;
+
+        if tree.version != AST_VERSION {
+            return Err(AppError::ParseError(format!("The version {} of the tree isn't supported, it must be {AST_VERSION}", tree.version)));
+        }
+
+        Ok(tree.root)
+    }
+
     pub fn print_tree(&self) {
         println!("-Root");
         print_tree("", self, true);
     } 
 
+    /// The name of the first unknown found in the tree
+    pub fn first_unknown(&self) -> Option<String> {
+        match self {
+            Node::Unknown { name } => Some(name.clone()),
+            Node::Unary { child, .. } => child.as_ref().and_then(|c| c.first_unknown()),
+            Node::Binary { lhs, rhs, .. } => lhs.as_ref().and_then(|l| l.first_unknown())
+                .or_else(|| rhs.as_ref().and_then(|r| r.first_unknown())),
+            Node::NAry { children, .. } => children.iter().find_map(|c| c.first_unknown()),
+            Node::Matrix { rows } => rows.iter().flatten().find_map(|c| c.first_unknown()),
+            _ => None,
+        }
+    }
+
     pub fn add(a: Node, b: Node) -> Self {
         Self::NAry { 
             op_type: NAryOperation::Add, 
@@ -117,6 +166,12 @@ fn print_tree(prefix: &str, root: &Node, last: bool) {
                 print_tree(&new_prefix, n, i==children.len()-1);
             }
         }
+        Node::Matrix { rows } => {
+            let entries: Vec<&Node> = rows.iter().flatten().collect();
+            for (i, n) in entries.iter().enumerate() {
+                print_tree(&new_prefix, n, i==entries.len()-1);
+            }
+        }
         _ => (),
     }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::f64::consts::{E, PI, SQRT_2};
+
+use crate::error::{self, AppError};
+
+use super::{ast::Node, operations::Distribution, BinaryOperation, UnaryOperation};
+
+/// Writes the probability functions with the operations the compiler already knows, so they can be drawn and derived
+pub fn distribution(dist: Distribution, mut args: Vec<Node>) -> error::Result<Node> {
+    let expected = match dist {
+        Distribution::NormalPdf | Distribution::NormalCdf => "(x) or (x, μ, σ)",
+        Distribution::BinomPdf => "(k, n, p)",
+        Distribution::PoissonPdf => "(k, λ)",
+    };
+    let name = format!("{dist:?}").to_lowercase();
+    let wrong_arguments = || AppError::ParseError(format!("It must be written as {name}{expected}"));
+
+    match dist {
+        Distribution::NormalPdf | Distribution::NormalCdf => {
+            let (x, mu, sigma) = match args.len() {
+                1 => (args.remove(0), Node::Constant { value: 0.0 }, Node::Constant { value: 1.0 }),
+                3 => {
+                    let sigma = args.pop().ok_or_else(wrong_arguments)?;
+                    let mu = args.pop().ok_or_else(wrong_arguments)?;
+                    (args.remove(0), mu, sigma)
+                }
+                _ => return Err(wrong_arguments()),
+            };
+            let z = Node::divide(Node::substract(x, mu), sigma.clone());
+
+            if dist == Distribution::NormalPdf { // e^(-z^2/2) / σ·sqrt(2π)
+                Ok(Node::divide(
+                    power(Node::Constant { value: E }, Node::divide(Node::op(UnaryOperation::Minus, power(z, Node::Constant { value: 2.0 })), Node::Constant { value: 2.0 })),
+                    Node::multiply(sigma, Node::Constant { value: (2.0 * PI).sqrt() }),
+                ))
+            } else { // (1 + erf(z/sqrt(2))) / 2
+                Ok(Node::divide(
+                    Node::add(Node::Constant { value: 1.0 }, Node::op(UnaryOperation::Erf, Node::divide(z, Node::Constant { value: SQRT_2 }))),
+                    Node::Constant { value: 2.0 },
+                ))
+            }
+        }
+        Distribution::BinomPdf => { // n! / k!(n-k)! · p^k · (1-p)^(n-k)
+            let [k, n, p]: [Node; 3] = args.try_into().map_err(|_| wrong_arguments())?;
+            let rest = Node::substract(n.clone(), k.clone());
+
+            let combinations = Node::divide(
+                Node::op(UnaryOperation::Fact, n),
+                Node::multiply(Node::op(UnaryOperation::Fact, k.clone()), Node::op(UnaryOperation::Fact, rest.clone())),
+            );
+            Ok(Node::multiply(
+                combinations,
+                Node::multiply(power(p.clone(), k), power(Node::substract(Node::Constant { value: 1.0 }, p), rest)),
+            ))
+        }
+        Distribution::PoissonPdf => { // λ^k · e^-λ / k!
+            let [k, lambda]: [Node; 2] = args.try_into().map_err(|_| wrong_arguments())?;
+
+            Ok(Node::divide(
+                Node::multiply(power(lambda.clone(), k.clone()), power(Node::Constant { value: E }, Node::op(UnaryOperation::Minus, lambda))),
+                Node::op(UnaryOperation::Fact, k),
+            ))
+        }
+    }
+}
+
+fn power(base: Node, exponent: Node) -> Node {
+    Node::Binary { op_type: BinaryOperation::Power, lhs: Some(Box::new(base)), rhs: Some(Box::new(exponent)) }
+}
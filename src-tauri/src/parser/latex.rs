@@ -0,0 +1,112 @@
+use std::f64::consts::{E, PI};
+
+use super::{ast::Node, series::format_number, BinaryOperation, NAryOperation, UnaryOperation};
+
+/// Writes the tree in LaTeX as MathQuill would write it, with parentheses only where the parser needs them to read the same tree back
+pub fn to_latex(root: &Node) -> String {
+    match root {
+        Node::Constant { value } => constant(*value),
+        Node::Variable { name } => match name.split_once('_') {
+            Some((name, subscript)) => format!("{name}_{{{subscript}}}"),
+            None => name.to_owned(),
+        },
+        Node::Unknown { name } => name.to_owned(),
+        Node::Unary { op_type, child: Some(child) } => unary(op_type, child),
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => match op_type {
+            BinaryOperation::Division => format!("\\frac{{{}}}{{{}}}", to_latex(lhs), to_latex(rhs)),
+            BinaryOperation::Power => format!("{}^{{{}}}", atom(lhs), to_latex(rhs)),
+            BinaryOperation::Equal => format!("{}={}", to_latex(lhs), to_latex(rhs)),
+        },
+        Node::NAry { op_type: NAryOperation::Add, children } => {
+            children.iter().enumerate().fold(String::new(), |latex, (i, child)| {
+                let term = to_latex(child);
+                if i == 0 || term.starts_with('-') { latex + &term } else { latex + "+" + &term }
+            })
+        }
+        Node::NAry { op_type: NAryOperation::Multiply, children } => product(children),
+        Node::Matrix { rows } => {
+            let rows: Vec<String> = rows.iter()
+                .map(|row| row.iter().map(to_latex).collect::<Vec<_>>().join("&"))
+                .collect();
+            format!("\\begin{{pmatrix}}{}\\end{{pmatrix}}", rows.join("\\\\"))
+        }
+        _ => String::new(),
+    }
+}
+
+fn constant(value: f64) -> String {
+    match value {
+        v if v == PI => "\\pi".to_owned(),
+        v if v == E => "e".to_owned(),
+        v if v == -PI => "-\\pi".to_owned(),
+        v if v == -E => "-e".to_owned(),
+        v => format_number(v),
+    }
+}
+
+fn unary(op_type: &UnaryOperation, child: &Node) -> String {
+    let argument = to_latex(child);
+    match op_type {
+        //The terms and the negative numbers would be read as a subtraction
+        UnaryOperation::Minus if argument.starts_with('-') || is_sum(child) => format!("-{}", parenthesis(&argument)),
+        UnaryOperation::Minus => format!("-{argument}"),
+        UnaryOperation::Sqrt => format!("\\sqrt{{{argument}}}"),
+        UnaryOperation::Fact => format!("{}!", atom(child)),
+        UnaryOperation::Sin | UnaryOperation::Cos | UnaryOperation::Tan | UnaryOperation::Ln | UnaryOperation::Log | UnaryOperation::Det => {
+            format!("\\{}{}", name(op_type), parenthesis(&argument))
+        }
+        _ => format!("\\operatorname{{{}}}{}", name(op_type), parenthesis(&argument)),
+    }
+}
+
+/// The factors are written next to each other, with a \cdot before the numbers so they aren't read as one number
+fn product(children: &[Box<Node>]) -> String {
+    let mut latex = String::new();
+    for (i, child) in children.iter().enumerate() {
+        let factor = to_latex(child);
+
+        let factor = if i == 0 {
+            match child.as_ref() {
+                Node::Constant { value } if *value == -1.0 && children.len() > 1 => "-".to_owned(),
+                Node::Constant { value } if *value == 1.0 && children.len() > 1 => continue,
+                _ if is_sum(child) => parenthesis(&factor),
+                _ => factor,
+            }
+        } else if factor.starts_with('-') || is_sum(child) {
+            parenthesis(&factor)
+        } else {
+            factor
+        };
+
+        if !latex.is_empty() && latex != "-" && factor.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            latex += "\\cdot";
+        } else if latex.ends_with("\\pi") && factor.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            latex += " ";
+        }
+        latex += &factor;
+    }
+
+    latex
+}
+
+/// The bases of the powers and the factorials, the parser only reads them right if they are a single symbol or a number
+fn atom(root: &Node) -> String {
+    let latex = to_latex(root);
+    match root {
+        Node::Variable { .. } | Node::Unknown { .. } | Node::Matrix { .. } => latex,
+        Node::Constant { value } if *value >= 0.0 && !latex.contains('\\') || latex == "\\pi" => latex,
+        _ => parenthesis(&latex),
+    }
+}
+
+fn is_sum(root: &Node) -> bool {
+    matches!(root, Node::NAry { op_type: NAryOperation::Add, .. } | Node::Binary { op_type: BinaryOperation::Equal, .. })
+}
+
+fn parenthesis(latex: &str) -> String {
+    format!("\\left({latex}\\right)")
+}
+
+fn name(op_type: &UnaryOperation) -> String {
+    format!("{op_type:?}").to_lowercase()
+}
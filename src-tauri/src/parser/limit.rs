@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use crate::{compiler::ast_unknowns, error::{self, AppError}};
+
+use super::{ast::Node, parse_latex, simplifier::{derive_function, simplify_tree, substitute_func}, solver::variable_to_unknown, BinaryOperation};
+
+const LHOPITAL_DEPTH: usize = 4;
+const SAMPLES: i32 = 40;
+const RICHARDSON_SAMPLES: usize = 10;
+/// The distance to the point where the function is sampled first, every other sample is twice as close
+const FIRST_STEP: f64 = 1e-2;
+/// The distance where a function is checked to be continuous
+const PROBE_STEP: f64 = 1e-7;
+
+/// The side a limit is approached from, x→a^{-} is from the left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Both, Left, Right
+}
+
+/// A limit written as \lim_{x\to a}f(x), the variable of the limit is the unknown of the function
+#[derive(Debug, Clone)]
+pub struct Limit {
+    pub body: Node,
+    pub target: f64,
+    pub side: Side,
+}
+
+/// What the samples of a function do as they get closer to the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Approach {
+    Converges(f64),
+    /// The sign of the infinity it diverges to
+    Diverges(f64),
+    NoLimit,
+}
+
+impl Limit {
+    /// Reads \lim_{x\to a}f(x), where a can be \infty and have a side like a^{+}. It's None if the expression isn't a limit
+    pub fn parse(eq: &str, func_map: &HashMap<String, Box<Node>>, variable_map: &HashMap<String, f64>) -> error::Result<Option<Self>> {
+        let Some(rest) = ["\\lim_", "\\operatorname{lim}_"].iter().find_map(|prefix| eq.trim().strip_prefix(prefix)) else {
+            return Ok(None);
+        };
+
+        let (subscript, body) = split_group(rest)
+            .ok_or_else(|| AppError::ParseError("A limit must be written as \\lim_{x\\to a}f(x)".to_owned()))?;
+        let (variable, target) = ["\\to", "\\rightarrow"].iter()
+            .find_map(|arrow| subscript.split_once(arrow))
+            .ok_or_else(|| AppError::ParseError("The subscript of a limit must be like x\\to a".to_owned()))?;
+
+        let (target, side) = [("^{+}", Side::Right), ("^+", Side::Right), ("^{-}", Side::Left), ("^-", Side::Left)].iter()
+            .find_map(|(suffix, side)| target.trim().strip_suffix(suffix).map(|t| (t, *side)))
+            .unwrap_or((target.trim(), Side::Both));
+        let target = match target.strip_prefix('+').unwrap_or(target).trim() {
+            "\\infty" => f64::INFINITY,
+            "-\\infty" => f64::NEG_INFINITY,
+            target => {
+                let mut root = parse_latex(target, func_map)?;
+                simplify_tree(&mut root, variable_map);
+                simplify_tree(&mut root, variable_map)
+                    .ok_or_else(|| AppError::MathError("The point of a limit must evaluate to a certain value".to_owned()))?
+            }
+        };
+
+        let mut body = parse_latex(body, func_map)?;
+        let (x, y) = ast_unknowns(&body)?;
+        match parse_latex(variable, func_map)? {
+            Node::Unknown { .. } if x && y => {
+                return Err(AppError::MathError("The function of a limit can only depend on one unknown".to_owned()));
+            }
+            Node::Unknown { .. } => (),
+            Node::Variable { .. } if x || y => {
+                return Err(AppError::MathError("The function of a limit can only depend on the variable of the limit".to_owned()));
+            }
+            Node::Variable { name } => variable_to_unknown(&mut body, &name),
+            _ => return Err(AppError::ParseError(format!("{variable} isn't the variable of a limit"))),
+        }
+
+        //Infinity is only approached from one side
+        let side = if target.is_infinite() { Side::Both } else { side };
+        Ok(Some(Self { body, target, side }))
+    }
+
+    /// Tries to evaluate the function in the point, then L'Hôpital's rule for 0/0 and ∞/∞ and finally the extrapolation of its values near the point
+    pub fn evaluate(&self, variable_map: &HashMap<String, f64>) -> error::Result<f64> {
+        if let Some(value) = self.lhopital(&self.body, variable_map, 0) {
+            return Ok(snap(value));
+        }
+
+        match self.approaches(variable_map)[..] {
+            [Approach::Converges(a)] => Ok(snap(a)),
+            [Approach::Converges(a), Approach::Converges(b)] if close(a, b, 1e-6) => Ok(snap((a + b) / 2.0)),
+            [Approach::Converges(a), Approach::Converges(b)] => Err(AppError::MathError(format!(
+                "The limit doesn't exist, it's {} from the left and {} from the right", snap(a), snap(b)
+            ))),
+            [Approach::Diverges(sign)] => Err(diverges(sign)),
+            [Approach::Diverges(a), Approach::Diverges(b)] if a == b => Err(diverges(a)),
+            _ => Err(AppError::MathError("The limit doesn't exist".to_owned())),
+        }
+    }
+
+    /// What the function does near the target from every side of the limit, the left one first
+    pub fn approaches(&self, variable_map: &HashMap<String, f64>) -> Vec<Approach> {
+        self.directions().iter().map(|&dir| self.approach(&self.body, dir, variable_map)).collect()
+    }
+
+    /// The value in the point if the function is continuous there, otherwise L'Hôpital's rule is applied to the quotients
+    fn lhopital(&self, f: &Node, variable_map: &HashMap<String, f64>, depth: usize) -> Option<f64> {
+        let value = value_at(f, self.target, variable_map);
+        let continuous = value.is_finite() && self.directions().iter()
+            .all(|&dir| close(value_at(f, self.point(dir, PROBE_STEP), variable_map), value, 1e-4));
+        if continuous {
+            return Some(value);
+        }
+
+        let Node::Binary { op_type: BinaryOperation::Division, lhs: Some(num), rhs: Some(den) } = f else {
+            return None;
+        };
+        let (n, d) = (value_at(num, self.target, variable_map), value_at(den, self.target, variable_map));
+        let indeterminate = (n.abs() < 1e-12 && d.abs() < 1e-12) || (n.is_infinite() && d.is_infinite());
+        if !indeterminate || depth == LHOPITAL_DEPTH {
+            return None;
+        }
+
+        let quotient = Node::divide(*derive_function(num).ok()?, *derive_function(den).ok()?);
+        self.lhopital(&quotient, variable_map, depth + 1)
+    }
+
+    /// Samples the function closer and closer to the target from one direction
+    fn approach(&self, f: &Node, dir: f64, variable_map: &HashMap<String, f64>) -> Approach {
+        let scale = if self.target.is_finite() { self.target.abs().max(1.0) } else { 1.0 };
+        let samples: Vec<f64> = (0..SAMPLES)
+            .map(|k| FIRST_STEP / 2f64.powi(k))
+            .take_while(|h| self.target.is_infinite() || h * scale >= 1e-12)
+            .map(|h| value_at(f, self.point(dir, h), variable_map))
+            .collect();
+
+        let tail = &samples[samples.len() - 6..];
+        if tail.iter().any(|v| v.is_nan()) {
+            return Approach::NoLimit;
+        }
+
+        //It diverges if the values have the same sign and grow without getting closer to each other
+        let sign = tail[0].signum();
+        let growing = tail.windows(2).all(|w| w[1].signum() == sign && (w[1].abs() > w[0].abs() || w[1].is_infinite()));
+        let not_contracting = tail.windows(3).all(|w| w[2].is_infinite() || (w[2] - w[1]).abs() >= 0.9 * (w[1] - w[0]).abs());
+        if growing && not_contracting {
+            return Approach::Diverges(sign);
+        } else if tail.iter().any(|v| v.is_infinite()) {
+            return Approach::NoLimit;
+        }
+
+        richardson(&samples[..RICHARDSON_SAMPLES])
+            .or_else(|| aitken(tail))
+            .map_or(Approach::NoLimit, Approach::Converges)
+    }
+
+    /// Where the function is sampled at a distance h of the target, the infinity is approached by 1/h
+    fn point(&self, dir: f64, h: f64) -> f64 {
+        if self.target.is_infinite() {
+            self.target.signum() / h
+        } else {
+            self.target + dir * h * self.target.abs().max(1.0)
+        }
+    }
+
+    fn directions(&self) -> &'static [f64] {
+        match self.side {
+            _ if self.target.is_infinite() => &[1.0],
+            Side::Both => &[-1.0, 1.0],
+            Side::Left => &[-1.0],
+            Side::Right => &[1.0],
+        }
+    }
+}
+
+/// Splits {content}rest into the content of the group and the rest
+fn split_group(s: &str) -> Option<(&str, &str)> {
+    let s = s.strip_prefix('{')?;
+
+    let mut depth = 1;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some((&s[..i], &s[i + 1..])),
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// The value of the function in x, it's NaN where it isn't defined
+fn value_at(f: &Node, x: f64, variable_map: &HashMap<String, f64>) -> f64 {
+    let mut f = f.clone();
+    if substitute_func(&mut f, &Node::Constant { value: x }).is_err() {
+        return f64::NAN;
+    }
+
+    simplify_tree(&mut f, variable_map);
+    simplify_tree(&mut f, variable_map).unwrap_or(f64::NAN)
+}
+
+/// Extrapolates the samples to h=0 supposing the error is a power series of h, as every sample halves h
+fn richardson(samples: &[f64]) -> Option<f64> {
+    if samples.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+
+    let mut table: Vec<Vec<f64>> = Vec::with_capacity(samples.len());
+    for (k, &sample) in samples.iter().enumerate() {
+        let mut row = vec![sample];
+        for j in 1..=k {
+            let factor = 2f64.powi(j as i32) - 1.0;
+            row.push(row[j - 1] + (row[j - 1] - table[k - 1][j - 1]) / factor);
+        }
+        table.push(row);
+    }
+
+    //The extrapolation with the smallest change is the most accurate before the rounding errors grow
+    table.windows(2)
+        .skip(1)
+        .map(|w| (w[1][w[1].len() - 1], (w[1][w[1].len() - 1] - w[0][w[0].len() - 1]).abs()))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|(value, change)| *change < 1e-8 * value.abs().max(1.0))
+        .map(|(value, _)| value)
+}
+
+/// Aitken's extrapolation of the last samples, for the ones that converge geometrically like sqrt(h) or have already converged
+fn aitken(tail: &[f64]) -> Option<f64> {
+    let [.., a, b, c] = tail else {
+        return None;
+    };
+    let (d1, d2) = (b - a, c - b);
+
+    if d1.abs().max(d2.abs()) < 1e-10 * c.abs().max(1.0) {
+        Some(*c)
+    } else if d2.abs() >= 0.9 * d1.abs() || d2.abs() > 1e-6 * c.abs().max(1.0) {
+        None
+    } else {
+        Some(c - d2 * d2 / (d2 - d1))
+    }
+}
+
+/// Relative comparison, it's absolute near 0
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() < tolerance * a.abs().max(1.0)
+}
+
+/// Rounds away the errors of the extrapolation when the limit is an integer
+fn snap(value: f64) -> f64 {
+    if (value - value.round()).abs() < 1e-9 {
+        value.round() + 0.0
+    } else {
+        value
+    }
+}
+
+fn diverges(sign: f64) -> AppError {
+    if sign > 0.0 {
+        AppError::MathError("The limit diverges to infinity".to_owned())
+    } else {
+        AppError::MathError("The limit diverges to minus infinity".to_owned())
+    }
+}
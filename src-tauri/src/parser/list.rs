@@ -0,0 +1,44 @@
+use crate::error::{self, AppError};
+
+use super::UnaryOperation;
+
+/// Reduces a list to a number with a statistic like its mean
+pub fn aggregate(op: &UnaryOperation, list: &[f64]) -> error::Result<f64> {
+    if list.is_empty() {
+        return Err(AppError::MathError(format!("The {op:?} of an empty list isn't defined")));
+    }
+
+    match op {
+        UnaryOperation::Mean => Ok(mean(list)),
+        UnaryOperation::Median => Ok(median(list)),
+        UnaryOperation::Var => variance(list),
+        UnaryOperation::Stdev => Ok(variance(list)?.sqrt()),
+        _ => Err(AppError::MathError(format!("{op:?} doesn't make a number out of a list"))),
+    }
+}
+
+fn mean(list: &[f64]) -> f64 {
+    list.iter().sum::<f64>() / list.len() as f64
+}
+
+fn median(list: &[f64]) -> f64 {
+    let mut sorted = list.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        sorted[middle]
+    } else {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    }
+}
+
+/// The sample variance, it's divided by n-1
+fn variance(list: &[f64]) -> error::Result<f64> {
+    if list.len() < 2 {
+        return Err(AppError::MathError("The variance of a sample needs at least two elements".to_owned()));
+    }
+
+    let mean = mean(list);
+    Ok(list.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / (list.len() - 1) as f64)
+}
@@ -0,0 +1,617 @@
+use std::f64::consts::{E, PI};
+
+use crate::error::{self, AppError};
+
+use super::{ast::Node, BinaryOperation, NAryOperation, UnaryOperation};
+
+const NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+const INVISIBLE_TIMES: char = '\u{2062}';
+const APPLY_FUNCTION: char = '\u{2061}';
+
+/// The functions written as <mi>name</mi>, with the same names as in LaTeX
+const FUNCTIONS: [(&str, UnaryOperation); 16] = [
+    ("sin", UnaryOperation::Sin), ("cos", UnaryOperation::Cos), ("tan", UnaryOperation::Tan), ("floor", UnaryOperation::Floor),
+    ("ceil", UnaryOperation::Ceil), ("abs", UnaryOperation::Abs), ("log", UnaryOperation::Log), ("ln", UnaryOperation::Ln),
+    ("det", UnaryOperation::Det), ("eig", UnaryOperation::Eig), ("mean", UnaryOperation::Mean), ("median", UnaryOperation::Median),
+    ("stdev", UnaryOperation::Stdev), ("var", UnaryOperation::Var), ("erf", UnaryOperation::Erf), ("erfc", UnaryOperation::Erfc),
+];
+
+/// The elements and the text of an XML document, the attributes are ignored as they don't change the meaning of the formula
+#[derive(Debug, Clone, PartialEq)]
+enum Xml {
+    Element { name: String, children: Vec<Xml> },
+    Text(String),
+}
+
+/// Builds the tree of a formula written in Presentation MathML, like the ones of the documentation tools
+pub fn parse_mathml(mathml: &str) -> error::Result<Node> {
+    let mut reader = XmlReader { chars: mathml.chars().collect(), pos: 0 };
+    let nodes = reader.content()?;
+    if reader.pos < reader.chars.len() {
+        return Err(AppError::ParseError(format!("Unexpected closing tag in the MathML at {}", reader.pos)));
+    }
+
+    let root = match nodes.iter().find(|n| matches!(n, Xml::Element { .. })) {
+        Some(Xml::Element { name, children }) if name == "math" => children,
+        _ => return Err(AppError::ParseError("The MathML must be inside a <math> element".to_owned())),
+    };
+    row(root)
+}
+
+/// Writes the tree in Presentation MathML, which shows the formula as it's written
+pub fn to_presentation_mathml(root: &Node) -> String {
+    format!("<math xmlns=\"{NAMESPACE}\">{}</math>", presentation(root))
+}
+
+/// Writes the tree in Content MathML, which describes the meaning of the formula
+pub fn to_content_mathml(root: &Node) -> String {
+    format!("<math xmlns=\"{NAMESPACE}\">{}</math>", content(root))
+}
+
+struct XmlReader {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl XmlReader {
+    /// Reads the elements and the text until a closing tag or the end
+    fn content(&mut self) -> error::Result<Vec<Xml>> {
+        let mut nodes = Vec::new();
+        loop {
+            let rest: String = self.chars[self.pos..self.chars.len().min(self.pos + 9)].iter().collect();
+            if self.pos >= self.chars.len() || rest.starts_with("</") {
+                return Ok(nodes);
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with('<') {
+                nodes.push(self.element()?);
+            } else {
+                let text = self.text()?;
+                if !text.trim().is_empty() {
+                    nodes.push(Xml::Text(text.trim().to_owned()));
+                }
+            }
+        }
+    }
+
+    fn element(&mut self) -> error::Result<Xml> {
+        self.pos += 1;
+        let name = self.name();
+
+        //The attributes are skipped, the quotes can have > inside
+        let mut quote = None;
+        while let Some(&c) = self.chars.get(self.pos) {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                (None, '>') => break,
+                _ => (),
+            }
+            self.pos += 1;
+        }
+        if self.pos >= self.chars.len() {
+            return Err(AppError::ParseError(format!("The tag <{name}> isn't closed")));
+        }
+
+        let empty = self.chars[self.pos - 1] == '/';
+        self.pos += 1;
+        if empty {
+            return Ok(Xml::Element { name, children: Vec::new() });
+        }
+
+        let children = self.content()?;
+        if self.pos >= self.chars.len() {
+            return Err(AppError::ParseError(format!("The element <{name}> isn't closed")));
+        }
+        self.pos += 2;
+        let closing = self.name();
+        self.skip_past(">")?;
+        if closing != name {
+            return Err(AppError::ParseError(format!("The element <{name}> is closed by </{closing}>")));
+        }
+
+        Ok(Xml::Element { name, children })
+    }
+
+    /// The name of a tag without its namespace prefix, like m:mi
+    fn name(&mut self) -> String {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| !c.is_whitespace() && !matches!(c, '>' | '/')) {
+            self.pos += 1;
+        }
+
+        let name: String = self.chars[start..self.pos].iter().collect();
+        name.rsplit(':').next().unwrap_or_default().to_owned()
+    }
+
+    fn text(&mut self) -> error::Result<String> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.get(self.pos) {
+            match c {
+                '<' => break,
+                '&' => {
+                    let end = self.chars[self.pos..].iter().position(|&c| c == ';')
+                        .ok_or_else(|| AppError::ParseError("An entity of the MathML isn't closed by ;".to_owned()))?;
+                    let entity: String = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                    text.push(decode_entity(&entity)?);
+                    self.pos += end + 1;
+                }
+                c => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        Ok(text)
+    }
+
+    fn skip_past(&mut self, end: &str) -> error::Result<()> {
+        let end: Vec<char> = end.chars().collect();
+        let i = self.chars[self.pos..].windows(end.len()).position(|w| w == end.as_slice())
+            .ok_or_else(|| AppError::ParseError("The MathML ends unexpectedly".to_owned()))?;
+        self.pos += i + end.len();
+        Ok(())
+    }
+}
+
+fn decode_entity(entity: &str) -> error::Result<char> {
+    let code = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(decimal) = entity.strip_prefix('#') {
+        decimal.parse().ok()
+    } else {
+        None
+    };
+    if let Some(c) = code.and_then(char::from_u32) {
+        return Ok(c);
+    }
+
+    match entity {
+        "lt" => Ok('<'),
+        "gt" => Ok('>'),
+        "amp" => Ok('&'),
+        "quot" => Ok('"'),
+        "apos" => Ok('\''),
+        "pi" => Ok('π'),
+        "minus" => Ok('−'),
+        "times" => Ok('×'),
+        "sdot" | "middot" | "centerdot" => Ok('·'),
+        "InvisibleTimes" | "it" => Ok(INVISIBLE_TIMES),
+        "ApplyFunction" | "af" => Ok(APPLY_FUNCTION),
+        "ExponentialE" | "ee" => Ok('e'),
+        "lfloor" => Ok('⌊'),
+        "rfloor" => Ok('⌋'),
+        "lceil" => Ok('⌈'),
+        "rceil" => Ok('⌉'),
+        "verbar" | "vert" => Ok('|'),
+        _ => Err(AppError::ParseError(format!("The entity &{entity}; isn't supported"))),
+    }
+}
+
+/// A row of Presentation MathML, the operators are read with their precedence
+struct RowParser<'a> {
+    items: Vec<&'a Xml>,
+    pos: usize,
+    /// Inside |x| the next | closes the absolute value instead of opening another one
+    in_abs: bool,
+}
+
+fn row(items: &[Xml]) -> error::Result<Node> {
+    let items: Vec<&Xml> = items.iter()
+        .filter(|e| !matches!(e, Xml::Element { name, .. } if name == "mspace" || name == "none"))
+        .filter(|e| operator(e) != Some(APPLY_FUNCTION.to_string()))
+        .collect();
+
+    let mut parser = RowParser { items, pos: 0, in_abs: false };
+    let node = parser.equation()?;
+    match parser.items.get(parser.pos) {
+        None => Ok(node),
+        Some(item) => Err(AppError::ParseError(format!("Unexpected {} in the MathML", describe(item)))),
+    }
+}
+
+/// The text of an <mo>, with the symbols that mean the same normalized
+fn operator(item: &Xml) -> Option<String> {
+    match item {
+        Xml::Element { name, children } if name == "mo" => {
+            let text = text_of(children);
+            Some(match text.as_str() {
+                "−" | "‐" => "-".to_owned(),
+                "·" | "⋅" | "×" | "∙" => "*".to_owned(),
+                "∕" | "÷" => "/".to_owned(),
+                t if t == INVISIBLE_TIMES.to_string() => "*".to_owned(),
+                _ => text,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn text_of(children: &[Xml]) -> String {
+    children.iter().map(|c| match c {
+        Xml::Text(t) => t.to_owned(),
+        Xml::Element { children, .. } => text_of(children),
+    }).collect()
+}
+
+fn describe(item: &Xml) -> String {
+    match item {
+        Xml::Element { name, children } => format!("<{name}>{}", text_of(children)),
+        Xml::Text(t) => t.to_owned(),
+    }
+}
+
+impl RowParser<'_> {
+    fn peek_operator(&self) -> Option<String> {
+        self.items.get(self.pos).and_then(|e| operator(e))
+    }
+
+    fn expect(&mut self, symbol: &str) -> error::Result<()> {
+        if self.peek_operator().as_deref() == Some(symbol) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(AppError::ParseError(format!("Missing {symbol} in the MathML")))
+        }
+    }
+
+    fn equation(&mut self) -> error::Result<Node> {
+        let lhs = self.sum()?;
+        if self.peek_operator().as_deref() == Some("=") {
+            self.pos += 1;
+            let rhs = self.sum()?;
+            return Ok(Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(Box::new(lhs)), rhs: Some(Box::new(rhs)) });
+        }
+
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> error::Result<Node> {
+        let mut terms = vec![Box::new(self.product()?)];
+        loop {
+            let subtract = match self.peek_operator().as_deref() {
+                Some("-") => true,
+                Some("+") => false,
+                _ => break,
+            };
+            self.pos += 1;
+
+            let term = self.product()?;
+            terms.push(Box::new(if subtract { Node::op(UnaryOperation::Minus, term) } else { term }));
+        }
+
+        match terms.len() {
+            1 => Ok(*terms.remove(0)),
+            _ => Ok(Node::NAry { op_type: NAryOperation::Add, children: terms }),
+        }
+    }
+
+    fn product(&mut self) -> error::Result<Node> {
+        let mut factors = vec![self.postfix()?];
+        loop {
+            match self.peek_operator().as_deref() {
+                Some("*") => {
+                    self.pos += 1;
+                    factors.push(self.postfix()?);
+                }
+                Some("/") => {
+                    self.pos += 1;
+                    let num = collect_product(std::mem::take(&mut factors));
+                    factors.push(Node::divide(num, self.postfix()?));
+                }
+                Some("|") if self.in_abs => break,
+                //The operands next to each other are multiplied, like 2x
+                Some("(" | "[" | "{" | "|" | "⌊" | "⌈") | None if self.pos < self.items.len() => factors.push(self.postfix()?),
+                _ => break,
+            }
+        }
+
+        Ok(collect_product(factors))
+    }
+
+    fn postfix(&mut self) -> error::Result<Node> {
+        let mut node = self.primary()?;
+        while self.peek_operator().as_deref() == Some("!") {
+            self.pos += 1;
+            node = Node::op(UnaryOperation::Fact, node);
+        }
+
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> error::Result<Node> {
+        let item = *self.items.get(self.pos)
+            .ok_or_else(|| AppError::ParseError("An operand is missing in the MathML".to_owned()))?;
+        self.pos += 1;
+
+        if let Some(symbol) = operator(item) {
+            let (close, op_type) = match symbol.as_str() {
+                "(" => (")", None),
+                "[" => ("]", None),
+                "{" => ("}", None),
+                "|" => ("|", Some(UnaryOperation::Abs)),
+                "⌊" => ("⌋", Some(UnaryOperation::Floor)),
+                "⌈" => ("⌉", Some(UnaryOperation::Ceil)),
+                "-" => return Ok(Node::op(UnaryOperation::Minus, self.postfix()?)),
+                s => return Err(AppError::ParseError(format!("The operator {s} isn't expected in the MathML"))),
+            };
+
+            let in_abs = std::mem::replace(&mut self.in_abs, close == "|");
+            let inner = self.sum();
+            self.in_abs = in_abs;
+            self.expect(close)?;
+            return Ok(match op_type {
+                Some(op_type) => Node::op(op_type, inner?),
+                None => inner?,
+            });
+        }
+
+        let Xml::Element { name, children } = item else {
+            return Err(AppError::ParseError(format!("The text {} must be inside an element of the MathML", describe(item))));
+        };
+        let child = |i: usize| children.iter().filter(|c| matches!(c, Xml::Element { .. })).nth(i)
+            .ok_or_else(|| AppError::ParseError(format!("The element <{name}> is missing an argument")));
+
+        match name.as_str() {
+            "mn" => {
+                let text = text_of(children).replace('−', "-");
+                let value = text.parse().map_err(|_| AppError::ParseError(format!("Couldn't parse number {text}")))?;
+                Ok(Node::Constant { value })
+            }
+            "mi" => {
+                let text = text_of(children);
+                match function(&text) {
+                    Some(op_type) => Ok(Node::op(op_type, self.postfix()?)),
+                    None => identifier(&text),
+                }
+            }
+            "mrow" | "mstyle" | "mpadded" | "mfenced" => row(children),
+            "semantics" => row(std::slice::from_ref(child(0)?)),
+            "mfrac" => Ok(Node::divide(row(std::slice::from_ref(child(0)?))?, row(std::slice::from_ref(child(1)?))?)),
+            "msqrt" => Ok(Node::op(UnaryOperation::Sqrt, row(children)?)),
+            "mroot" => {
+                let index = Node::divide(Node::Constant { value: 1.0 }, row(std::slice::from_ref(child(1)?))?);
+                Ok(power(row(std::slice::from_ref(child(0)?))?, index))
+            }
+            "msup" => {
+                let exponent = row(std::slice::from_ref(child(1)?))?;
+                //sin²x is (sin x)²
+                match child(0)? {
+                    Xml::Element { name, children } if name == "mi" && function(&text_of(children)).is_some() => {
+                        let op_type = function(&text_of(children)).unwrap_or(UnaryOperation::Sin);
+                        Ok(power(Node::op(op_type, self.postfix()?), exponent))
+                    }
+                    base => Ok(power(row(std::slice::from_ref(base))?, exponent)),
+                }
+            }
+            "msub" => self.subscript(child(0)?, child(1)?),
+            "msubsup" => {
+                let base = self.subscript(child(0)?, child(1)?)?;
+                Ok(power(base, row(std::slice::from_ref(child(2)?))?))
+            }
+            "mtable" => {
+                let rows: error::Result<Vec<Vec<Node>>> = element_children(children, "mtr")
+                    .map(|row_children| element_children(row_children, "mtd").map(row).collect())
+                    .collect();
+                Ok(Node::Matrix { rows: rows? })
+            }
+            name => Err(AppError::ParseError(format!("The element <{name}> isn't supported"))),
+        }
+    }
+
+    /// The variables with subscripts like a_1 and the logarithms in other bases
+    fn subscript(&mut self, base: &Xml, subscript: &Xml) -> error::Result<Node> {
+        let (Xml::Element { children: name, .. }, Xml::Element { children: index, .. }) = (base, subscript) else {
+            return Err(AppError::ParseError("The subscripts must be elements".to_owned()));
+        };
+
+        match text_of(name).as_str() {
+            "log" => {
+                let base = row(std::slice::from_ref(subscript))?;
+                let argument = self.postfix()?;
+                Ok(Node::divide(Node::op(UnaryOperation::Ln, argument), Node::op(UnaryOperation::Ln, base)))
+            }
+            name => Ok(Node::Variable { name: format!("{name}_{}", text_of(index)) }),
+        }
+    }
+}
+
+fn element_children<'a>(children: &'a [Xml], element: &'a str) -> impl Iterator<Item = &'a [Xml]> {
+    children.iter().filter_map(move |c| match c {
+        Xml::Element { name, children } if name == element => Some(children.as_slice()),
+        _ => None,
+    })
+}
+
+fn function(name: &str) -> Option<UnaryOperation> {
+    FUNCTIONS.iter().find(|(n, _)| *n == name).map(|(_, op)| op.clone())
+}
+
+fn identifier(name: &str) -> error::Result<Node> {
+    match name {
+        "x" | "y" => Ok(Node::Unknown { name: name.to_owned() }),
+        "π" => Ok(Node::Constant { value: PI }),
+        "e" => Ok(Node::Constant { value: E }),
+        name if name.chars().count() == 1 => Ok(Node::Variable { name: name.to_owned() }),
+        name => Err(AppError::ParseError(format!("{name} is not a known operation"))),
+    }
+}
+
+fn power(base: Node, exponent: Node) -> Node {
+    Node::Binary { op_type: BinaryOperation::Power, lhs: Some(Box::new(base)), rhs: Some(Box::new(exponent)) }
+}
+
+fn collect_product(mut factors: Vec<Node>) -> Node {
+    match factors.len() {
+        1 => factors.remove(0),
+        _ => Node::NAry { op_type: NAryOperation::Multiply, children: factors.into_iter().map(Box::new).collect() },
+    }
+}
+
+fn presentation(root: &Node) -> String {
+    match root {
+        Node::Constant { value } if *value < 0.0 => mrow(&[mo("−"), presentation(&Node::Constant { value: -value })]),
+        Node::Constant { value } if *value == PI => "<mi>π</mi>".to_owned(),
+        Node::Constant { value } if *value == E => "<mi>e</mi>".to_owned(),
+        Node::Constant { value } => format!("<mn>{value}</mn>"),
+        Node::Variable { name } => match name.split_once('_') {
+            Some((name, subscript)) if subscript.chars().all(|c| c.is_ascii_digit()) => format!("<msub><mi>{name}</mi><mn>{subscript}</mn></msub>"),
+            Some((name, subscript)) => format!("<msub><mi>{name}</mi><mi>{subscript}</mi></msub>"),
+            None => format!("<mi>{name}</mi>"),
+        },
+        Node::Unknown { name } => format!("<mi>{name}</mi>"),
+        Node::Unary { op_type, child: Some(child) } => match op_type {
+            UnaryOperation::Minus if is_sum(child) || starts_negative(child) => mrow(&[mo("−"), fenced(&presentation(child))]),
+            UnaryOperation::Minus => mrow(&[mo("−"), presentation(child)]),
+            UnaryOperation::Sqrt => format!("<msqrt>{}</msqrt>", presentation(child)),
+            UnaryOperation::Fact => mrow(&[atom(child), mo("!")]),
+            UnaryOperation::Abs => mrow(&[mo("|"), presentation(child), mo("|")]),
+            op_type => {
+                let name = format!("{op_type:?}").to_lowercase();
+                mrow(&[format!("<mi>{name}</mi>"), mo("&#x2061;"), fenced(&presentation(child))])
+            }
+        },
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => match op_type {
+            BinaryOperation::Division => format!("<mfrac>{}{}</mfrac>", presentation(lhs), presentation(rhs)),
+            BinaryOperation::Power => format!("<msup>{}{}</msup>", atom(lhs), presentation(rhs)),
+            BinaryOperation::Equal => mrow(&[presentation(lhs), mo("="), presentation(rhs)]),
+        },
+        Node::NAry { op_type: NAryOperation::Add, children } => {
+            let mut items = Vec::new();
+            for (i, child) in children.iter().enumerate() {
+                match child.as_ref() {
+                    Node::Unary { op_type: UnaryOperation::Minus, child: Some(c) } if i > 0 => {
+                        items.push(mo("−"));
+                        items.push(if is_sum(c) || starts_negative(c) { fenced(&presentation(c)) } else { presentation(c) });
+                    }
+                    Node::Constant { value } if i > 0 && *value < 0.0 => {
+                        items.push(mo("−"));
+                        items.push(presentation(&Node::Constant { value: -value }));
+                    }
+                    child => {
+                        if i > 0 {
+                            items.push(mo("+"));
+                        }
+                        items.push(presentation(child));
+                    }
+                }
+            }
+            mrow(&items)
+        }
+        Node::NAry { op_type: NAryOperation::Multiply, children } => {
+            let mut items = Vec::new();
+            for (i, child) in children.iter().enumerate() {
+                let factor = if is_sum(child) || (i > 0 && starts_negative(child)) { fenced(&presentation(child)) } else { presentation(child) };
+                if i > 0 {
+                    //The numbers next to each other would be read as one
+                    items.push(if factor.starts_with("<mn>") { mo("·") } else { mo("&#x2062;") });
+                }
+                items.push(factor);
+            }
+            mrow(&items)
+        }
+        Node::Matrix { rows } => {
+            let rows: String = rows.iter()
+                .map(|row| format!("<mtr>{}</mtr>", row.iter().map(|e| format!("<mtd>{}</mtd>", presentation(e))).collect::<String>()))
+                .collect();
+            fenced(&format!("<mtable>{rows}</mtable>"))
+        }
+        _ => "<mrow></mrow>".to_owned(),
+    }
+}
+
+fn content(root: &Node) -> String {
+    let apply = |operator: &str, children: &[&Node]| {
+        format!("<apply>{operator}{}</apply>", children.iter().map(|c| content(c)).collect::<String>())
+    };
+
+    match root {
+        Node::Constant { value } if *value == PI => "<pi/>".to_owned(),
+        Node::Constant { value } if *value == E => "<exponentiale/>".to_owned(),
+        Node::Constant { value } => format!("<cn>{value}</cn>"),
+        Node::Variable { name } | Node::Unknown { name } => format!("<ci>{name}</ci>"),
+        Node::Unary { op_type, child: Some(child) } => {
+            let operator = match op_type {
+                UnaryOperation::Minus => "<minus/>",
+                UnaryOperation::Sin => "<sin/>",
+                UnaryOperation::Cos => "<cos/>",
+                UnaryOperation::Tan => "<tan/>",
+                UnaryOperation::Floor => "<floor/>",
+                UnaryOperation::Ceil => "<ceiling/>",
+                UnaryOperation::Abs => "<abs/>",
+                UnaryOperation::Log => "<log/>",
+                UnaryOperation::Ln => "<ln/>",
+                UnaryOperation::Sqrt => "<root/>",
+                UnaryOperation::Fact => "<factorial/>",
+                UnaryOperation::Det => "<determinant/>",
+                UnaryOperation::Mean => "<mean/>",
+                UnaryOperation::Median => "<median/>",
+                UnaryOperation::Stdev => "<sdev/>",
+                UnaryOperation::Var => "<variance/>",
+                UnaryOperation::Eig => "<csymbol>eig</csymbol>",
+                UnaryOperation::Erf => "<csymbol>erf</csymbol>",
+                UnaryOperation::Erfc => "<csymbol>erfc</csymbol>",
+            };
+            apply(operator, &[child])
+        }
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => {
+            let operator = match op_type {
+                BinaryOperation::Division => "<divide/>",
+                BinaryOperation::Power => "<power/>",
+                BinaryOperation::Equal => "<eq/>",
+            };
+            apply(operator, &[lhs, rhs])
+        }
+        Node::NAry { op_type, children } => {
+            let operator = match op_type {
+                NAryOperation::Add => "<plus/>",
+                NAryOperation::Multiply => "<times/>",
+            };
+            apply(operator, &children.iter().map(|c| c.as_ref()).collect::<Vec<_>>())
+        }
+        Node::Matrix { rows } => {
+            let rows: String = rows.iter()
+                .map(|row| format!("<matrixrow>{}</matrixrow>", row.iter().map(content).collect::<String>()))
+                .collect();
+            format!("<matrix>{rows}</matrix>")
+        }
+        _ => String::new(),
+    }
+}
+
+fn mo(symbol: &str) -> String {
+    format!("<mo>{symbol}</mo>")
+}
+
+fn mrow(items: &[String]) -> String {
+    match items {
+        [item] => item.to_owned(),
+        items => format!("<mrow>{}</mrow>", items.concat()),
+    }
+}
+
+fn fenced(inner: &str) -> String {
+    mrow(&[mo("("), inner.to_owned(), mo(")")])
+}
+
+/// The bases of the powers and the factorials are single symbols or they are inside parentheses
+fn atom(root: &Node) -> String {
+    match root {
+        Node::Variable { .. } | Node::Unknown { .. } | Node::Matrix { .. } => presentation(root),
+        Node::Constant { value } if *value >= 0.0 => presentation(root),
+        _ => fenced(&presentation(root)),
+    }
+}
+
+fn is_sum(root: &Node) -> bool {
+    matches!(root, Node::NAry { op_type: NAryOperation::Add, .. } | Node::Binary { op_type: BinaryOperation::Equal, .. })
+}
+
+fn starts_negative(root: &Node) -> bool {
+    presentation(root).starts_with("<mrow><mo>−</mo>")
+}
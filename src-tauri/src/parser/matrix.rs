@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::ast_unknowns, error::{self, AppError}};
+
+use super::{ast::Node, polynomial::{solve_cubic, solve_quadratic}, series::format_number, BinaryOperation};
+
+/// A dense matrix of real numbers, it's sent to the frontend as a list of rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Vec<Vec<f64>>", try_from = "Vec<Vec<f64>>")]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(rows: Vec<Vec<f64>>) -> error::Result<Self> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if cols == 0 || rows.iter().any(|r| r.len() != cols) {
+            return Err(AppError::MathError("Every row of a matrix must have the same number of elements".to_owned()));
+        }
+
+        Ok(Self { rows: rows.len(), cols, data: rows.into_iter().flatten().collect() })
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut data = vec![0.0; n * n];
+        (0..n).for_each(|i| data[i * n + i] = 1.0);
+        Self { rows: n, cols: n, data }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        self.data[i * self.cols + j]
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn transpose(&self) -> Self {
+        let data = (0..self.cols)
+            .flat_map(|j| (0..self.rows).map(move |i| (i, j)))
+            .map(|(i, j)| self.get(i, j))
+            .collect();
+        Self { rows: self.cols, cols: self.rows, data }
+    }
+
+    pub fn scale(&self, c: f64) -> Self {
+        Self { data: self.data.iter().map(|e| e * c).collect(), ..self.clone() }
+    }
+
+    pub fn sum(&self, other: &Matrix) -> error::Result<Self> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return Err(AppError::MathError(format!("A {}x{} matrix can't be added to a {}x{} one", self.rows, self.cols, other.rows, other.cols)));
+        }
+
+        Ok(Self { data: self.data.iter().zip(&other.data).map(|(a, b)| a + b).collect(), ..self.clone() })
+    }
+
+    pub fn product(&self, other: &Matrix) -> error::Result<Self> {
+        if self.cols != other.rows {
+            return Err(AppError::MathError(format!("A {}x{} matrix can't be multiplied by a {}x{} one", self.rows, self.cols, other.rows, other.cols)));
+        }
+
+        let data = (0..self.rows)
+            .flat_map(|i| (0..other.cols).map(move |j| (i, j)))
+            .map(|(i, j)| (0..self.cols).map(|k| self.get(i, k) * other.get(k, j)).sum())
+            .collect();
+        Ok(Self { rows: self.rows, cols: other.cols, data })
+    }
+
+    pub fn power(&self, n: i32) -> error::Result<Self> {
+        self.check_square("be raised to a power")?;
+
+        //Exponentiation by squaring, about 2·log2(n) products
+        let mut base = if n < 0 { self.inverse()? } else { self.clone() };
+        let mut result = Self::identity(self.rows);
+        let mut n = n.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.product(&base)?;
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.product(&base)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Gaussian elimination with partial pivoting
+    pub fn determinant(&self) -> error::Result<f64> {
+        self.check_square("have a determinant")?;
+
+        let n = self.rows;
+        let mut a = self.data.clone();
+        let mut det = 1.0;
+        for col in 0..n {
+            let pivot = (col..n).max_by(|&r, &s| a[r * n + col].abs().total_cmp(&a[s * n + col].abs())).unwrap_or(col);
+            if a[pivot * n + col] == 0.0 {
+                return Ok(0.0);
+            }
+            if pivot != col {
+                (0..n).for_each(|j| a.swap(col * n + j, pivot * n + j));
+                det = -det;
+            }
+
+            det *= a[col * n + col];
+            for r in col+1..n {
+                let factor = a[r * n + col] / a[col * n + col];
+                (col..n).for_each(|j| a[r * n + j] -= factor * a[col * n + j]);
+            }
+        }
+
+        Ok(det)
+    }
+
+    /// Gauss-Jordan elimination of the matrix next to the identity
+    pub fn inverse(&self) -> error::Result<Self> {
+        self.check_square("be inverted")?;
+
+        let n = self.rows;
+        let scale = self.data.iter().fold(0.0, |acc: f64, e| acc.max(e.abs()));
+        let mut a = self.data.clone();
+        let mut inv = Self::identity(n).data;
+        for col in 0..n {
+            let pivot = (col..n).max_by(|&r, &s| a[r * n + col].abs().total_cmp(&a[s * n + col].abs())).unwrap_or(col);
+            if a[pivot * n + col].abs() <= 1e-12 * scale {
+                return Err(AppError::MathError("This matrix isn't invertible, its determinant is zero".to_owned()));
+            }
+            for j in 0..n {
+                a.swap(col * n + j, pivot * n + j);
+                inv.swap(col * n + j, pivot * n + j);
+            }
+
+            let p = a[col * n + col];
+            for j in 0..n {
+                a[col * n + j] /= p;
+                inv[col * n + j] /= p;
+            }
+
+            for r in (0..n).filter(|&r| r != col) {
+                let factor = a[r * n + col];
+                for j in 0..n {
+                    a[r * n + j] -= factor * a[col * n + j];
+                    inv[r * n + j] -= factor * inv[col * n + j];
+                }
+            }
+        }
+
+        Ok(Self { rows: n, cols: n, data: inv })
+    }
+
+    /// The real roots of the characteristic polynomial, only for matrices up to 3x3
+    pub fn eigenvalues(&self) -> error::Result<Vec<f64>> {
+        self.check_square("have eigenvalues")?;
+
+        let m = |i, j| self.get(i, j);
+        let mut eigenvalues = match self.rows {
+            1 => vec![m(0, 0)],
+            2 => solve_quadratic(1.0, -(m(0, 0) + m(1, 1)), self.determinant()?),
+            3 => {
+                let trace = m(0, 0) + m(1, 1) + m(2, 2);
+                let minors = m(0, 0)*m(1, 1) - m(0, 1)*m(1, 0) + m(0, 0)*m(2, 2) - m(0, 2)*m(2, 0) + m(1, 1)*m(2, 2) - m(1, 2)*m(2, 1);
+                solve_cubic(1.0, -trace, minors, -self.determinant()?)
+            }
+            _ => return Err(AppError::MathError("Only the eigenvalues of matrices up to 3x3 can be found".to_owned())),
+        };
+
+        if eigenvalues.is_empty() {
+            return Err(AppError::MathError("This matrix doesn't have any real eigenvalues".to_owned()));
+        }
+
+        eigenvalues.sort_by(f64::total_cmp);
+        Ok(eigenvalues)
+    }
+
+    pub fn to_latex(&self) -> String {
+        let rows: Vec<String> = (0..self.rows)
+            .map(|i| (0..self.cols).map(|j| format_number(self.get(i, j))).collect::<Vec<_>>().join("&"))
+            .collect();
+        format!("\\begin{{pmatrix}}{}\\end{{pmatrix}}", rows.join("\\\\"))
+    }
+
+    fn check_square(&self, action: &str) -> error::Result<()> {
+        if self.is_square() {
+            Ok(())
+        } else {
+            Err(AppError::MathError(format!("A {}x{} matrix can't {action}, it must be square", self.rows, self.cols)))
+        }
+    }
+}
+
+impl TryFrom<Vec<Vec<f64>>> for Matrix {
+    type Error = AppError;
+
+    fn try_from(rows: Vec<Vec<f64>>) -> error::Result<Self> {
+        Matrix::new(rows)
+    }
+}
+
+impl From<Matrix> for Vec<Vec<f64>> {
+    fn from(m: Matrix) -> Self {
+        m.data.chunks(m.cols).map(<[f64]>::to_vec).collect()
+    }
+}
+
+/// The image of the curve by the linear transformation, which is the curve evaluated in M^-1·(x, y)
+pub fn transform_curve(root: &Node, matrix: &Matrix) -> error::Result<Node> {
+    if matrix.rows() != 2 || matrix.cols() != 2 {
+        return Err(AppError::MathError("Only a 2x2 matrix can transform a curve".to_owned()));
+    }
+
+    let inv = matrix.inverse()?;
+    let (x, y) = (Node::Unknown { name: "x".to_owned() }, Node::Unknown { name: "y".to_owned() });
+    let combination = |a: f64, b: f64| Node::add(
+        Node::multiply(Node::Constant { value: a }, x.clone()),
+        Node::multiply(Node::Constant { value: b }, y.clone()),
+    );
+
+    //The curves without an equal sign are y=f(x) or x=f(y), like the compiler does
+    let mut curve = match root {
+        Node::Binary { op_type: BinaryOperation::Equal, .. } => root.clone(),
+        _ if !ast_unknowns(root)?.0 => Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(Box::new(root.clone())), rhs: Some(Box::new(x.clone())) },
+        _ => Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(Box::new(root.clone())), rhs: Some(Box::new(y.clone())) },
+    };
+
+    substitute_unknowns(&mut curve, &combination(inv.get(0, 0), inv.get(0, 1)), &combination(inv.get(1, 0), inv.get(1, 1)));
+    Ok(curve)
+}
+
+/// Substitutes both unknowns at the same time
+pub(super) fn substitute_unknowns(root: &mut Node, x: &Node, y: &Node) {
+    match root {
+        Node::Unknown { name } if name == "x" => *root = x.clone(),
+        Node::Unknown { name } if name == "y" => *root = y.clone(),
+        Node::Unary { child, .. } => child.iter_mut().for_each(|e| substitute_unknowns(e, x, y)),
+        Node::Binary { lhs, rhs, .. } => lhs.iter_mut().chain(rhs.iter_mut()).for_each(|e| substitute_unknowns(e, x, y)),
+        Node::NAry { children, .. } => children.iter_mut().for_each(|e| substitute_unknowns(e, x, y)),
+        Node::Matrix { rows } => rows.iter_mut().flatten().for_each(|e| substitute_unknowns(e, x, y)),
+        _ => (),
+    }
+}
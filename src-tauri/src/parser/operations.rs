@@ -1,22 +1,33 @@
 use std::f64;
 
+use serde::{Deserialize, Serialize};
+
 use crate::error;
 
 use super::EXP_SYMBOL_STR;
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
+#[serde(rename_all = "snake_case")]
 pub enum NAryOperation {
     Add, Multiply
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum BinaryOperation {
     Division, Power, Equal
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum UnaryOperation {
-    Minus, Sin, Cos, Tan, Floor, Abs, Ceil, Log, Ln, Sqrt, Fact
+    Minus, Sin, Cos, Tan, Floor, Abs, Ceil, Log, Ln, Sqrt, Fact, Det, Eig, Mean, Median, Stdev, Var, Erf, Erfc
+}
+
+/// The probability functions, they have several arguments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    NormalPdf, NormalCdf, BinomPdf, PoissonPdf
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -30,6 +41,7 @@ pub enum OpType {
     Unary(UnaryOperation),
     NAry(NAryOperation),
     Constant(Constants),
+    Distribution(Distribution),
 }
 
 pub fn get_op_type(name: &str) -> error::Result<OpType> {
@@ -52,6 +64,18 @@ pub fn get_op_type(name: &str) -> error::Result<OpType> {
         "log" =>            Ok(OpType::Unary( UnaryOperation::Log )),
         "ln" =>             Ok(OpType::Unary( UnaryOperation::Ln )), 
         "sqrt" =>           Ok(OpType::Unary( UnaryOperation::Sqrt )),
+        "det" =>            Ok(OpType::Unary( UnaryOperation::Det )),
+        "eig" =>            Ok(OpType::Unary( UnaryOperation::Eig )),
+        "mean" =>           Ok(OpType::Unary( UnaryOperation::Mean )),
+        "median" =>         Ok(OpType::Unary( UnaryOperation::Median )),
+        "stdev" =>          Ok(OpType::Unary( UnaryOperation::Stdev )),
+        "var" =>            Ok(OpType::Unary( UnaryOperation::Var )),
+        "erf" =>            Ok(OpType::Unary( UnaryOperation::Erf )),
+        "erfc" =>           Ok(OpType::Unary( UnaryOperation::Erfc )),
+        "normalpdf" =>      Ok(OpType::Distribution( Distribution::NormalPdf )),
+        "normalcdf" =>      Ok(OpType::Distribution( Distribution::NormalCdf )),
+        "binompdf" =>       Ok(OpType::Distribution( Distribution::BinomPdf )),
+        "poissonpdf" =>     Ok(OpType::Distribution( Distribution::PoissonPdf )),
         "theta"|"rho"|"phi"|"lambda" => Err(error::AppError::ParseError("The greek letters aren't implemented yet".to_owned())),
         _ => Err(error::AppError::ParseError(format!("{name} is not a known operation")))
     }
@@ -67,19 +91,64 @@ impl Constants {
 }
 
 impl UnaryOperation {
+    /// The factorial of the integer part, 171! is already too big for a f64
     pub fn factorial(n: f64) -> f64 {
-        let n = n as i32;
-        let mut ans = 0;
-        for i in 2..=n {
-            ans = ans * i;
+        if n < 0.0 || n.is_nan() {
+            f64::NAN
+        } else if n > 170.0 {
+            f64::INFINITY
+        } else {
+            (2..=n as u32).map(f64::from).product()
+        }
+    }
+
+    /// The error function, with its Maclaurin series near 0 and with erfc in the tails
+    pub fn erf(x: f64) -> f64 {
+        if x.abs() >= 3.0 {
+            return x.signum() * (1.0 - Self::erfc(x.abs()));
+        }
+
+        let (mut term, mut sum) = (x, x);
+        for n in 1..100 {
+            term *= -x * x / n as f64;
+            sum += term / (2 * n + 1) as f64;
+            if term.abs() < 1e-17 {
+                break;
+            }
         }
-        ans as f64
+
+        sum * 2.0 / f64::consts::PI.sqrt()
+    }
+
+    /// The complementary error function, with a continued fraction in the tails where 1-erf(x) loses precision
+    pub fn erfc(x: f64) -> f64 {
+        if x < 3.0 {
+            return 1.0 - Self::erf(x);
+        }
+
+        let f = (1..=60).rev().fold(x, |f, k| x + k as f64 / 2.0 / f);
+        (-x * x).exp() / (f64::consts::PI.sqrt() * f)
+    }
+
+    /// The sample deviation and variance of a single number
+    pub fn undefined(_: f64) -> f64 {
+        f64::NAN
     }
 
     pub fn minus(n: f64) -> f64 {
         -n
     }
 
+    /// The determinant and the eigenvalue of a 1x1 matrix, and the mean and median of a single number
+    pub fn identity(n: f64) -> f64 {
+        n
+    }
+
+    /// The operations that make a number out of a whole list
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Self::Mean | Self::Median | Self::Stdev | Self::Var)
+    }
+
     pub fn func(&self) -> error::Result<fn(f64) -> f64> {
         match self {
             Self::Sin => Ok(f64::sin),
@@ -93,6 +162,10 @@ impl UnaryOperation {
             Self::Log => Ok(f64::log10),
             Self::Minus => Ok(Self::minus),
             Self::Sqrt => Ok(f64::sqrt),
+            Self::Det | Self::Eig | Self::Mean | Self::Median => Ok(Self::identity),
+            Self::Stdev | Self::Var => Ok(Self::undefined),
+            Self::Erf => Ok(Self::erf),
+            Self::Erfc => Ok(Self::erfc),
             //_ => Err(error::AppError::MathError(format!("There's no operation called {self:?}"))),
         }
     }
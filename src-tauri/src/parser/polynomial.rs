@@ -0,0 +1,665 @@
+use std::{collections::HashMap, ops::{Add, Mul, Neg, Sub}};
+
+use crate::{analysis::roots::{dedup_roots, find_roots}, error::{self, AppError}};
+
+use super::{ast::Node, rational::{gcd, lcm, overflow, Rational}, series::format_number, BinaryOperation, NAryOperation, UnaryOperation};
+
+/// The biggest degree of the polynomials. The exact coefficients can overflow with any degree, that's an error,
+/// but the powers of bigger polynomials rarely fit in an i128
+pub const MAX_DEGREE: usize = 16;
+
+/// A polynomial in one unknown with exact coefficients, the first one is the constant term
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<Rational>,
+}
+
+/// The polynomial is constant * product(factor^multiplicity), every factor has integer coefficients
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factorization {
+    pub constant: Rational,
+    pub factors: Vec<(Polynomial, usize)>,
+}
+
+/// A real root of a polynomial, written exactly when it's possible
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub value: f64,
+    pub latex: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolynomialCommand {
+    Expand, Factor, Solve
+}
+
+impl Polynomial {
+    pub fn new(mut coefficients: Vec<Rational>) -> Self {
+        while coefficients.last().is_some_and(Rational::is_zero) {
+            coefficients.pop();
+        }
+        Self { coefficients }
+    }
+
+    pub fn constant(c: Rational) -> Self {
+        Self::new(vec![c])
+    }
+
+    /// The polynomial u, the unknown itself
+    pub fn unknown() -> Self {
+        Self::new(vec![Rational::ZERO, Rational::ONE])
+    }
+
+    pub fn coefficients(&self) -> &[Rational] {
+        &self.coefficients
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// The degree of the zero polynomial is 0 too
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    pub fn leading(&self) -> Rational {
+        self.coefficients.last().copied().unwrap_or(Rational::ZERO)
+    }
+
+    /// Builds the polynomial from a tree, everything but the unknown must evaluate to a rational number
+    pub fn from_node(root: &Node, unknown: &str, variable_map: &HashMap<String, f64>) -> error::Result<Self> {
+        let not_polynomial = || AppError::MathError(format!("This expression isn't a polynomial in {unknown}"));
+        let too_big = || AppError::MathError(format!("The polynomials can't have a degree greater than {MAX_DEGREE}"));
+        let constant = |value: f64| Rational::from_f64(value)
+            .map(Self::constant)
+            .ok_or_else(|| AppError::MathError(format!("{value} can't be written as a fraction")));
+
+        let poly = match root {
+            Node::Constant { value } => constant(*value)?,
+            Node::Unknown { name } | Node::Variable { name } if name == unknown => Self::unknown(),
+            Node::Variable { name } => {
+                let value = variable_map.get(name).ok_or_else(|| AppError::IoError(format!("There are no variable called {name}")))?;
+                constant(*value)?
+            }
+            Node::Unknown { .. } | Node::Matrix { .. } => Err(not_polynomial())?,
+            Node::Unary { op_type, child } => {
+                let child = Self::from_node(child.as_ref().ok_or(AppError::EmptyError)?, unknown, variable_map)?;
+                match op_type {
+                    UnaryOperation::Minus => (-&child)?,
+                    op if child.degree() == 0 => constant((op.func()?)(child.leading().to_f64()))?,
+                    _ => Err(not_polynomial())?,
+                }
+            }
+            Node::Binary { op_type, lhs, rhs } => {
+                let lhs = Self::from_node(lhs.as_ref().ok_or(AppError::EmptyError)?, unknown, variable_map)?;
+                let rhs = Self::from_node(rhs.as_ref().ok_or(AppError::EmptyError)?, unknown, variable_map)?;
+                match op_type {
+                    BinaryOperation::Equal => (&lhs - &rhs)?,
+                    BinaryOperation::Division => {
+                        let (q, r) = lhs.div_rem(&rhs)?;
+                        if !r.is_zero() {
+                            Err(not_polynomial())?
+                        }
+                        q
+                    }
+                    BinaryOperation::Power => {
+                        if rhs.degree() > 0 {
+                            Err(not_polynomial())?
+                        }
+
+                        let exp = rhs.leading();
+                        if exp.is_integer() && (0..=MAX_DEGREE as i128).contains(&exp.numerator()) && exp.numerator() as usize * lhs.degree() <= MAX_DEGREE {
+                            lhs.pow(exp.numerator() as usize)?
+                        } else if lhs.degree() == 0 {
+                            constant(lhs.leading().to_f64().powf(exp.to_f64()))?
+                        } else if exp.is_integer() && exp.numerator() > 0 {
+                            Err(too_big())?
+                        } else {
+                            Err(not_polynomial())?
+                        }
+                    }
+                }
+            }
+            Node::NAry { op_type, children } => {
+                let mut poly = match op_type {
+                    NAryOperation::Add => Self::constant(Rational::ZERO),
+                    NAryOperation::Multiply => Self::constant(Rational::ONE),
+                };
+
+                for child in children {
+                    let child = Self::from_node(child, unknown, variable_map)?;
+                    poly = match op_type {
+                        NAryOperation::Add => (&poly + &child)?,
+                        NAryOperation::Multiply => (&poly * &child)?,
+                    };
+                }
+
+                poly
+            }
+        };
+
+        if poly.degree() > MAX_DEGREE {
+            return Err(too_big());
+        }
+
+        Ok(poly)
+    }
+
+    pub fn to_node(&self, unknown: &str) -> Node {
+        let terms: Vec<Box<Node>> = self.coefficients.iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_zero())
+            .map(|(k, c)| {
+                let c = Node::Constant { value: c.to_f64() };
+                let u = Node::Unknown { name: unknown.to_owned() };
+                Box::new(match k {
+                    0 => c,
+                    1 => Node::multiply(c, u),
+                    _ => Node::multiply(c, Node::Binary {
+                        op_type: BinaryOperation::Power,
+                        lhs: Some(Box::new(u)),
+                        rhs: Some(Box::new(Node::Constant { value: k as f64 }))
+                    }),
+                })
+            })
+            .collect();
+
+        match terms.len() {
+            0 => Node::Constant { value: 0.0 },
+            1 => *terms.into_iter().next().unwrap(),
+            _ => Node::NAry { op_type: NAryOperation::Add, children: terms },
+        }
+    }
+
+    pub fn scale(&self, c: Rational) -> error::Result<Self> {
+        Ok(Self::new(self.coefficients.iter().map(|&e| e * c).collect::<error::Result<_>>()?))
+    }
+
+    pub fn pow(&self, n: usize) -> error::Result<Self> {
+        (0..n).try_fold(Self::constant(Rational::ONE), |acc, _| &acc * self)
+    }
+
+    pub fn derivative(&self) -> error::Result<Self> {
+        Ok(Self::new(self.coefficients.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, &c)| c * Rational::integer(k as i128))
+            .collect::<error::Result<_>>()?))
+    }
+
+    pub fn evaluate(&self, x: f64) -> f64 {
+        self.coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c.to_f64())
+    }
+
+    pub fn evaluate_exact(&self, x: Rational) -> error::Result<Rational> {
+        self.coefficients.iter().rev().try_fold(Rational::ZERO, |acc, &c| (acc * x)? + c)
+    }
+
+    /// Polynomial long division, returns the quotient and the remainder
+    pub fn div_rem(&self, divisor: &Polynomial) -> error::Result<(Polynomial, Polynomial)> {
+        if divisor.is_zero() {
+            return Err(AppError::MathError("A polynomial can't be divided by zero".to_owned()));
+        }
+
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![Rational::ZERO; (self.degree() + 1).saturating_sub(divisor.degree())];
+        let n = divisor.degree();
+        let lead = divisor.leading();
+
+        while remainder.len() > n && !Self::new(remainder.clone()).is_zero() {
+            let k = remainder.len() - 1 - n;
+            let c = (remainder[remainder.len() - 1] / lead)?;
+            quotient[k] = c;
+            for (i, &d) in divisor.coefficients.iter().enumerate() {
+                remainder[k + i] = (remainder[k + i] - (c * d)?)?;
+            }
+            remainder.pop();
+        }
+
+        Ok((Self::new(quotient), Self::new(remainder)))
+    }
+
+    /// The monic greatest common divisor, using Euclid's algorithm. The remainders are made primitive so their coefficients don't grow
+    pub fn gcd(&self, other: &Polynomial) -> error::Result<Polynomial> {
+        let (mut a, mut b) = (self.primitive()?, other.primitive()?);
+        //Coprime polynomials are the usual case, they are found without the remainders
+        if !a.is_zero() && modular_gcd_degree(&a, &b) == Some(0) {
+            return Ok(Self::constant(Rational::ONE));
+        }
+
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b)?;
+            (a, b) = (b, r.primitive()?);
+        }
+
+        if a.is_zero() {
+            Ok(a)
+        } else {
+            a.scale(a.leading().recip()?)
+        }
+    }
+
+    /// The same polynomial with coprime integer coefficients and a positive leading coefficient
+    pub fn primitive(&self) -> error::Result<Polynomial> {
+        if self.is_zero() {
+            return Ok(self.clone());
+        }
+
+        let l = self.coefficients.iter().try_fold(1, |acc, c| lcm(acc, c.denominator()))?;
+        let g = self.coefficients.iter().try_fold(0, |acc, c| {
+            c.numerator().checked_mul(l / c.denominator()).map(|n| gcd(acc, n)).ok_or_else(overflow)
+        })?;
+        self.scale(Rational::new(l * self.leading().signum(), g)?)
+    }
+
+    /// Finds the rational roots with the rational root theorem
+    pub fn rational_roots(&self) -> error::Result<Vec<Rational>> {
+        let p = self.primitive()?;
+        if p.degree() == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut roots = Vec::new();
+        let first = p.coefficients.iter().position(|c| !c.is_zero()).unwrap_or(0);
+        if first > 0 {
+            roots.push(Rational::ZERO);
+        }
+
+        let a0 = p.coefficients[first].numerator();
+        let an = p.leading().numerator();
+        for num in divisors(a0) {
+            for den in divisors(an) {
+                for candidate in [Rational::new(num, den)?, Rational::new(-num, den)?] {
+                    //The float filter avoids the exact evaluation of most candidates
+                    let approx = p.evaluate(candidate.to_f64()).abs();
+                    if approx <= 1e-6 * p.evaluate(candidate.to_f64().abs()).abs().max(1.0)
+                        && p.evaluate_exact(candidate)?.is_zero() && !roots.contains(&candidate) {
+                        roots.push(candidate);
+                    }
+                }
+            }
+        }
+
+        roots.sort();
+        Ok(roots)
+    }
+
+    /// Splits the polynomial in square-free factors with Yun's algorithm and then takes out its rational roots,
+    /// the factors without rational roots are left as they are
+    pub fn factor(&self) -> error::Result<Factorization> {
+        if self.degree() == 0 {
+            return Ok(Factorization { constant: self.leading(), factors: Vec::new() });
+        }
+
+        let mut square_free = Vec::new();
+        let p = self.primitive()?;
+        let d = p.derivative()?;
+        let a = p.gcd(&d)?;
+        let (mut b, _) = p.div_rem(&a)?;
+        let (c, _) = d.div_rem(&a)?;
+        let mut d = (&c - &b.derivative()?)?;
+        let mut multiplicity = 1;
+        while b.degree() > 0 {
+            let a = b.gcd(&d)?;
+            let (next_b, _) = b.div_rem(&a)?;
+            let (c, _) = d.div_rem(&a)?;
+            if a.degree() > 0 {
+                square_free.push((a.primitive()?, multiplicity));
+            }
+
+            d = (&c - &next_b.derivative()?)?;
+            b = next_b;
+            multiplicity += 1;
+        }
+
+        let mut factors = Vec::new();
+        for (mut f, multiplicity) in square_free {
+            for r in f.rational_roots()? {
+                //(den*u - num) has integer coefficients
+                let linear = Polynomial::new(vec![Rational::integer(-r.numerator()), Rational::integer(r.denominator())]);
+                factors.push((linear.clone(), multiplicity));
+                f = f.div_rem(&linear)?.0;
+            }
+
+            if f.degree() > 0 {
+                factors.push((f.primitive()?, multiplicity));
+            }
+        }
+
+        factors.sort_by_key(|(f, _)| f.degree());
+        let product = factors.iter().try_fold(Rational::ONE, |acc, (f, m)| (0..*m).try_fold(acc, |acc, _| acc * f.leading()))?;
+        Ok(Factorization { constant: (self.leading() / product)?, factors })
+    }
+
+    /// The real roots, they are exact for the rational and quadratic factors
+    pub fn solve(&self) -> error::Result<Vec<Root>> {
+        if self.is_zero() {
+            return Err(AppError::MathError("Every number is a solution of this equation".to_owned()));
+        }
+
+        let mut roots = Vec::new();
+        for (f, _) in self.factor()?.factors {
+            let c: Vec<f64> = f.coefficients.iter().map(|c| c.to_f64()).collect();
+            match f.degree() {
+                1 => {
+                    let r = (-f.coefficients[0] / f.coefficients[1])?;
+                    roots.push(Root { value: r.to_f64(), latex: r.to_latex() });
+                }
+                2 => roots.extend(quadratic_roots(&f)?),
+                3 => roots.extend(solve_cubic(c[3], c[2], c[1], c[0]).into_iter()
+                    .map(|value| Root { value, latex: format_number(value) })),
+                _ => {
+                    //Cauchy's bound, every root is inside it
+                    let bound = 1.0 + c.iter().rev().skip(1).map(|e| (e / c[c.len() - 1]).abs()).fold(0.0, f64::max);
+                    let found = find_roots(|x| Some(f.evaluate(x)), -bound, bound);
+                    roots.extend(found.into_iter().map(|value| Root { value, latex: format_number(value) }));
+                }
+            }
+        }
+
+        roots.sort_by(|a, b| a.value.total_cmp(&b.value));
+        Ok(roots)
+    }
+
+    /// Writes the polynomial in LaTeX, from the greatest degree
+    pub fn to_latex(&self, unknown: &str) -> String {
+        let mut latex = String::new();
+        for (k, c) in self.coefficients.iter().enumerate().rev() {
+            if c.is_zero() {
+                continue;
+            }
+
+            if c.signum() < 0 {
+                latex.push('-');
+            } else if !latex.is_empty() {
+                latex.push('+');
+            }
+
+            let abs = c.abs();
+            if k == 0 || abs != Rational::ONE {
+                latex += &abs.to_latex();
+            }
+
+            match k {
+                0 => (),
+                1 => latex += unknown,
+                _ => latex += &format!("{unknown}^{{{k}}}"),
+            }
+        }
+
+        if latex.is_empty() { "0".to_owned() } else { latex }
+    }
+}
+
+impl Factorization {
+    pub fn to_latex(&self, unknown: &str) -> String {
+        if self.factors.is_empty() {
+            return self.constant.to_latex();
+        }
+
+        let mut latex = if self.constant == Rational::ONE {
+            String::new()
+        } else if self.constant == -Rational::ONE {
+            "-".to_owned()
+        } else {
+            self.constant.to_latex()
+        };
+
+        let alone = self.factors.len() == 1 && self.factors[0].1 == 1 && latex.is_empty();
+        for (f, multiplicity) in &self.factors {
+            let is_monomial = f.coefficients.iter().filter(|c| !c.is_zero()).count() == 1 && f.leading() == Rational::ONE;
+            if alone || is_monomial {
+                latex += &f.to_latex(unknown);
+            } else {
+                latex += &format!("\\left({}\\right)", f.to_latex(unknown));
+            }
+
+            if *multiplicity > 1 {
+                latex += &format!("^{{{multiplicity}}}");
+            }
+        }
+
+        latex
+    }
+}
+
+impl PolynomialCommand {
+    /// Splits \operatorname{name}\left(content\right) into the command and its content
+    pub fn parse(eq: &str) -> Option<(Self, &str)> {
+        let (command, rest) = [(Self::Expand, "expand"), (Self::Factor, "factor"), (Self::Solve, "solve")]
+            .into_iter()
+            .find_map(|(c, name)| eq.trim().strip_prefix(&format!("\\operatorname{{{name}}}")).map(|rest| (c, rest)))?;
+
+        let rest = rest.trim();
+        let content = rest.strip_prefix("\\left(")
+            .and_then(|r| r.strip_suffix("\\right)"))
+            .unwrap_or(rest);
+
+        Some((command, content))
+    }
+
+    /// Runs the command on the tree and writes the result in LaTeX
+    pub fn run(&self, root: &Node, variable_map: &HashMap<String, f64>) -> error::Result<String> {
+        let unknown = root.first_unknown().unwrap_or_else(|| "x".to_owned());
+        let poly = Polynomial::from_node(root, &unknown, variable_map)?;
+
+        match self {
+            Self::Expand => Ok(poly.to_latex(&unknown)),
+            Self::Factor => Ok(poly.factor()?.to_latex(&unknown)),
+            Self::Solve => {
+                let roots = poly.solve()?;
+                if roots.is_empty() {
+                    Ok("\\text{No real solutions}".to_owned())
+                } else {
+                    Ok(roots.iter().map(|r| format!("{unknown}={}", r.latex)).collect::<Vec<_>>().join(",\\ "))
+                }
+            }
+        }
+    }
+}
+
+/// The real roots of ax^2+bx+c
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let disc = b*b - 4.0*a*c;
+    if disc < 0.0 {
+        Vec::new()
+    } else if disc == 0.0 {
+        vec![-b / (2.0*a)]
+    } else {
+        //This way there isn't any cancellation
+        let q = -0.5 * (b + b.signum() * disc.sqrt());
+        let mut roots = if q == 0.0 { vec![0.0] } else { vec![q / a, c / q] };
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+}
+
+/// The real roots of ax^3+bx^2+cx+d, with the trigonometric method if there are three of them and Cardano's formula otherwise
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+
+    //Depressed cubic t^3 + pt + q, with x = t - b/3a
+    let shift = b / (3.0*a);
+    let p = (3.0*a*c - b*b) / (3.0*a*a);
+    let q = (2.0*b*b*b - 9.0*a*b*c + 27.0*a*a*d) / (27.0*a*a*a);
+    let disc = q*q/4.0 + p*p*p/27.0;
+
+    let roots = if p.abs() < 1e-14 && q.abs() < 1e-14 {
+        vec![0.0]
+    } else if disc < 0.0 {
+        let m = 2.0 * (-p/3.0).sqrt();
+        let theta = (3.0*q / (p*m)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3).map(|k| m * (theta - 2.0*std::f64::consts::PI*k as f64/3.0).cos()).collect()
+    } else {
+        let s = disc.sqrt();
+        vec![(-q/2.0 + s).cbrt() + (-q/2.0 - s).cbrt()]
+    };
+
+    dedup_roots(roots.into_iter().map(|t| t - shift).collect(), 1e-12)
+}
+
+/// The biggest square factors taken out of the discriminant of a quadratic
+const MAX_SQUARE_FACTOR: i128 = 10_000;
+
+/// The roots of a quadratic factor with integer coefficients, as (-b ± k√m) / 2a
+fn quadratic_roots(f: &Polynomial) -> error::Result<Vec<Root>> {
+    let [c, b, a] = [0, 1, 2].map(|i| f.coefficients[i].numerator());
+    let disc = b.checked_mul(b).zip(a.checked_mul(c).and_then(|ac| ac.checked_mul(4)))
+        .and_then(|(b2, ac4)| b2.checked_sub(ac4))
+        .ok_or_else(overflow)?;
+    if disc < 0 {
+        return Ok(Vec::new());
+    }
+
+    //The rational roots aren't searched when the coefficients are too big, so the factor can still have them
+    let root = disc.isqrt();
+    if root * root == disc {
+        return [-root, root].into_iter().map(|r| {
+            let x = Rational::new(r - b, 2*a)?;
+            Ok(Root { value: x.to_f64(), latex: x.to_latex() })
+        }).collect();
+    }
+
+    //disc = k^2 * m, with m square free. Only the small squares are tried, if they don't simplify it the discriminant is kept whole
+    let (mut k, mut m) = (1, disc);
+    let mut i = 2;
+    while i * i <= m && i <= MAX_SQUARE_FACTOR {
+        while m % (i * i) == 0 {
+            m /= i * i;
+            k *= i;
+        }
+        i += 1;
+    }
+    if i * i <= m {
+        (k, m) = (1, disc);
+    }
+
+    let g = gcd(gcd(b, k), 2*a);
+    let (b, k, den) = (-b / g, k / g, 2*a / g);
+    let sqrt = if k == 1 { format!("\\sqrt{{{m}}}") } else { format!("{k}\\sqrt{{{m}}}") };
+
+    Ok([1.0, -1.0].into_iter().map(|sign| {
+        let value = (b as f64 + sign * k as f64 * (m as f64).sqrt()) / den as f64;
+        let numerator = match (b, sign > 0.0) {
+            (0, true) => sqrt.clone(),
+            (0, false) => format!("-{sqrt}"),
+            (_, true) => format!("{b}+{sqrt}"),
+            (_, false) => format!("{b}-{sqrt}"),
+        };
+        let latex = if den == 1 { numerator } else { format!("\\frac{{{numerator}}}{{{den}}}") };
+        Root { value, latex }
+    }).collect())
+}
+
+/// The degree of the gcd of two primitive polynomials modulo a prime, it's never less than the degree of their gcd.
+/// It's None when the prime divides a leading coefficient, then the degrees can be wrong
+fn modular_gcd_degree(a: &Polynomial, b: &Polynomial) -> Option<usize> {
+    //2^61-1, the products of two residues fit in an i128
+    const PRIME: i128 = (1 << 61) - 1;
+    let reduce = |p: &Polynomial| -> Option<Vec<i128>> {
+        let residues: Vec<i128> = p.coefficients.iter().map(|c| c.numerator().rem_euclid(PRIME)).collect();
+        (residues.last().is_some_and(|&l| l != 0) && p.coefficients.iter().all(Rational::is_integer)).then_some(residues)
+    };
+    let inverse = |mut x: i128| {
+        let (mut result, mut e) = (1, PRIME - 2);
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result * x % PRIME;
+            }
+            x = x * x % PRIME;
+            e >>= 1;
+        }
+        result
+    };
+
+    let (mut a, mut b) = (reduce(a)?, reduce(b)?);
+    while !b.is_empty() {
+        let lead = inverse(b[b.len() - 1]);
+        while a.len() >= b.len() {
+            let c = a[a.len() - 1] * lead % PRIME;
+            let k = a.len() - b.len();
+            for (i, d) in b.iter().enumerate() {
+                a[k + i] = (a[k + i] - c * d % PRIME).rem_euclid(PRIME);
+            }
+            while a.last() == Some(&0) {
+                a.pop();
+            }
+        }
+        (a, b) = (b, a);
+    }
+
+    Some(a.len().saturating_sub(1))
+}
+
+/// The positive divisors of n, the numbers that are too big don't have any so the search is fast
+fn divisors(n: i128) -> Vec<i128> {
+    let n = n.abs();
+    if n == 0 || n > 1_000_000_000_000 {
+        return Vec::new();
+    }
+
+    let mut small = Vec::new();
+    let mut big = Vec::new();
+    let mut i = 1;
+    while i * i <= n {
+        if n % i == 0 {
+            small.push(i);
+            if i * i != n {
+                big.push(n / i);
+            }
+        }
+        i += 1;
+    }
+
+    small.extend(big.into_iter().rev());
+    small
+}
+
+impl Add for &Polynomial {
+    type Output = error::Result<Polynomial>;
+    fn add(self, rhs: Self) -> Self::Output {
+        let n = self.coefficients.len().max(rhs.coefficients.len());
+        Ok(Polynomial::new((0..n)
+            .map(|i| {
+                let a = self.coefficients.get(i).copied().unwrap_or(Rational::ZERO);
+                let b = rhs.coefficients.get(i).copied().unwrap_or(Rational::ZERO);
+                a + b
+            })
+            .collect::<error::Result<_>>()?))
+    }
+}
+
+impl Sub for &Polynomial {
+    type Output = error::Result<Polynomial>;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &(-rhs)?
+    }
+}
+
+impl Mul for &Polynomial {
+    type Output = error::Result<Polynomial>;
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_zero() || rhs.is_zero() {
+            return Ok(Polynomial::new(Vec::new()));
+        }
+
+        let mut coefficients = vec![Rational::ZERO; self.coefficients.len() + rhs.coefficients.len() - 1];
+        for (i, &a) in self.coefficients.iter().enumerate() {
+            for (j, &b) in rhs.coefficients.iter().enumerate() {
+                coefficients[i + j] = (coefficients[i + j] + (a * b)?)?;
+            }
+        }
+
+        Ok(Polynomial::new(coefficients))
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = error::Result<Polynomial>;
+    fn neg(self) -> Self::Output {
+        self.scale(-Rational::ONE)
+    }
+}
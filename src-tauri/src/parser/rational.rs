@@ -0,0 +1,212 @@
+use std::{cmp::Ordering, fmt::Display, ops::{Add, Div, Mul, Neg, Sub}};
+
+use crate::error::{self, AppError};
+
+/// The biggest denominator accepted when a float is converted to a fraction
+const MAX_DENOMINATOR: i128 = 1_000_000;
+
+/// An exact fraction, it's always reduced and the denominator is positive.
+/// The arithmetic is checked, the operations whose result doesn't fit in an i128 are an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    pub fn new(num: i128, den: i128) -> error::Result<Self> {
+        if den == 0 {
+            return Err(AppError::MathError("A fraction can't have a zero denominator".to_owned()));
+        }
+        //i128::MIN is never stored, so the absolute value and the negation of every fraction fit
+        if num == i128::MIN || den == i128::MIN {
+            return Err(overflow());
+        }
+
+        let g = gcd(num, den).max(1) * den.signum();
+        Ok(Self { num: num / g, den: den / g })
+    }
+
+    pub fn integer(n: i128) -> Self {
+        Self { num: n, den: 1 }
+    }
+
+    /// Finds the fraction with a small denominator that is equal to the float, if there's any
+    pub fn from_f64(x: f64) -> Option<Self> {
+        if !x.is_finite() || x.abs() > 1e15 {
+            return None;
+        }
+
+        //Continued fractions, the convergents are the best approximations
+        let (mut h0, mut h1) = (0i128, 1i128);
+        let (mut k0, mut k1) = (1i128, 0i128);
+        let mut r = x;
+        for _ in 0..64 {
+            let a = r.floor();
+            let (h2, k2) = (a as i128 * h1 + h0, a as i128 * k1 + k0);
+            if k2 > MAX_DENOMINATOR {
+                break;
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+
+            if (x - h1 as f64 / k1 as f64).abs() <= 1e-12 * x.abs().max(1.0) {
+                return Self::new(h1, k1).ok();
+            }
+            if r - a == 0.0 {
+                break;
+            }
+            r = 1.0 / (r - a);
+        }
+
+        None
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    pub fn abs(&self) -> Self {
+        Self { num: self.num.abs(), den: self.den }
+    }
+
+    pub fn signum(&self) -> i128 {
+        self.num.signum()
+    }
+
+    pub fn recip(&self) -> error::Result<Self> {
+        Self::new(self.den, self.num)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Writes the fraction in LaTeX, like -\frac{1}{2}
+    pub fn to_latex(self) -> String {
+        if self.is_integer() {
+            self.num.to_string()
+        } else if self.num < 0 {
+            format!("-\\frac{{{}}}{{{}}}", -self.num, self.den)
+        } else {
+            format!("\\frac{{{}}}{{{}}}", self.num, self.den)
+        }
+    }
+}
+
+pub fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+pub fn lcm(a: i128, b: i128) -> error::Result<i128> {
+    if a == 0 || b == 0 { Ok(0) } else { (a / gcd(a, b)).checked_mul(b).map(i128::abs).ok_or_else(overflow) }
+}
+
+/// The error of the exact operations whose result doesn't fit in an i128
+pub fn overflow() -> AppError {
+    AppError::MathError("The numbers are too big to be operated exactly".to_owned())
+}
+
+fn checked_mul(a: i128, b: i128) -> error::Result<i128> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = error::Result<Rational>;
+    fn add(self, rhs: Self) -> Self::Output {
+        let l = lcm(self.den, rhs.den)?;
+        let num = checked_mul(self.num, l / self.den)?.checked_add(checked_mul(rhs.num, l / rhs.den)?).ok_or_else(overflow)?;
+        Rational::new(num, l)
+    }
+}
+
+impl Sub for Rational {
+    type Output = error::Result<Rational>;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl Mul for Rational {
+    type Output = error::Result<Rational>;
+    fn mul(self, rhs: Self) -> Self::Output {
+        //Reducing before multiplying keeps the numbers small
+        let g1 = gcd(self.num, rhs.den).max(1);
+        let g2 = gcd(rhs.num, self.den).max(1);
+        Rational::new(checked_mul(self.num / g1, rhs.num / g2)?, checked_mul(self.den / g2, rhs.den / g1)?)
+    }
+}
+
+impl Div for Rational {
+    type Output = error::Result<Rational>;
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.is_zero() {
+            return Err(AppError::MathError("A fraction can't be divided by zero".to_owned()));
+        }
+
+        let g1 = gcd(self.num, rhs.num).max(1);
+        let g2 = gcd(self.den, rhs.den).max(1);
+        Rational::new(checked_mul(self.num / g1, rhs.den / g2)?, checked_mul(self.den / g2, rhs.num / g1)?)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Self::Output {
+        Rational { num: -self.num, den: self.den }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    /// The cross products are compared when they fit, otherwise the integer parts and then the reciprocals of the fractional parts
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.num.checked_mul(other.den), other.num.checked_mul(self.den)) {
+            return a.cmp(&b);
+        }
+
+        let (a, b) = (self.num.div_euclid(self.den), other.num.div_euclid(other.den));
+        let (ra, rb) = (self.num.rem_euclid(self.den), other.num.rem_euclid(other.den));
+        match (a.cmp(&b), ra, rb) {
+            (Ordering::Equal, 0, 0) => Ordering::Equal,
+            (Ordering::Equal, 0, _) => Ordering::Less,
+            (Ordering::Equal, _, 0) => Ordering::Greater,
+            //The fractional parts are reduced too, as gcd(r, den) = gcd(num, den)
+            (Ordering::Equal, ra, rb) => Rational { num: other.den, den: rb }.cmp(&Rational { num: self.den, den: ra }),
+            (ordering, _, _) => ordering,
+        }
+    }
+}
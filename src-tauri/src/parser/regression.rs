@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{compiler::ast_unknowns, error::{self, AppError}};
+
+use super::{ast::Node, simplifier::substitute_func, solver::{constant, occurrences, variable_to_unknown}, system::{jacobian, solve_linear}};
+
+const MAX_ITERATIONS: usize = 200;
+const TOLERANCE: f64 = 1e-12;
+
+/// The parameters of a model fitted to some data by least squares
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Fit {
+    pub parameters: Vec<(String, f64)>,
+    pub r_squared: f64,
+    /// The observed value minus the value of the model, in every row of the data
+    pub residuals: Vec<f64>,
+}
+
+/// Fits the parameters of observed ~ model, where every element of the lists used is an observation.
+/// The models linear in their parameters are solved with linear least squares and the rest with Levenberg-Marquardt
+pub fn fit_model(observed: &Node, model: &Node, names: &[String], variable_map: &HashMap<String, f64>, list_map: &HashMap<String, Vec<f64>>) -> error::Result<Fit> {
+    if names.is_empty() {
+        return Err(AppError::MathError("The model doesn't have any parameters to fit".to_owned()));
+    }
+    for root in [observed, model] {
+        let (x, y) = ast_unknowns(root)?;
+        if x || y {
+            return Err(AppError::MathError("The model must use the columns of the data, not x nor y".to_owned()));
+        }
+    }
+
+    let columns: Vec<(&String, &Vec<f64>)> = list_map.iter()
+        .filter(|(name, _)| occurrences(observed, name) + occurrences(model, name) > 0)
+        .collect();
+    let rows = columns.first().map(|(_, l)| l.len())
+        .ok_or_else(|| AppError::MathError("The model must use the columns of a data table".to_owned()))?;
+    if columns.iter().any(|(_, l)| l.len() != rows) {
+        return Err(AppError::MathError("Every column of the model must have the same length".to_owned()));
+    }
+    if rows < names.len() {
+        return Err(AppError::MathError(format!("{} parameters can't be fitted with {rows} observations", names.len())));
+    }
+
+    //Every row is evaluated with its elements of the lists and the current parameters
+    let row_map = |i: usize, params: &[f64]| {
+        let mut map = variable_map.clone();
+        map.extend(columns.iter().map(|(name, l)| (name.to_string(), l[i])));
+        map.extend(names.iter().cloned().zip(params.iter().copied()));
+        map
+    };
+    let observations: Vec<f64> = (0..rows).map(|i| constant(observed, &row_map(i, &[]))).collect::<error::Result<_>>()?;
+
+    let derivatives = jacobian(std::slice::from_ref(model), names)?.remove(0);
+    let evaluate = |params: &[f64]| -> Option<(Vec<f64>, Vec<Vec<f64>>)> {
+        let mut residuals = Vec::with_capacity(rows);
+        let mut jacobian = Vec::with_capacity(rows);
+        for (i, o) in observations.iter().enumerate() {
+            let map = row_map(i, params);
+            residuals.push(o - constant(model, &map).ok().filter(|v| v.is_finite())?);
+            jacobian.push(derivatives.iter().map(|d| constant(d, &map).ok().filter(|v| v.is_finite())).collect::<Option<Vec<_>>>()?);
+        }
+        Some((residuals, jacobian))
+    };
+
+    let linear = derivatives.iter().all(|d| names.iter().all(|name| occurrences(d, name) == 0));
+    let params = if linear {
+        linear_least_squares(&evaluate, names.len())
+    } else {
+        levenberg_marquardt(&evaluate, names.len())
+    }.ok_or_else(|| AppError::MathError("The model couldn't be fitted to the data".to_owned()))?;
+
+    let (residuals, _) = evaluate(&params)
+        .ok_or_else(|| AppError::MathError("The fitted model isn't defined for every observation".to_owned()))?;
+
+    let mean = observations.iter().sum::<f64>() / rows as f64;
+    let total: f64 = observations.iter().map(|o| (o - mean).powi(2)).sum();
+    let unexplained = sum_of_squares(&residuals);
+    let r_squared = if total > 0.0 { 1.0 - unexplained / total } else if unexplained == 0.0 { 1.0 } else { 0.0 };
+
+    Ok(Fit {
+        parameters: names.iter().cloned().zip(params).collect(),
+        r_squared,
+        residuals,
+    })
+}
+
+/// The model as a function of x, when it only depends on one of the lists
+pub fn fitted_curve(model: &Node, list_map: &HashMap<String, Vec<f64>>) -> Option<Node> {
+    let lists: Vec<&String> = list_map.keys().filter(|name| occurrences(model, name) > 0).collect();
+    match lists[..] {
+        [list] => {
+            let mut curve = model.clone();
+            variable_to_unknown(&mut curve, list);
+            substitute_func(&mut curve, &Node::Unknown { name: "x".to_owned() }).ok()?;
+            Some(curve)
+        }
+        _ => None,
+    }
+}
+
+type Evaluation<'a> = dyn Fn(&[f64]) -> Option<(Vec<f64>, Vec<Vec<f64>>)> + 'a;
+
+/// The model is f(0) + J·p, so the parameters are the solution of the normal equations
+fn linear_least_squares(evaluate: &Evaluation, params: usize) -> Option<Vec<f64>> {
+    let (residuals, jacobian) = evaluate(&vec![0.0; params])?;
+    let (a, b) = normal_equations(&jacobian, &residuals, 0.0);
+    solve_linear(a, b)
+}
+
+fn levenberg_marquardt(evaluate: &Evaluation, params: usize) -> Option<Vec<f64>> {
+    let mut p = vec![1.0; params];
+    let (mut residuals, mut jacobian) = evaluate(&p)?;
+    let mut error = sum_of_squares(&residuals);
+    let mut lambda = 1e-3;
+
+    for _ in 0..MAX_ITERATIONS {
+        if error < TOLERANCE || lambda > 1e12 {
+            break;
+        }
+
+        let (a, b) = normal_equations(&jacobian, &residuals, lambda);
+        let Some(step) = solve_linear(a, b) else {
+            lambda *= 10.0;
+            continue;
+        };
+        let candidate: Vec<f64> = p.iter().zip(&step).map(|(p, s)| p + s).collect();
+
+        match evaluate(&candidate) {
+            Some((r, j)) if sum_of_squares(&r) < error => {
+                let improvement = (error - sum_of_squares(&r)) / error;
+                (p, residuals, jacobian) = (candidate, r, j);
+                error = sum_of_squares(&residuals);
+                lambda /= 10.0;
+
+                if improvement < TOLERANCE {
+                    break;
+                }
+            }
+            _ => lambda *= 10.0,
+        }
+    }
+
+    Some(p)
+}
+
+/// (JᵀJ + λ·diag(JᵀJ))·δ = Jᵀr, without damping it's the linear least squares
+fn normal_equations(jacobian: &[Vec<f64>], residuals: &[f64], lambda: f64) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let n = jacobian.first().map_or(0, Vec::len);
+
+    let mut a = vec![vec![0.0; n]; n];
+    let mut b = vec![0.0; n];
+    for (row, r) in jacobian.iter().zip(residuals) {
+        for ((a_i, b_i), d_i) in a.iter_mut().zip(b.iter_mut()).zip(row) {
+            *b_i += d_i * r;
+            for (a_ij, d_j) in a_i.iter_mut().zip(row) {
+                *a_ij += d_i * d_j;
+            }
+        }
+    }
+    for (i, row) in a.iter_mut().enumerate() {
+        row[i] += lambda * row[i];
+    }
+
+    (a, b)
+}
+
+fn sum_of_squares(values: &[f64]) -> f64 {
+    values.iter().map(|v| v * v).sum()
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::error::{self, AppError};
+
+use super::{ast::Node, simplifier::{derive_function, simplify_tree, substitute_func}, BinaryOperation, NAryOperation};
+
+/// Every derivative makes the tree bigger, so the degree is limited to keep the parser responsive
+pub const MAX_TAYLOR_DEGREE: usize = 20;
+
+/// Returns the coefficients f^(k)(a)/k! of the Taylor polynomial, the center can be any constant expression
+pub fn taylor_coefficients(func: &Node, center: &Node, degree: usize) -> error::Result<Vec<Node>> {
+    if degree > MAX_TAYLOR_DEGREE {
+        return Err(AppError::MathError(format!("The degree of a Taylor polynomial can't be greater than {MAX_TAYLOR_DEGREE}")));
+    }
+
+    let mut coefficients = Vec::with_capacity(degree + 1);
+    let mut derivative = Box::new(func.clone());
+    let mut factorial = 1.0;
+
+    for k in 0..=degree {
+        if k > 0 {
+            derivative = derive_function(&derivative)?;
+            simplify_tree(&mut derivative, &HashMap::new()); //Only the constants are folded so the tree doesn't grow on every step
+            factorial *= k as f64;
+        }
+
+        let mut value = derivative.clone();
+        substitute_func(&mut value, center)?;
+        coefficients.push(Node::divide(*value, Node::Constant { value: factorial }));
+    }
+
+    Ok(coefficients)
+}
+
+/// Builds the tree of sum(c_k * (u - a)^k), where u is the unknown of the function
+pub fn taylor_polynomial(func: &Node, center: &Node, degree: usize) -> error::Result<Node> {
+    let unknown = Node::Unknown { name: func.first_unknown().unwrap_or_else(|| "x".to_owned()) };
+    let displacement = Node::substract(unknown, center.clone());
+
+    let terms: Vec<Box<Node>> = taylor_coefficients(func, center, degree)?
+        .into_iter()
+        .enumerate()
+        .map(|(k, c)| Box::new(match k {
+            0 => c,
+            1 => Node::multiply(c, displacement.clone()),
+            _ => Node::multiply(c, Node::Binary {
+                op_type: BinaryOperation::Power,
+                lhs: Some(Box::new(displacement.clone())),
+                rhs: Some(Box::new(Node::Constant { value: k as f64 }))
+            }),
+        }))
+        .collect();
+
+    if terms.len() == 1 {
+        Ok(*terms.into_iter().next().unwrap())
+    } else {
+        Ok(Node::NAry { op_type: NAryOperation::Add, children: terms })
+    }
+}
+
+/// Writes the polynomial with evaluated coefficients in LaTeX, as MathQuill would write it
+pub fn taylor_latex(coefficients: &[f64], unknown: &str, center: f64) -> String {
+    let base = if center == 0.0 {
+        unknown.to_owned()
+    } else if center < 0.0 {
+        format!("\\left({unknown}+{}\\right)", format_number(-center))
+    } else {
+        format!("\\left({unknown}-{}\\right)", format_number(center))
+    };
+
+    let mut latex = String::new();
+    for (k, &c) in coefficients.iter().enumerate() {
+        if c.abs() < 1e-12 {
+            continue;
+        }
+
+        if c < 0.0 {
+            latex.push('-');
+        } else if !latex.is_empty() {
+            latex.push('+');
+        }
+
+        let abs = c.abs();
+        if k == 0 || (abs - 1.0).abs() > 1e-12 {
+            latex += &format_number(abs);
+        }
+
+        match k {
+            0 => (),
+            1 => latex += &base,
+            _ => latex += &format!("{base}^{{{k}}}"),
+        }
+    }
+
+    if latex.is_empty() { "0".to_owned() } else { latex }
+}
+
+/// Formats the number with at most 10 decimals, using powers of ten for the very small ones
+pub fn format_number(n: f64) -> String {
+    if n != 0.0 && (n.abs() < 1e-4 || n.abs() >= 1e10) {
+        let exp = n.abs().log10().floor() as i32;
+        let mantissa = n / 10f64.powi(exp);
+        return format!("{}\\cdot10^{{{exp}}}", format_number(mantissa));
+    }
+
+    let s = format!("{n:.10}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_owned() } else { s.to_owned() }
+}
+
+/// Evaluates the coefficients with the variables and writes the resulting polynomial in LaTeX
+pub fn taylor_series_latex(func: &Node, center: f64, degree: usize, variable_map: &HashMap<String, f64>) -> error::Result<String> {
+    let coefficients: error::Result<Vec<f64>> = taylor_coefficients(func, &Node::Constant { value: center }, degree)?
+        .into_iter()
+        .map(|mut c| {
+            simplify_tree(&mut c, variable_map);
+            simplify_tree(&mut c, variable_map).ok_or_else(|| AppError::MathError("The coefficients of the Taylor polynomial must evaluate to a certain value".to_owned()))
+        })
+        .collect();
+
+    Ok(taylor_latex(&coefficients?, &func.first_unknown().unwrap_or_else(|| "x".to_owned()), center))
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, f64::consts::LN_10, ops::Deref};
+use std::{collections::HashMap, f64::consts::{E, LN_10, PI}, ops::Deref};
 
 use crate::error::{self, AppError};
 
@@ -9,6 +9,12 @@ pub fn simplify_tree(root: &mut Node, variable_map: &HashMap<String, f64>) -> Op
         Node::Unknown {..} => None,
         Node::Constant {value} => Some(*value),
         Node::Variable { name } => variable_map.get(name).copied(),
+        Node::Matrix { rows } => {
+            for e in rows.iter_mut().flatten() {
+                simplify_tree(e, variable_map);
+            }
+            None
+        }
         Node::Unary { op_type, child } => {
             let child = child.as_mut().unwrap();
             if let Some(n) = simplify_tree(child, variable_map) {
@@ -42,20 +48,15 @@ pub fn simplify_tree(root: &mut Node, variable_map: &HashMap<String, f64>) -> Op
                 })
                 .collect();
 
-            let cnst = children
-                .iter_mut()
-                .filter_map(|e| simplify_tree(e, variable_map))
-                .reduce(|acc, e| (op_type.func().unwrap())(acc, e));
-            
-            let mut new_children: Vec<Box<Node>> = children
-                .into_iter()
-                .filter_map(|mut e| { 
-                    match simplify_tree(&mut e, variable_map) {
-                        Some(..) => None,
-                        None => Some(e.to_owned()),
-                    }
-                })
-                .collect();
+            //Every child is simplified only once, as a simplified child that became a constant must be kept
+            let mut cnst = None;
+            let mut new_children: Vec<Box<Node>> = Vec::new();
+            for e in children.iter_mut() {
+                match simplify_tree(e, variable_map) {
+                    Some(x) => cnst = Some(cnst.map_or(x, |acc| (op_type.func().unwrap())(acc, x))),
+                    None => new_children.push(e.to_owned()),
+                }
+            }
 
             if let Some(x) = cnst { //Add constants to vector
                 new_children.push(Box::new(Node::Constant { value: x }));
@@ -89,7 +90,10 @@ pub fn simplify_tree(root: &mut Node, variable_map: &HashMap<String, f64>) -> Op
                 Some(0.0)
             } else {
                 if new_children.len() == 1 {
-                    *root = *new_children.first().unwrap().to_owned()
+                    *root = *new_children.first().unwrap().to_owned();
+                    if let Node::Constant { value } = root {
+                        return Some(*value);
+                    }
                 } else {
                     *root = Node::NAry { op_type: *op_type, children: new_children };
                 }
@@ -124,6 +128,11 @@ pub fn substitute_func(root: &mut Node, variable: &Node) -> error::Result<()> {
                 substitute_func(n, variable)?;
             }
         }
+        Node::Matrix { rows } => {
+            for n in rows.iter_mut().flatten() {
+                substitute_func(n, variable)?;
+            }
+        }
         _ => (),
     }
 
@@ -137,7 +146,8 @@ pub fn derive_function(root: &Node) -> error::Result<Box<Node>> {
         Node::Unary { op_type, child } => {
             if let Some(child) = child {
                 match op_type {
-                    UnaryOperation::Minus => *child.to_owned(),
+                    UnaryOperation::Minus => Node::op(UnaryOperation::Minus, *derive_function(child)?),
+                    UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median => *derive_function(child)?,
                     UnaryOperation::Ln => { // f'(x)/f(x)
                         Node::divide(
                             *derive_function(&child)?, 
@@ -158,10 +168,10 @@ pub fn derive_function(root: &Node) -> error::Result<Box<Node>> {
                             )
                         )
                     }
-                    UnaryOperation::Tan => { // 1 / cos(x)^2
+                    UnaryOperation::Tan => { // f'(x) / cos(f(x))^2
                         let cosfx = Node::op(UnaryOperation::Cos, *child.to_owned());
                         Node::divide(
-                            Node::Constant { value: 1.0 }, 
+                            *derive_function(child)?, 
                             Node::multiply(cosfx.to_owned(), cosfx.to_owned())
                         )
                     }
@@ -179,10 +189,25 @@ pub fn derive_function(root: &Node) -> error::Result<Box<Node>> {
                             *derive_function(&child)?, 
                             Node::multiply(
                                 Node::Constant { value: LN_10 }, 
-                                Node::op(UnaryOperation::Sqrt, *child.to_owned())
+                                *child.to_owned()
                             )
                         )
                     }
+                    UnaryOperation::Erf | UnaryOperation::Erfc => { // 2/sqrt(pi) * e^(-f(x)^2) * f'(x)
+                        let derivative = Node::multiply(
+                            Node::multiply(
+                                Node::Constant { value: 2.0 / PI.sqrt() },
+                                Node::Binary { 
+                                    op_type: BinaryOperation::Power, 
+                                    lhs: Some(Box::new(Node::Constant { value: E })), 
+                                    rhs: Some(Box::new(Node::op(UnaryOperation::Minus, Node::multiply(*child.to_owned(), *child.to_owned())))) 
+                                }
+                            ),
+                            *derive_function(child)?
+                        );
+
+                        if *op_type == UnaryOperation::Erfc { Node::op(UnaryOperation::Minus, derivative) } else { derivative }
+                    }
                     
                     _ => Err(AppError::MathError(format!("Function {op_type:?} is not derivable in R")))?
                 }
@@ -272,6 +297,13 @@ pub fn derive_function(root: &Node) -> error::Result<Box<Node>> {
                 }
             }
         }
+        Node::Matrix { rows } => {
+            let rows: error::Result<Vec<Vec<Node>>> = rows.iter()
+                .map(|row| row.iter().map(|e| Ok(*derive_function(e)?)).collect())
+                .collect();
+
+            Node::Matrix { rows: rows? }
+        }
     };
 
     Ok(Box::new(answer))
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use crate::{analysis::{evaluate_at, roots::{dedup_roots, find_roots}}, compiler::ast_unknowns, error::{self, AppError}};
+
+use super::{ast::Node, polynomial::Polynomial, simplify_tree, BinaryOperation, NAryOperation, UnaryOperation};
+
+/// The ranges where the numeric solutions are searched, from the smallest one
+const NUMERIC_RANGES: [f64; 3] = [10.0, 1e3, 1e6];
+
+/// Finds the real values of the variable that satisfy the equation, every other variable must be defined.
+/// If the variable appears only once it's isolated, otherwise the equation is solved as a polynomial or numerically
+pub fn solve_variable(root: &Node, name: &str, variable_map: &HashMap<String, f64>) -> error::Result<Vec<f64>> {
+    let (x, y) = ast_unknowns(root)?;
+    if x || y {
+        return Err(AppError::MathError("An equation with x or y can't define a variable".to_owned()));
+    }
+
+    let (lhs, rhs) = match root {
+        Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } => (lhs.as_ref().clone(), rhs.as_ref().clone()),
+        _ => (root.clone(), Node::Constant { value: 0.0 }),
+    };
+
+    let solutions = match (occurrences(&lhs, name), occurrences(&rhs, name)) {
+        (0, 0) => Err(AppError::MathError(format!("The equation doesn't have the variable {name}")))?,
+        (1, 0) => isolate(&lhs, name, vec![constant(&rhs, variable_map)?], variable_map),
+        (0, 1) => isolate(&rhs, name, vec![constant(&lhs, variable_map)?], variable_map),
+        _ => None,
+    };
+
+    let solutions = match solutions {
+        Some(solutions) => solutions,
+        None => {
+            let mut f = Node::substract(lhs, rhs);
+            variable_to_unknown(&mut f, name);
+
+            let numeric = || NUMERIC_RANGES.iter()
+                .map(|r| find_roots(|x| evaluate_at(&f, x, variable_map), -r, *r))
+                .find(|roots| !roots.is_empty())
+                .unwrap_or_default();
+
+            //The exact arithmetic can overflow, then the roots are found numerically too
+            match Polynomial::from_node(&f, name, variable_map).map(|poly| (poly.solve(), poly)) {
+                Ok((Ok(roots), _)) => roots.into_iter().map(|r| r.value).collect(),
+                Ok((Err(e), poly)) if poly.is_zero() => Err(e)?,
+                _ => numeric(),
+            }
+        }
+    };
+
+    let solutions = dedup_roots(solutions.into_iter().filter(|s| s.is_finite()).collect(), 1e-12);
+    if solutions.is_empty() {
+        return Err(AppError::MathError(format!("There isn't any real value of {name} that satisfies the equation")));
+    }
+
+    Ok(solutions)
+}
+
+/// Changes the variable by an unknown with its name, so the tree can be evaluated and derived as a function of it
+pub fn variable_to_unknown(root: &mut Node, name: &str) {
+    match root {
+        Node::Variable { name: var } if var == name => {
+            *root = Node::Unknown { name: name.to_owned() };
+        }
+        Node::Unary { child: Some(child), .. } => variable_to_unknown(child, name),
+        Node::Binary { lhs, rhs, .. } => {
+            lhs.iter_mut().chain(rhs.iter_mut()).for_each(|e| variable_to_unknown(e, name));
+        }
+        Node::NAry { children, .. } => children.iter_mut().for_each(|e| variable_to_unknown(e, name)),
+        _ => (),
+    }
+}
+
+pub(super) fn occurrences(root: &Node, name: &str) -> usize {
+    match root {
+        Node::Variable { name: var } => (var == name) as usize,
+        Node::Unary { child, .. } => child.iter().map(|e| occurrences(e, name)).sum(),
+        Node::Binary { lhs, rhs, .. } => lhs.iter().chain(rhs.iter()).map(|e| occurrences(e, name)).sum(),
+        Node::NAry { children, .. } => children.iter().map(|e| occurrences(e, name)).sum(),
+        _ => 0,
+    }
+}
+
+pub(super) fn constant(root: &Node, variable_map: &HashMap<String, f64>) -> error::Result<f64> {
+    let mut root = root.clone();
+    simplify_tree(&mut root, variable_map);
+    simplify_tree(&mut root, variable_map)
+        .ok_or_else(|| AppError::MathError("Every other variable of the equation must be defined".to_owned()))
+}
+
+/// Undoes the operations of the branch where the variable is, so the targets become its values.
+/// It's None if some operation can't be inverted, like floor
+fn isolate(root: &Node, name: &str, targets: Vec<f64>, variable_map: &HashMap<String, f64>) -> Option<Vec<f64>> {
+    let value = |node: &Node| constant(node, variable_map).ok();
+
+    match root {
+        Node::Variable { .. } => Some(targets),
+        Node::Unary { op_type, child: Some(child) } => {
+            let targets = targets.into_iter().flat_map(|t| match op_type {
+                UnaryOperation::Minus => vec![-t],
+                UnaryOperation::Det | UnaryOperation::Eig | UnaryOperation::Mean | UnaryOperation::Median => vec![t],
+                UnaryOperation::Sin if t.abs() <= 1.0 => vec![t.asin()],
+                UnaryOperation::Cos if t.abs() <= 1.0 => vec![t.acos()],
+                UnaryOperation::Tan => vec![t.atan()],
+                UnaryOperation::Ln => vec![t.exp()],
+                UnaryOperation::Log => vec![10f64.powf(t)],
+                UnaryOperation::Sqrt if t >= 0.0 => vec![t * t],
+                UnaryOperation::Abs if t > 0.0 => vec![-t, t],
+                UnaryOperation::Abs if t == 0.0 => vec![0.0],
+                _ => Vec::new(),
+            }).collect();
+
+            match op_type {
+                UnaryOperation::Floor | UnaryOperation::Ceil | UnaryOperation::Fact | UnaryOperation::Erf | UnaryOperation::Erfc
+                    | UnaryOperation::Stdev | UnaryOperation::Var => None,
+                _ => isolate(child, name, targets, variable_map),
+            }
+        }
+        Node::Binary { op_type, lhs: Some(lhs), rhs: Some(rhs) } => {
+            let in_lhs = occurrences(lhs, name) > 0;
+            let (branch, other) = if in_lhs { (lhs, value(rhs)?) } else { (rhs, value(lhs)?) };
+
+            let targets = match (op_type, in_lhs) {
+                (BinaryOperation::Division, true) => targets.into_iter().map(|t| t * other).collect(),
+                (BinaryOperation::Division, false) => targets.into_iter().filter(|t| *t != 0.0).map(|t| other / t).collect(),
+                (BinaryOperation::Power, true) => targets.into_iter().flat_map(|t| root_of(t, other)).collect(),
+                (BinaryOperation::Power, false) if other > 0.0 && other != 1.0 => {
+                    targets.into_iter().filter(|t| *t > 0.0).map(|t| t.ln() / other.ln()).collect()
+                }
+                _ => None?,
+            };
+
+            isolate(branch, name, targets, variable_map)
+        }
+        Node::NAry { op_type, children } => {
+            let branch = children.iter().find(|e| occurrences(e, name) > 0)?;
+            let mut rest = match op_type {
+                NAryOperation::Add => 0.0,
+                NAryOperation::Multiply => 1.0,
+            };
+            for e in children.iter().filter(|e| occurrences(e, name) == 0) {
+                rest = (op_type.func().ok()?)(rest, value(e)?);
+            }
+
+            let targets = match op_type {
+                NAryOperation::Add => targets.into_iter().map(|t| t - rest).collect(),
+                NAryOperation::Multiply if rest != 0.0 => targets.into_iter().map(|t| t / rest).collect(),
+                NAryOperation::Multiply => None?,
+            };
+
+            isolate(branch, name, targets, variable_map)
+        }
+        _ => None,
+    }
+}
+
+/// The real values of b that satisfy b^n = t
+fn root_of(t: f64, n: f64) -> Vec<f64> {
+    if n == 0.0 {
+        return Vec::new();
+    }
+
+    let is_integer = n.fract() == 0.0;
+    let is_even = is_integer && (n / 2.0).fract() == 0.0;
+    let r = t.abs().powf(1.0 / n);
+
+    if t > 0.0 && is_even {
+        vec![-r, r]
+    } else if t >= 0.0 {
+        vec![r]
+    } else if is_integer && !is_even {
+        vec![-r]
+    } else {
+        Vec::new()
+    }
+}
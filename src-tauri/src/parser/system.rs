@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use crate::{compiler::ast_unknowns, error::{self, AppError}};
+
+use super::{ast::Node, rational::Rational, simplifier::{derive_function, substitute_func}, solver::{constant, variable_to_unknown}, BinaryOperation, NAryOperation, UnaryOperation};
+
+/// The biggest systems, Newton's method derives every equation with respect to every unknown
+pub const MAX_UNKNOWNS: usize = 10;
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+/// Every unknown starts with the same guess, but they're slightly different so symmetric systems don't get stuck
+const INITIAL_GUESSES: [f64; 6] = [1.0, -1.0, 0.5, 2.0, -2.0, 10.0];
+
+/// Solves a system with as many equations as unknowns, the values are in the same order as the names.
+/// Linear systems are solved exactly with Gaussian elimination, or in f64 if the fractions overflow, and the rest with Newton's method
+pub fn solve_system(equations: &[Node], names: &[String], variable_map: &HashMap<String, f64>) -> error::Result<Vec<f64>> {
+    if names.is_empty() || names.len() != equations.len() {
+        return Err(AppError::MathError(format!("A system with {} unknowns needs the same number of equations, not {}", names.len(), equations.len())));
+    }
+    if names.len() > MAX_UNKNOWNS {
+        return Err(AppError::MathError(format!("The systems can't have more than {MAX_UNKNOWNS} unknowns")));
+    }
+
+    //Every equation must have a value when the unknowns are given one
+    let mut test_map = variable_map.clone();
+    test_map.extend(names.iter().map(|name| (name.to_owned(), 0.0)));
+    for eq in equations {
+        let (x, y) = ast_unknowns(eq)?;
+        if x || y {
+            return Err(AppError::MathError("An equation with x or y can't define a variable".to_owned()));
+        }
+        constant(eq, &test_map)?;
+    }
+
+    let rows: Option<Vec<Vec<Rational>>> = equations.iter().map(|eq| linear_form(eq, names, variable_map)).collect();
+    match rows {
+        Some(rows) => match gaussian_elimination(rows.clone()) {
+            Ok(solution) => Ok(solution.iter().map(|e| e.to_f64()).collect()),
+            Err(e) => {
+                let a = rows.iter().map(|row| row[..names.len()].iter().map(|e| e.to_f64()).collect()).collect();
+                let b = rows.iter().map(|row| -row[names.len()].to_f64()).collect();
+                solve_linear(a, b).ok_or(e)
+            }
+        },
+        None => newton(equations, names, variable_map),
+    }
+}
+
+/// The coefficients of the unknowns and the constant term, if the expression is linear with rational coefficients
+fn linear_form(root: &Node, names: &[String], variable_map: &HashMap<String, f64>) -> Option<Vec<Rational>> {
+    let n = names.len();
+    let constant = |value: f64| {
+        let mut form = vec![Rational::ZERO; n + 1];
+        form[n] = Rational::from_f64(value)?;
+        Some(form)
+    };
+    let is_constant = |form: &[Rational]| form[..n].iter().all(Rational::is_zero);
+    let scale = |form: Vec<Rational>, c: Rational| form.into_iter().map(|e| (e * c).ok()).collect::<Option<Vec<_>>>();
+    let add = |a: Vec<Rational>, b: Vec<Rational>| a.into_iter().zip(b).map(|(a, b)| (a + b).ok()).collect::<Option<Vec<_>>>();
+
+    match root {
+        Node::Constant { value } => constant(*value),
+        Node::Variable { name } => match names.iter().position(|e| e == name) {
+            Some(i) => {
+                let mut form = vec![Rational::ZERO; n + 1];
+                form[i] = Rational::ONE;
+                Some(form)
+            }
+            None => constant(*variable_map.get(name)?),
+        },
+        Node::Unknown { .. } | Node::Matrix { .. } => None,
+        Node::Unary { op_type, child } => {
+            let child = linear_form(child.as_ref()?, names, variable_map)?;
+            match op_type {
+                UnaryOperation::Minus => scale(child, -Rational::ONE),
+                op if is_constant(&child) => constant((op.func().ok()?)(child[n].to_f64())),
+                _ => None,
+            }
+        }
+        Node::Binary { op_type, lhs, rhs } => {
+            let lhs = linear_form(lhs.as_ref()?, names, variable_map)?;
+            let rhs = linear_form(rhs.as_ref()?, names, variable_map)?;
+            match op_type {
+                BinaryOperation::Equal => add(lhs, scale(rhs, -Rational::ONE)?),
+                BinaryOperation::Division if is_constant(&rhs) && !rhs[n].is_zero() => scale(lhs, rhs[n].recip().ok()?),
+                BinaryOperation::Power if is_constant(&lhs) && is_constant(&rhs) => constant(lhs[n].to_f64().powf(rhs[n].to_f64())),
+                BinaryOperation::Power if is_constant(&rhs) && rhs[n] == Rational::ONE => Some(lhs),
+                _ => None,
+            }
+        }
+        Node::NAry { op_type, children } => {
+            let mut forms = children.iter().map(|e| linear_form(e, names, variable_map));
+            let first = forms.next()??;
+            forms.try_fold(first, |acc, form| {
+                let form = form?;
+                match op_type {
+                    NAryOperation::Add => add(acc, form),
+                    NAryOperation::Multiply if is_constant(&form) => scale(acc, form[n]),
+                    NAryOperation::Multiply if is_constant(&acc) => scale(form, acc[n]),
+                    NAryOperation::Multiply => None,
+                }
+            })
+        }
+    }
+}
+
+/// Each row is a·v + c = 0, so the solution is exact
+fn gaussian_elimination(mut rows: Vec<Vec<Rational>>) -> error::Result<Vec<Rational>> {
+    let n = rows.len();
+    let singular = || AppError::MathError("The system doesn't have a unique solution".to_owned());
+
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| !rows[r][col].is_zero()).ok_or_else(singular)?;
+        rows.swap(col, pivot);
+
+        let p = rows[col][col];
+        rows[col] = rows[col].iter().map(|&e| e / p).collect::<error::Result<_>>()?;
+        for r in (0..n).filter(|&r| r != col) {
+            let factor = rows[r][col];
+            if factor.is_zero() {
+                continue;
+            }
+            rows[r] = rows[r].iter().zip(&rows[col]).map(|(&a, &b)| a - (factor * b)?).collect::<error::Result<_>>()?;
+        }
+    }
+
+    Ok(rows.into_iter().map(|row| -row[n]).collect())
+}
+
+/// Multivariate Newton's method, the Jacobian is made of the symbolic partial derivatives
+fn newton(equations: &[Node], names: &[String], variable_map: &HashMap<String, f64>) -> error::Result<Vec<f64>> {
+    let residuals: Vec<Node> = equations.iter()
+        .map(|eq| match eq {
+            Node::Binary { op_type: BinaryOperation::Equal, lhs: Some(lhs), rhs: Some(rhs) } => Node::substract(*lhs.to_owned(), *rhs.to_owned()),
+            _ => eq.clone(),
+        })
+        .collect();
+
+    let jacobian = jacobian(&residuals, names)?;
+
+    let evaluate = |node: &Node, values: &[f64]| {
+        let mut map = variable_map.clone();
+        map.extend(names.iter().cloned().zip(values.iter().copied()));
+        constant(node, &map).ok().filter(|v| v.is_finite())
+    };
+    let norm = |values: &[f64]| -> Option<f64> {
+        residuals.iter().map(|f| evaluate(f, values)).try_fold(0.0, |acc, v| Some(acc + v? * v?))
+    };
+
+    for guess in INITIAL_GUESSES {
+        let mut values: Vec<f64> = (0..names.len()).map(|i| guess * (1.0 + 0.1 * i as f64)).collect();
+
+        for _ in 0..MAX_ITERATIONS {
+            let Some(current) = norm(&values) else { break };
+            if current < TOLERANCE * TOLERANCE {
+                return Ok(values);
+            }
+
+            let f: Option<Vec<f64>> = residuals.iter().map(|f| evaluate(f, &values).map(|v| -v)).collect();
+            let j: Option<Vec<Vec<f64>>> = jacobian.iter()
+                .map(|row| row.iter().map(|d| evaluate(d, &values)).collect())
+                .collect();
+            let (Some(f), Some(j)) = (f, j) else { break };
+            let Some(step) = solve_linear(j, f) else { break };
+
+            //The step is halved until the residuals decrease
+            let mut t = 1.0;
+            let next = loop {
+                let candidate: Vec<f64> = values.iter().zip(&step).map(|(v, s)| v + t * s).collect();
+                if norm(&candidate).is_some_and(|n| n < current) || t < 1e-6 {
+                    break candidate;
+                }
+                t /= 2.0;
+            };
+
+            values = next;
+        }
+
+        if norm(&values).is_some_and(|n| n.sqrt() < 1e-9) {
+            return Ok(values);
+        }
+    }
+
+    Err(AppError::MathError("The system couldn't be solved, Newton's method didn't converge".to_owned()))
+}
+
+/// The symbolic partial derivatives of every function with respect to every variable
+pub(super) fn jacobian(functions: &[Node], names: &[String]) -> error::Result<Vec<Vec<Node>>> {
+    let mut jacobian = Vec::new();
+    for f in functions {
+        let mut row = Vec::new();
+        for name in names {
+            //The derivative is taken with respect to the only unknown, then it's a variable again
+            let mut f = f.clone();
+            variable_to_unknown(&mut f, name);
+            let mut d = derive_function(&f)?;
+            substitute_func(&mut d, &Node::Variable { name: name.to_owned() })?;
+            row.push(*d);
+        }
+        jacobian.push(row);
+    }
+
+    Ok(jacobian)
+}
+
+/// Gaussian elimination with partial pivoting of a·x = b, it's None if a is singular
+pub(super) fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&r, &s| a[r][col].abs().total_cmp(&a[s][col].abs()))?;
+        if a[pivot][col].abs() < 1e-14 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let (top, bottom) = a.split_at_mut(col + 1);
+        let (pivot_row, pivot_b) = (&top[col], b[col]);
+        for (row, br) in bottom.iter_mut().zip(b[col+1..].iter_mut()) {
+            let factor = row[col] / pivot_row[col];
+            for (e, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *e -= factor * p;
+            }
+            *br -= factor * pivot_b;
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for r in (0..n).rev() {
+        let sum: f64 = (r+1..n).map(|c| a[r][c] * x[c]).sum();
+        x[r] = (b[r] - sum) / a[r][r];
+    }
+
+    Some(x)
+}
@@ -0,0 +1,646 @@
+use std::{collections::HashMap, f64::consts::{E, PI}};
+
+use crate::error;
+
+use super::{ast::Node, matrix::substitute_unknowns, parse_latex, parse_mathml, to_content_mathml, to_latex, to_presentation_mathml, BinaryOperation, Limit, NAryOperation, UnaryOperation, resolve_values, simplifier::{derive_function, simplify_tree, substitute_func}, transform_curve, value::evaluate_value, Matrix, Value};
+
+/// Evaluates a tree with only one unknown in a point, as the functions are substituted in the parser
+fn evaluate(root: &Node, x: f64) -> Option<f64> {
+    let mut root = root.clone();
+    substitute_func(&mut root, &Node::Constant { value: x }).ok()?;
+    simplify_tree(&mut root, &HashMap::new());
+    simplify_tree(&mut root, &HashMap::new())
+}
+
+fn functions(list: &[(&str, &str)]) -> error::Result<HashMap<String, Box<Node>>> {
+    let mut func_map = HashMap::new();
+    for (name, content) in list {
+        let f = parse_latex(content, &func_map)?;
+        func_map.insert(name.to_string(), Box::new(f));
+    }
+
+    Ok(func_map)
+}
+
+#[test]
+fn derivatives() -> error::Result<()> {
+    let cases = [
+        ("-x^{2}", 1.5, -3.0),
+        ("\\tan\\left(2x\\right)", 0.3, 2.0 / f64::cos(0.6).powi(2)),
+        ("\\log\\left(x\\right)", 4.0, 1.0 / (4.0 * std::f64::consts::LN_10)),
+    ];
+
+    for (latex, x, expected) in cases {
+        let tree = parse_latex(latex, &HashMap::new())?;
+        let d = derive_function(&tree)?;
+        let val = evaluate(&d, x).unwrap();
+        assert!((val - expected).abs() < 1e-9, "d/dx {latex} = {val} in {x}, expected {expected}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn taylor_polynomial() -> error::Result<()> {
+    let func_map = functions(&[("f", "e^{x}"), ("g", "\\sin\\left(x\\right)")])?;
+
+    // T_4(e^x, 0) = 1 + x + x^2/2 + x^3/6 + x^4/24
+    let tree = parse_latex("T_{4}{f,0}", &func_map)?;
+    let expected = |x: f64| 1.0 + x + x*x/2.0 + x.powi(3)/6.0 + x.powi(4)/24.0;
+    for i in -10..=10 {
+        let x = i as f64 / 5.0;
+        assert!((evaluate(&tree, x).unwrap() - expected(x)).abs() < 1e-9);
+    }
+
+    // The polynomial around a point equals the function there
+    let tree = parse_latex("T_5{g,1}", &func_map)?;
+    assert!((evaluate(&tree, 1.0).unwrap() - f64::sin(1.0)).abs() < 1e-12);
+    assert!((evaluate(&tree, 1.2).unwrap() - f64::sin(1.2)).abs() < 1e-5);
+
+    // Without the arguments T is a subscripted variable
+    let variables = HashMap::from([("T_1".to_owned(), 3.0), ("T_n".to_owned(), 4.0)]);
+    let value = |latex: &str| evaluate_value(&parse_latex(latex, &func_map)?, &variables, &HashMap::new(), &HashMap::new());
+    assert_eq!(value("2T_{1}")?, Value::Scalar(6.0));
+    assert_eq!(value("T_1+T_n")?, Value::Scalar(7.0));
+
+    Ok(())
+}
+
+#[test]
+fn taylor_latex() -> error::Result<()> {
+    let func_map = functions(&[("g", "\\sin\\left(x\\right)")])?;
+
+    let latex = super::taylor_series_latex(&func_map["g"], 0.0, 5, &HashMap::new())?;
+    assert_eq!(latex, "x-0.1666666667x^{3}+0.0083333333x^{5}");
+
+    let latex = super::taylor_series_latex(&func_map["g"], -2.0, 1, &HashMap::new())?;
+    assert_eq!(latex, "-0.9092974268-0.4161468365\\left(x+2\\right)");
+
+    Ok(())
+}
+
+
+fn polynomial(command: &str) -> error::Result<String> {
+    use super::PolynomialCommand;
+
+    let (command, content) = PolynomialCommand::parse(command).unwrap();
+    command.run(&parse_latex(content, &HashMap::new())?, &HashMap::new())
+}
+
+#[test]
+fn polynomial_expansion() -> error::Result<()> {
+    assert_eq!(polynomial("\\operatorname{expand}\\left(\\left(x+1\\right)^{3}\\right)")?, "x^{3}+3x^{2}+3x+1");
+    assert_eq!(polynomial("\\operatorname{expand}\\left(\\left(x-2\\right)\\left(x+2\\right)-x^{2}\\right)")?, "-4");
+    assert_eq!(polynomial("\\operatorname{expand}\\left(\\frac{x^{2}-1}{x-1}\\right)")?, "x+1");
+    assert!(polynomial("\\operatorname{expand}\\left(\\frac{1}{x}\\right)").is_err());
+    assert!(polynomial("\\operatorname{expand}\\left(\\sin\\left(x\\right)\\right)").is_err());
+    //999^16 doesn't fit in an i128, the overflow is an error
+    assert!(polynomial("\\operatorname{expand}\\left(\\left(\\frac{x}{999}+1\\right)^{16}\\right)").is_err());
+    assert!(polynomial("\\operatorname{expand}\\left(\\left(x+1\\right)^{40}\\right)").unwrap_err().to_string().contains("degree greater than"));
+
+    Ok(())
+}
+
+#[test]
+fn polynomial_factoring() -> error::Result<()> {
+    assert_eq!(polynomial("\\operatorname{factor}\\left(x^{2}-1\\right)")?, "\\left(x+1\\right)\\left(x-1\\right)");
+    assert_eq!(polynomial("\\operatorname{factor}\\left(2x^{3}-4x^{2}+2x\\right)")?, "2x\\left(x-1\\right)^{2}");
+    assert_eq!(polynomial("\\operatorname{factor}\\left(x^{2}+1\\right)")?, "x^{2}+1");
+    assert_eq!(polynomial("\\operatorname{factor}\\left(6x^{2}-x-1\\right)")?, "\\left(3x+1\\right)\\left(2x-1\\right)");
+    //The remainders of the gcd are primitive, so their coefficients don't overflow
+    let p = "7x^{12}+3x^{11}-5x^{9}+11x^{4}-5x+13";
+    assert_eq!(polynomial(&format!("\\operatorname{{factor}}\\left({p}\\right)"))?, p);
+
+    Ok(())
+}
+
+#[test]
+fn polynomial_solving() -> error::Result<()> {
+    assert_eq!(polynomial("\\operatorname{solve}\\left(x^{2}-x-2=0\\right)")?, "x=-1,\\ x=2");
+    assert_eq!(polynomial("\\operatorname{solve}\\left(x^{2}-2x-1\\right)")?, "x=1-\\sqrt{2},\\ x=1+\\sqrt{2}");
+    assert_eq!(polynomial("\\operatorname{solve}\\left(2x=-1\\right)")?, "x=-\\frac{1}{2}");
+    assert_eq!(polynomial("\\operatorname{solve}\\left(x^{2}+1\\right)")?, "\\text{No real solutions}");
+    //The discriminants with big prime factors are written whole, and the big rational roots aren't searched
+    assert_eq!(polynomial("\\operatorname{solve}\\left(x^{2}+123456789011x+1\\right)")?,
+        "x=\\frac{-123456789011-\\sqrt{15241578752906570358117}}{2},\\ x=\\frac{-123456789011+\\sqrt{15241578752906570358117}}{2}");
+    assert_eq!(polynomial("\\operatorname{solve}\\left(x^{2}-10000000000001x+10000000000000\\right)")?, "x=1,\\ x=10000000000000");
+
+    // x^3-2 has no rational roots, so it's solved in closed form
+    let roots = super::polynomial::Polynomial::from_node(&parse_latex("x^{3}-2", &HashMap::new())?, "x", &HashMap::new())?.solve()?;
+    assert_eq!(roots.len(), 1);
+    assert!((roots[0].value - 2f64.cbrt()).abs() < 1e-12);
+
+    Ok(())
+}
+
+fn solutions(latex: &str, name: &str, variable_map: &HashMap<String, f64>) -> error::Result<Vec<f64>> {
+    super::solve_variable(&parse_latex(latex, &HashMap::new())?, name, variable_map)
+}
+
+fn assert_solutions(found: &[f64], expected: &[f64]) {
+    assert_eq!(found.len(), expected.len(), "found {found:?}, expected {expected:?}");
+    for (a, b) in found.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-9, "found {found:?}, expected {expected:?}");
+    }
+}
+
+#[test]
+fn solve_variable() -> error::Result<()> {
+    let no_vars = HashMap::new();
+
+    // Isolated symbolically
+    assert_solutions(&solutions("a^{2}=9", "a", &no_vars)?, &[-3.0, 3.0]);
+    assert_solutions(&solutions("\\ln\\left(c\\right)+1=3", "c", &no_vars)?, &[f64::exp(2.0)]);
+    assert_solutions(&solutions("2^{k}=\\frac{1}{8}", "k", &no_vars)?, &[-3.0]);
+    assert_solutions(&solutions("\\sin\\left(2t\\right)=\\frac{1}{2}", "t", &no_vars)?, &[std::f64::consts::PI / 12.0]);
+
+    // As a polynomial
+    assert_solutions(&solutions("2b+1=\\frac{b}{3}", "b", &no_vars)?, &[-0.6]);
+    let vars = HashMap::from([("p".to_owned(), 2.0)]);
+    assert_solutions(&solutions("r^{2}-pr=3", "r", &vars)?, &[-1.0, 3.0]);
+
+    // Numerically
+    let found = solutions("\\cos\\left(d\\right)=d", "d", &no_vars)?;
+    assert_solutions(&found, &[0.7390851332151607]);
+
+    // The exact coefficients overflow, so the double roots of the sextic are found numerically
+    let sextic = "\\frac{7}{3}a^{6}+\\frac{3}{11}a^{5}-5a^{3}+\\frac{11}{7}a^{2}-5a-13";
+    let found = solutions(&format!("\\left({sextic}\\right)^{{2}}\\left(a-2\\right)=0"), "a", &no_vars)?;
+    assert_eq!(found.len(), 3);
+    assert!((found[2] - 2.0).abs() < 1e-9);
+    for a in &found[..2] {
+        let vars = HashMap::from([("a".to_owned(), *a)]);
+        assert!(super::solver::constant(&parse_latex(sextic, &HashMap::new())?, &vars)?.abs() < 1e-6);
+    }
+
+    assert!(solutions("a^{2}=-1", "a", &no_vars).is_err());
+    assert!(solutions("a=x", "a", &no_vars).is_err());
+    assert!(solutions("a=q", "a", &no_vars).is_err());
+
+    Ok(())
+}
+
+fn system(equations: &[&str], names: &[&str], variable_map: &HashMap<String, f64>) -> error::Result<Vec<f64>> {
+    let equations: error::Result<Vec<Node>> = equations.iter().map(|e| parse_latex(e, &HashMap::new())).collect();
+    let names: Vec<String> = names.iter().map(|e| e.to_string()).collect();
+    super::solve_system(&equations?, &names, variable_map)
+}
+
+#[test]
+fn linear_systems() -> error::Result<()> {
+    let no_vars = HashMap::new();
+
+    assert_solutions(&system(&["a+b=3", "a-b=1"], &["a", "b"], &no_vars)?, &[2.0, 1.0]);
+    assert_solutions(&system(&["2a+b-c=8", "-3a-b+2c=-11", "-2a+b+2c=-3"], &["a", "b", "c"], &no_vars)?, &[2.0, 3.0, -1.0]);
+
+    let vars = HashMap::from([("k".to_owned(), 3.0)]);
+    assert_solutions(&system(&["\\frac{a}{k}+b=1", "a=kb"], &["a", "b"], &vars)?, &[1.5, 0.5]);
+
+    // The exact fractions of the decimals overflow, so it's solved in f64
+    let equations = [
+        "0.123457a+0.654321b+0.777773c+0.4d=1.234567", "0.987654a-0.111111b+0.222223c+0.5d=0.345679",
+        "0.314159a+0.271828b-0.141421c+0.6d=0.577216", "0.161803a+0.707107b+0.333333c-0.7d=0.693147",
+    ];
+    let found = system(&equations, &["a", "b", "c", "d"], &no_vars)?;
+    let vars = HashMap::from_iter(["a", "b", "c", "d"].iter().map(|e| e.to_string()).zip(found));
+    for eq in equations {
+        let Node::Binary { lhs: Some(lhs), rhs: Some(rhs), .. } = parse_latex(eq, &HashMap::new())? else { panic!("{eq} isn't an equation") };
+        assert!((super::solver::constant(&lhs, &vars)? - super::solver::constant(&rhs, &vars)?).abs() < 1e-9, "{eq} isn't satisfied");
+    }
+
+    assert!(system(&["a+b=3", "2a+2b=6"], &["a", "b"], &no_vars).is_err());
+    assert!(system(&["a+b=3"], &["a", "b"], &no_vars).is_err());
+    assert!(system(&["a+b=q", "a-b=1"], &["a", "b"], &no_vars).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn nonlinear_systems() -> error::Result<()> {
+    let equations = ["a^{2}+b^{2}=5", "ab=2"];
+    let found = system(&equations, &["a", "b"], &HashMap::new())?;
+
+    let vars = HashMap::from([("a".to_owned(), found[0]), ("b".to_owned(), found[1])]);
+    for eq in equations {
+        let mut root = parse_latex(eq, &HashMap::new())?;
+        simplify_tree(&mut root, &vars);
+        assert!(simplify_tree(&mut root, &vars).unwrap().abs() < 1e-9, "{eq} isn't satisfied by {found:?}");
+    }
+
+    assert_solutions(&system(&["e^{a}=b", "b=2"], &["a", "b"], &HashMap::new())?, &[f64::ln(2.0), 2.0]);
+
+    Ok(())
+}
+
+fn matrix_value(latex: &str, matrix_map: &HashMap<String, Matrix>) -> error::Result<Value> {
+    let root = parse_latex(latex, &HashMap::new())?;
+    evaluate_value(&root, &HashMap::new(), matrix_map, &HashMap::new())
+}
+
+fn matrix(rows: &[&[f64]]) -> Matrix {
+    Matrix::new(rows.iter().map(|r| r.to_vec()).collect()).unwrap()
+}
+
+#[test]
+fn matrix_operations() -> error::Result<()> {
+    let a = matrix(&[&[1.0, 2.0], &[3.0, 4.0]]);
+    let matrices = HashMap::from([("A".to_owned(), a.clone())]);
+
+    assert_eq!(matrix_value("\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}", &matrices)?, Value::Matrix(a.clone()));
+    assert_eq!(matrix_value("A\\begin{bmatrix}1\\\\1\\end{bmatrix}", &matrices)?, Value::Matrix(matrix(&[&[3.0], &[7.0]])));
+    assert_eq!(matrix_value("A^{T}", &matrices)?, Value::Matrix(matrix(&[&[1.0, 3.0], &[2.0, 4.0]])));
+    assert_eq!(matrix_value("2A-A", &matrices)?, Value::Matrix(a.clone()));
+    assert_eq!(matrix_value("\\det\\left(A\\right)", &matrices)?, Value::Scalar(-2.0));
+    let Value::Matrix(inverse) = matrix_value("A^{-1}", &matrices)? else { panic!("The inverse isn't a matrix") };
+    let inverse: Vec<Vec<f64>> = inverse.into();
+    assert_solutions(&inverse.concat(), &[-2.0, 1.0, 1.5, -0.5]);
+
+    assert!(matrix_value("\\det\\left(\\begin{pmatrix}1&2\\end{pmatrix}\\right)", &matrices).is_err());
+    assert!(matrix_value("\\begin{pmatrix}1&2\\\\2&4\\end{pmatrix}^{-1}", &matrices).is_err());
+    assert!(matrix_value("A+1", &matrices).is_err());
+
+    //The powers are computed by squaring, so the big exponents are fast
+    let rotation = matrix(&[&[0.0, -1.0], &[1.0, 0.0]]);
+    let matrices = HashMap::from([("R".to_owned(), rotation)]);
+    assert_eq!(matrix_value("R^{1000000000}", &matrices)?, Value::Matrix(matrix(&[&[1.0, 0.0], &[0.0, 1.0]])));
+    assert_eq!(matrix_value("R^{5}", &matrices)?, Value::Matrix(matrix(&[&[0.0, -1.0], &[1.0, 0.0]])));
+    assert!(matrix_value("R^{1000000000000}", &matrices).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn matrix_eigenvalues() -> error::Result<()> {
+    let eigenvalues = |rows: &[&[f64]]| matrix(rows).eigenvalues();
+
+    assert_solutions(&eigenvalues(&[&[2.0, 0.0], &[0.0, 3.0]])?, &[2.0, 3.0]);
+    assert_solutions(&eigenvalues(&[&[2.0, 1.0], &[1.0, 2.0]])?, &[1.0, 3.0]);
+    assert_solutions(&eigenvalues(&[&[2.0, 0.0, 0.0], &[0.0, 3.0, 4.0], &[0.0, 4.0, 9.0]])?, &[1.0, 2.0, 11.0]);
+
+    Ok(())
+}
+
+#[test]
+fn matrix_transformations() -> error::Result<()> {
+    let matrices = HashMap::from([("R".to_owned(), matrix(&[&[0.0, -1.0], &[1.0, 0.0]]))]);
+
+    //The parabola y=x^2 rotated a quarter turn is x=-y^2
+    let curve = transform_curve(&parse_latex("x^{2}", &HashMap::new())?, &matrices["R"])?;
+    let vars = HashMap::new();
+    for (x, y) in [(-1.0, 1.0), (-4.0, -2.0), (0.0, 0.0)] {
+        let mut point = curve.clone();
+        substitute_unknowns(&mut point, &Node::Constant { value: x }, &Node::Constant { value: y });
+        let Node::Binary { lhs: Some(lhs), rhs: Some(rhs), .. } = point else { panic!("The curve isn't an equation") };
+        let (mut lhs, mut rhs) = (*lhs, *rhs);
+        simplify_tree(&mut lhs, &vars);
+        simplify_tree(&mut rhs, &vars);
+        assert!((simplify_tree(&mut lhs, &vars).unwrap() - simplify_tree(&mut rhs, &vars).unwrap()).abs() < 1e-9);
+    }
+
+    let mut drawn = parse_latex("\\det\\left(R\\right)x", &HashMap::new())?;
+    assert!(resolve_values(&mut drawn, &vars, &matrices, &HashMap::new())?.is_none());
+    assert_eq!(evaluate(&drawn, 2.0), Some(2.0));
+
+    let mut drawn = parse_latex("Rx", &HashMap::new())?;
+    assert!(resolve_values(&mut drawn, &vars, &matrices, &HashMap::new()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn lists() -> error::Result<()> {
+    let lists = HashMap::from([("x_1".to_owned(), vec![1.0, 2.0, 3.0]), ("y_1".to_owned(), vec![3.0, 3.0, 6.0])]);
+    let value = |latex: &str| evaluate_value(&parse_latex(latex, &HashMap::new())?, &HashMap::new(), &HashMap::new(), &lists);
+
+    assert_eq!(value("\\operatorname{mean}\\left(y_{1}\\right)")?, Value::Scalar(4.0));
+    assert_eq!(value("\\operatorname{mean}\\left(x_1y_1\\right)")?, Value::Scalar(9.0));
+    assert_eq!(value("2x_{1}+1")?, Value::List(vec![3.0, 5.0, 7.0]));
+    assert!(value("\\operatorname{mean}\\left(x_{2}\\right)").is_err());
+
+    let mut drawn = parse_latex("\\operatorname{mean}\\left(x_{1}\\right)x", &HashMap::new())?;
+    assert!(resolve_values(&mut drawn, &HashMap::new(), &HashMap::new(), &lists)?.is_none());
+    assert_eq!(evaluate(&drawn, 3.0), Some(6.0));
+
+    let mut drawn = parse_latex("x_{1}x", &HashMap::new())?;
+    assert!(resolve_values(&mut drawn, &HashMap::new(), &HashMap::new(), &lists).is_err());
+
+    Ok(())
+}
+
+fn fit(latex: &str, names: &[&str], lists: &HashMap<String, Vec<f64>>) -> error::Result<super::Fit> {
+    let (observed, model) = latex.split_once("\\sim").unwrap();
+    let names: Vec<String> = names.iter().map(|e| e.to_string()).collect();
+    super::fit_model(&parse_latex(observed, &HashMap::new())?, &parse_latex(model, &HashMap::new())?, &names, &HashMap::new(), lists)
+}
+
+#[test]
+fn linear_regression() -> error::Result<()> {
+    let xs = vec![-2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+    let ys = xs.iter().map(|x| 2.0 * x * x - x + 3.0).collect();
+    let lists = HashMap::from([("x_1".to_owned(), xs), ("y_1".to_owned(), ys)]);
+
+    let found = fit("y_{1}\\sim ax_{1}^{2}+bx_{1}+c", &["a", "b", "c"], &lists)?;
+    let params: Vec<f64> = found.parameters.iter().map(|(_, v)| *v).collect();
+    assert_solutions(&params, &[2.0, -1.0, 3.0]);
+    assert!((found.r_squared - 1.0).abs() < 1e-9);
+    assert!(found.residuals.iter().all(|r| r.abs() < 1e-9));
+
+    //A line can't go through a parabola
+    let found = fit("y_{1}\\sim mx_{1}+n", &["m", "n"], &lists)?;
+    assert!(found.r_squared < 0.9);
+    assert!((found.residuals.iter().sum::<f64>()).abs() < 1e-9);
+
+    //The curve drawn is the model as a function of x
+    let mut curve = super::fitted_curve(&parse_latex("mx_{1}+n", &HashMap::new())?, &lists).unwrap();
+    let vars = HashMap::from([("m".to_owned(), 2.0), ("n".to_owned(), 1.0)]);
+    substitute_func(&mut curve, &Node::Constant { value: 3.0 })?;
+    simplify_tree(&mut curve, &vars);
+    assert_eq!(simplify_tree(&mut curve, &vars), Some(7.0));
+
+    assert!(fit("y_{1}\\sim ax_{1}", &[], &lists).is_err());
+    assert!(fit("y_{1}\\sim ax", &["a"], &lists).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn nonlinear_regression() -> error::Result<()> {
+    let xs = vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5];
+    let ys = xs.iter().map(|x: &f64| 3.0 * f64::exp(-0.7 * x)).collect();
+    let lists = HashMap::from([("x_1".to_owned(), xs), ("y_1".to_owned(), ys)]);
+
+    let found = fit("y_{1}\\sim ae^{kx_{1}}", &["a", "k"], &lists)?;
+    let params: Vec<f64> = found.parameters.iter().map(|(_, v)| *v).collect();
+    assert!((params[0] - 3.0).abs() < 1e-6 && (params[1] + 0.7).abs() < 1e-6, "{params:?}");
+    assert!((found.r_squared - 1.0).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn list_statistics() -> error::Result<()> {
+    let lists = HashMap::from([("x_1".to_owned(), vec![4.0, 1.0, 3.0, 8.0]), ("y_1".to_owned(), vec![5.0])]);
+    let value = |latex: &str| evaluate_value(&parse_latex(latex, &HashMap::new())?, &HashMap::new(), &HashMap::new(), &lists);
+
+    assert_eq!(value("\\operatorname{median}\\left(x_{1}\\right)")?, Value::Scalar(3.5));
+    assert!(value("\\operatorname{median}\\left(x_{1}+y_{1}\\right)").is_err());
+    assert_eq!(value("\\operatorname{var}\\left(x_{1}\\right)")?, Value::Scalar(26.0 / 3.0));
+    assert_eq!(value("\\operatorname{stdev}\\left(x_{1}\\right)")?, Value::Scalar((26.0f64 / 3.0).sqrt()));
+    assert!(value("\\operatorname{var}\\left(y_{1}\\right)").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn distributions() -> error::Result<()> {
+    let value = |latex: &str| {
+        let mut root = parse_latex(latex, &HashMap::new())?;
+        simplify_tree(&mut root, &HashMap::new());
+        Ok::<_, error::AppError>(simplify_tree(&mut root, &HashMap::new()).unwrap())
+    };
+
+    assert_solutions(&[value("\\operatorname{normalpdf}\\left(0\\right)")?], &[1.0 / (2.0 * PI).sqrt()]);
+    assert_solutions(&[value("\\operatorname{normalpdf}\\left(3,1,2\\right)")?], &[(-0.5f64).exp() / (2.0 * (2.0 * PI).sqrt())]);
+    assert_solutions(&[value("\\operatorname{normalcdf}\\left(0\\right)")?], &[0.5]);
+    assert!((value("\\operatorname{normalcdf}\\left(1.96\\right)")? - 0.975).abs() < 1e-4);
+    assert_solutions(&[value("\\operatorname{binompdf}\\left(2,4,0.5\\right)")?], &[0.375]);
+    assert_solutions(&[value("\\operatorname{poissonpdf}\\left(2,3\\right)")?], &[4.5 * (-3.0f64).exp()]);
+    assert!(parse_latex("\\operatorname{binompdf}\\left(2,4\\right)", &HashMap::new()).is_err());
+
+    //The density can be drawn as a function of x
+    let pdf = parse_latex("\\operatorname{normalpdf}\\left(x,1,1\\right)", &HashMap::new())?;
+    assert_solutions(&[evaluate(&pdf, 1.0).unwrap()], &[1.0 / (2.0 * PI).sqrt()]);
+
+    Ok(())
+}
+
+#[test]
+fn error_function() -> error::Result<()> {
+    let erf = parse_latex("\\operatorname{erf}\\left(x\\right)", &HashMap::new())?;
+    assert_eq!(evaluate(&erf, 0.0), Some(0.0));
+    assert!((evaluate(&erf, 1.0).unwrap() - 0.842_700_792_949_715).abs() < 1e-14);
+    assert!((evaluate(&erf, -0.5).unwrap() + 0.520_499_877_813_046_5).abs() < 1e-14);
+
+    let erfc = parse_latex("\\operatorname{erfc}\\left(x\\right)", &HashMap::new())?;
+    assert!((evaluate(&erfc, 5.0).unwrap() - 1.537_459_794_428_035e-12).abs() < 1e-24);
+
+    //d/dx erf(x) = 2/sqrt(pi) e^(-x^2)
+    let derivative = derive_function(&erf)?;
+    assert_solutions(&[evaluate(&derivative, 0.0).unwrap()], &[2.0 / PI.sqrt()]);
+    assert_solutions(&[evaluate(&*derive_function(&erfc)?, 1.0).unwrap()], &[-2.0 / PI.sqrt() / E]);
+
+    let fact = parse_latex("x!", &HashMap::new())?;
+    assert_eq!(evaluate(&fact, 5.0), Some(120.0));
+    assert_eq!(evaluate(&fact, 0.0), Some(1.0));
+
+    Ok(())
+}
+
+#[test]
+fn limits() -> error::Result<()> {
+    let vars = HashMap::from([("a".to_owned(), 3.0)]);
+    let limit = |latex: &str| Limit::parse(latex, &HashMap::new(), &vars)?.unwrap().evaluate(&vars);
+    let approx = |latex: &str, expected: f64| {
+        let found = limit(latex).unwrap();
+        assert!((found - expected).abs() < 1e-6, "{latex}: found {found}, expected {expected}");
+    };
+
+    //Continuous functions and L'Hôpital's rule
+    assert_eq!(limit("\\lim_{x\\to2}x^2+1")?, 5.0);
+    assert_eq!(limit("\\lim_{x\\to0}\\frac{\\sin\\left(x\\right)}{x}")?, 1.0);
+    assert_eq!(limit("\\lim_{x\\to0}\\frac{1-\\cos\\left(x\\right)}{x^2}")?, 0.5);
+    assert_eq!(limit("\\lim_{t\\to2}\\frac{t^2-4}{t-2}")?, 4.0);
+    assert_eq!(limit("\\lim_{x\\to0}\\frac{\\sin\\left(ax\\right)}{x}")?, 3.0);
+    assert_eq!(limit("\\lim_{x\\to\\infty}\\frac{x^2+1}{2x^2+x}")?, 0.5);
+    assert_eq!(limit("\\lim_{x\\to\\infty}\\frac{x}{e^x}")?, 0.0);
+
+    //Numeric extrapolation
+    approx("\\lim_{x\\to\\infty}\\left(1+\\frac{1}{x}\\right)^x", E);
+    approx("\\lim_{x\\to-\\infty}\\left(1+\\frac{1}{x}\\right)^x", E);
+    approx("\\lim_{x\\to0^{+}}x\\ln\\left(x\\right)", 0.0);
+    approx("\\lim_{x\\to0^+}\\sqrt{x}", 0.0);
+
+    //One-sided limits
+    assert_eq!(limit("\\lim_{x\\to1^{-}}\\operatorname{floor}\\left(x\\right)")?, 0.0);
+    assert_eq!(limit("\\lim_{x\\to1^{+}}\\operatorname{floor}\\left(x\\right)")?, 1.0);
+    assert!(limit("\\lim_{x\\to1}\\operatorname{floor}\\left(x\\right)").is_err());
+
+    //Divergent and nonexistent limits
+    assert!(limit("\\lim_{x\\to0^{+}}\\frac{1}{x}").unwrap_err().to_string().contains("diverges to infinity"));
+    assert!(limit("\\lim_{x\\to0^{-}}\\frac{1}{x}").unwrap_err().to_string().contains("minus infinity"));
+    assert!(limit("\\lim_{x\\to0}\\frac{1}{x^2}").unwrap_err().to_string().contains("diverges to infinity"));
+    assert!(limit("\\lim_{x\\to0^{+}}\\ln\\left(x\\right)").unwrap_err().to_string().contains("minus infinity"));
+    assert!(limit("\\lim_{x\\to\\infty}e^x").unwrap_err().to_string().contains("diverges to infinity"));
+    assert!(limit("\\lim_{x\\to0}\\frac{1}{x}").unwrap_err().to_string().contains("doesn't exist"));
+    assert!(limit("\\lim_{x\\to0}\\sin\\left(\\frac{1}{x}\\right)").unwrap_err().to_string().contains("doesn't exist"));
+    assert!(limit("\\lim_{x\\to0^{-}}\\sqrt{x}").is_err());
+
+    assert!(Limit::parse("x^2", &HashMap::new(), &vars)?.is_none());
+    assert!(Limit::parse("\\lim_{t\\to0}tx", &HashMap::new(), &vars).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn latex_printer() -> error::Result<()> {
+    let cases = [
+        ("x^{2}-3x+1", "x^{2}-3x+1"),
+        ("-\\frac{x+1}{2x}", "-\\frac{x+1}{2x}"),
+        ("\\left(x+1\\right)^{3}\\left(x-2\\right)", "\\left(x+1\\right)^{3}\\left(x-2\\right)"),
+        ("2\\cdot3^{x}", "2\\cdot3^{x}"),
+        ("\\left(\\sin\\left(x\\right)\\right)^{2}+\\cos\\left(x^{2}\\right)", "\\left(\\sin\\left(x\\right)\\right)^{2}+\\cos\\left(x^{2}\\right)"),
+        ("\\operatorname{floor}\\left(x\\right)+\\sqrt{x}-\\ln\\left(x\\right)", "\\operatorname{floor}\\left(x\\right)+\\sqrt{x}-\\ln\\left(x\\right)"),
+        ("\\left(x+1\\right)!+x!", "\\left(x+1\\right)!+x!"),
+        ("e^{-x}+\\pi x", "e^{-x}+\\pi x"),
+        ("a_{1}x^{-2}", "a_{1}x^{-2}"),
+        ("y=-\\left(x-1\\right)", "y=-\\left(x-1\\right)"),
+    ];
+
+    for (latex, expected) in cases {
+        let tree = parse_latex(latex, &HashMap::new())?;
+        assert_eq!(to_latex(&tree), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn latex_round_trip() -> error::Result<()> {
+    let x = || Node::Unknown { name: "x".to_owned() };
+    let constant = |value: f64| Node::Constant { value };
+    let power = |a: Node, b: Node| Node::Binary { op_type: BinaryOperation::Power, lhs: Some(Box::new(a)), rhs: Some(Box::new(b)) };
+
+    //Trees that don't come from the parser, with negative constants and nested operations
+    let trees = [
+        Node::multiply(constant(-2.5), power(x(), constant(3.0))),
+        Node::add(Node::multiply(x(), constant(-1.0)), constant(-4.0)),
+        Node::op(UnaryOperation::Minus, Node::add(x(), constant(-1.0))),
+        Node::op(UnaryOperation::Minus, constant(-3.0)),
+        power(Node::op(UnaryOperation::Sin, x()), constant(2.0)),
+        Node::multiply(power(constant(-2.0), Node::op(UnaryOperation::Minus, constant(3.0))), x()),
+        power(Node::divide(x(), constant(2.0)), constant(2.0)),
+        power(power(x(), constant(2.0)), constant(0.5)),
+        Node::op(UnaryOperation::Fact, power(constant(2.0), constant(2.0))),
+        Node::multiply(constant(0.000015), x()),
+        Node::multiply(constant(PI), Node::multiply(constant(2.0), x())),
+        Node::NAry { op_type: NAryOperation::Multiply, children: vec![Box::new(x()), Box::new(constant(3.0)), Box::new(Node::op(UnaryOperation::Minus, x()))] },
+        *derive_function(&parse_latex("\\frac{\\operatorname{erf}\\left(x\\right)}{x^{2}+1}", &HashMap::new())?)?,
+        *derive_function(&parse_latex("\\tan\\left(2x\\right)\\log\\left(x\\right)", &HashMap::new())?)?,
+    ];
+
+    for tree in trees {
+        let latex = to_latex(&tree);
+        let parsed = parse_latex(&latex, &HashMap::new())?;
+        assert_eq!(to_latex(&parsed), latex);
+
+        for x in [0.3, 1.7, 2.0] {
+            let (a, b) = (evaluate(&tree, x).unwrap(), evaluate(&parsed, x).unwrap());
+            assert!((a - b).abs() < 1e-9 * a.abs().max(1.0), "{latex} is {b} in {x}, expected {a}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn json_tree() -> error::Result<()> {
+    let tree = parse_latex("-\\sin\\left(x\\right)", &HashMap::new())?;
+    assert_eq!(
+        tree.to_json()?,
+        r#"{"version":1,"root":{"type":"unary","op_type":"minus","child":{"type":"unary","op_type":"sin","child":{"type":"unknown","name":"x"}}}}"#
+    );
+
+    for latex in ["\\frac{x^{2}+a_{1}}{3x}=y", "\\operatorname{erf}\\left(x\\right)x!", "\\begin{pmatrix}1&x\\\\2&3\\end{pmatrix}"] {
+        let tree = parse_latex(latex, &HashMap::new())?;
+        assert_eq!(to_latex(&Node::from_json(&tree.to_json()?)?), to_latex(&tree));
+    }
+
+    assert!(Node::from_json(r#"{"version":0,"root":{"type":"constant","value":1.0}}"#).is_err());
+    assert!(Node::from_json(r#"{"version":1,"root":{"type":"constant"}}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn mathml_reader() -> error::Result<()> {
+    let read = |body: &str| parse_mathml(&format!("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{body}</math>"));
+
+    //Written by other tools, with entities, namespace prefixes and the invisible operators
+    type Expected = fn(f64) -> f64;
+    let cases: [(&str, Expected); 6] = [
+        ("<mfrac><mrow><mn>1</mn><mo>+</mo><mi>x</mi></mrow><msqrt><mi>x</mi></msqrt></mfrac>", |x| (1.0 + x) / x.sqrt()),
+        ("<mrow><mn>2</mn><mo>&InvisibleTimes;</mo><msup><mi>x</mi><mn>3</mn></msup><mo>&minus;</mo><mi>&pi;</mi></mrow>", |x| 2.0 * x.powi(3) - PI),
+        ("<msup><mi>sin</mi><mn>2</mn></msup><mo>&#x2061;</mo><mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow><mo>+</mo><mi>cos</mi><mi>x</mi>", |x| x.sin().powi(2) + x.cos()),
+        ("<mroot><mi>x</mi><mn>3</mn></mroot><mo>&#xD7;</mo><mo>|</mo><mn>1</mn><mo>-</mo><mi>x</mi><mo>|</mo>", |x| x.cbrt() * (1.0 - x).abs()),
+        ("<m:mrow><m:msub><m:mi>log</m:mi><m:mn>2</m:mn></m:msub><m:mi>x</m:mi><m:mo>/</m:mo><m:mn>4</m:mn></m:mrow>", |x| x.log2() / 4.0),
+        ("<mo>-</mo><mi>x</mi><mo>!</mo><mo>+</mo><msup><mi>e</mi><mrow><mo>-</mo><mi>x</mi></mrow></msup>", |x| -UnaryOperation::factorial(x) + (-x).exp()),
+    ];
+    for (body, f) in cases {
+        let tree = read(body)?;
+        for x in [0.3, 1.7, 2.0] {
+            let (a, b) = (f(x), evaluate(&tree, x).unwrap());
+            assert!((a - b).abs() < 1e-9 * a.abs().max(1.0), "{body} is {b} in {x}, expected {a}");
+        }
+    }
+
+    assert_eq!(to_latex(&read("<msub><mi>a</mi><mn>1</mn></msub><mi>y</mi><mo>=</mo><mi>x</mi>")?), "a_{1}y=x");
+    assert!(read("<mi>x</mi><mo>+</mo>").is_err());
+    assert!(read("<mfrac><mi>x</mi></mfrac>").is_err());
+    assert!(read("<mi>x</mi></mrow>").is_err());
+    assert!(parse_mathml("<mi>x</mi>").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn mathml_writer() -> error::Result<()> {
+    let tree = parse_latex("\\frac{\\sqrt{x}}{2}-3x^{2}", &HashMap::new())?;
+    assert_eq!(
+        to_content_mathml(&tree),
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><apply><plus/><apply><divide/><apply><root/><ci>x</ci></apply><cn>2</cn></apply>\
+        <apply><minus/><apply><times/><cn>3</cn><apply><power/><ci>x</ci><cn>2</cn></apply></apply></apply></apply></math>"
+    );
+    assert_eq!(
+        to_presentation_mathml(&tree),
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mfrac><msqrt><mi>x</mi></msqrt><mn>2</mn></mfrac><mo>−</mo>\
+        <mrow><mn>3</mn><mo>&#x2062;</mo><msup><mi>x</mi><mn>2</mn></msup></mrow></mrow></math>"
+    );
+
+    //The Presentation MathML is read back as the same formula
+    let latex = [
+        "\\frac{x^{3}-2}{\\left(x+1\\right)^{5}}+\\sin\\left(-\\pi x\\right)e^{x}",
+        "-\\left(x-1\\right)\\cdot 2\\cdot 3+\\operatorname{floor}\\left(x\\right)!",
+        "\\left(-2\\right)^{-3}x-\\ln\\left(\\operatorname{abs}\\left(x\\right)\\right)",
+        "\\operatorname{erf}\\left(x\\right)^{2}-\\left(-x\\right)",
+    ];
+    for latex in latex {
+        let tree = parse_latex(latex, &HashMap::new())?;
+        let parsed = parse_mathml(&to_presentation_mathml(&tree))?;
+        for x in [0.3, 1.7, 2.0] {
+            let (a, b) = (evaluate(&tree, x).unwrap(), evaluate(&parsed, x).unwrap());
+            assert!((a - b).abs() < 1e-9 * a.abs().max(1.0), "{latex} is {b} in {x}, expected {a}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn drawn_equations_are_written() -> error::Result<()> {
+    use crate::{export_mathml, expression_latex, process, CompilerState, ExpressionRef};
+
+    let mut state = CompilerState::default();
+    process("x^{2}+y^{2}=1", &mut state, 0)?;
+    process("y=x^{2}", &mut state, 1)?;
+
+    for (i, content) in [(0, "<apply><eq/>"), (1, "<apply><eq/><ci>y</ci>")] {
+        assert!(export_mathml(&ExpressionRef::Index(i), true, &state)?.contains(content));
+        assert!(export_mathml(&ExpressionRef::Index(i), false, &state)?.contains("<mo>=</mo>"));
+    }
+    assert_eq!(expression_latex(&ExpressionRef::Index(0), 0, &state)?, "x^{2}+y^{2}=1");
+    assert_eq!(expression_latex(&ExpressionRef::Index(1), 0, &state)?, "y=x^{2}");
+    assert!(expression_latex(&ExpressionRef::Index(1), 1, &state).is_ok());
+    assert!(expression_latex(&ExpressionRef::Index(0), 1, &state).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{self, AppError};
+
+use super::{ast::Node, list::aggregate, matrix::Matrix, BinaryOperation, NAryOperation, UnaryOperation};
+
+/// The value of an expression without unknowns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Scalar(f64),
+    Matrix(Matrix),
+    List(Vec<f64>),
+}
+
+/// Evaluates an expression without unknowns, which can have matrices and lists in it
+pub fn evaluate_value(root: &Node, variable_map: &HashMap<String, f64>, matrix_map: &HashMap<String, Matrix>, list_map: &HashMap<String, Vec<f64>>) -> error::Result<Value> {
+    let eval = |node: &Node| evaluate_value(node, variable_map, matrix_map, list_map);
+    let eval_child = |node: &Option<Box<Node>>| eval(node.as_ref().ok_or(AppError::EmptyError)?);
+
+    match root {
+        Node::Constant { value } => Ok(Value::Scalar(*value)),
+        Node::Variable { name } => {
+            if let Some(m) = matrix_map.get(name) {
+                Ok(Value::Matrix(m.clone()))
+            } else if let Some(l) = list_map.get(name) {
+                Ok(Value::List(l.clone()))
+            } else {
+                let v = variable_map.get(name).ok_or_else(|| AppError::IoError(format!("There are no variable called {name}")))?;
+                Ok(Value::Scalar(*v))
+            }
+        }
+        Node::Unknown { .. } => Err(AppError::MathError("An expression with unknowns doesn't have a value".to_owned())),
+        Node::Matrix { rows } => {
+            let rows: error::Result<Vec<Vec<f64>>> = rows.iter()
+                .map(|row| row.iter().map(|e| match eval(e)? {
+                    Value::Scalar(v) => Ok(v),
+                    _ => Err(AppError::MathError("A matrix can only have numbers inside".to_owned())),
+                }).collect())
+                .collect();
+            Ok(Value::Matrix(Matrix::new(rows?)?))
+        }
+        Node::Unary { op_type, child } => match (op_type, eval_child(child)?) {
+            (op, Value::Scalar(v)) => Ok(Value::Scalar((op.func()?)(v))),
+            (UnaryOperation::Minus, Value::Matrix(m)) => Ok(Value::Matrix(m.scale(-1.0))),
+            (UnaryOperation::Det, Value::Matrix(m)) => Ok(Value::Scalar(m.determinant()?)),
+            (UnaryOperation::Eig, Value::Matrix(m)) => {
+                let eigenvalues = m.eigenvalues()?.into_iter().map(|e| vec![e]).collect();
+                Ok(Value::Matrix(Matrix::new(eigenvalues)?))
+            }
+            (op, Value::Matrix(_)) => Err(AppError::MathError(format!("{op:?} can't be applied to a matrix"))),
+            (op, Value::List(l)) if op.is_aggregate() => Ok(Value::Scalar(aggregate(op, &l)?)),
+            (op, Value::List(l)) => {
+                let f = op.func()?;
+                Ok(Value::List(l.into_iter().map(f).collect()))
+            }
+        },
+        Node::Binary { op_type, lhs, rhs } => {
+            //A^{T} is the transpose, unless T is a variable
+            if let (BinaryOperation::Power, Some(rhs)) = (op_type, rhs) {
+                if matches!(rhs.as_ref(), Node::Variable { name } if name == "T" && !variable_map.contains_key(name)) {
+                    return match eval_child(lhs)? {
+                        Value::Matrix(m) => Ok(Value::Matrix(m.transpose())),
+                        _ => Err(AppError::MathError("Only a matrix can be transposed".to_owned())),
+                    };
+                }
+            }
+
+            match (op_type, eval_child(lhs)?, eval_child(rhs)?) {
+                (BinaryOperation::Division, Value::Matrix(m), Value::Scalar(s)) => Ok(Value::Matrix(m.scale(1.0 / s))),
+                (BinaryOperation::Power, Value::Matrix(m), Value::Scalar(n)) if n.fract() == 0.0 => {
+                    if n.abs() > i32::MAX as f64 {
+                        return Err(AppError::MathError(format!("A matrix can't be raised to {n}, the exponent is too big")));
+                    }
+                    Ok(Value::Matrix(m.power(n as i32)?))
+                }
+                (op, Value::Matrix(_), _) | (op, _, Value::Matrix(_)) => Err(AppError::MathError(format!("{op:?} can't be done with these matrices"))),
+                (op, a, b) => elementwise(a, b, op.func()?),
+            }
+        }
+        Node::NAry { op_type, children } => {
+            let mut values = children.iter().map(|e| eval(e));
+            let first = values.next().ok_or(AppError::EmptyError)??;
+
+            values.try_fold(first, |acc, value| match (op_type, acc, value?) {
+                (NAryOperation::Add, Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(a.sum(&b)?)),
+                (NAryOperation::Multiply, Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(a.product(&b)?)),
+                (NAryOperation::Multiply, Value::Matrix(m), Value::Scalar(s)) | (NAryOperation::Multiply, Value::Scalar(s), Value::Matrix(m)) => {
+                    Ok(Value::Matrix(m.scale(s)))
+                }
+                (NAryOperation::Add, Value::Matrix(_), _) | (NAryOperation::Add, _, Value::Matrix(_)) => {
+                    Err(AppError::MathError("Only a matrix can be added to a matrix".to_owned()))
+                }
+                (NAryOperation::Multiply, Value::Matrix(_), _) | (NAryOperation::Multiply, _, Value::Matrix(_)) => {
+                    Err(AppError::MathError("A matrix can't be multiplied by a list".to_owned()))
+                }
+                (op, a, b) => elementwise(a, b, op.func()?),
+            })
+        }
+    }
+}
+
+/// Changes the parts of the tree with matrices and lists by their value, so it can be simplified and compiled.
+/// If the whole tree is a matrix or a list it is returned, as it can't be drawn
+pub fn resolve_values(root: &mut Node, variable_map: &HashMap<String, f64>, matrix_map: &HashMap<String, Matrix>, list_map: &HashMap<String, Vec<f64>>) -> error::Result<Option<Value>> {
+    if !has_values(root, matrix_map, list_map) {
+        return Ok(None);
+    }
+
+    if root.first_unknown().is_none() {
+        return match evaluate_value(root, variable_map, matrix_map, list_map)? {
+            Value::Scalar(value) => {
+                *root = Node::Constant { value };
+                Ok(None)
+            }
+            value => Ok(Some(value)),
+        };
+    }
+
+    let children: Vec<&mut Node> = match root {
+        Node::Unary { child, .. } => child.iter_mut().map(|e| e.as_mut()).collect(),
+        Node::Binary { lhs, rhs, .. } => lhs.iter_mut().chain(rhs.iter_mut()).map(|e| e.as_mut()).collect(),
+        Node::NAry { children, .. } => children.iter_mut().map(|e| e.as_mut()).collect(),
+        _ => Vec::new(),
+    };
+
+    for child in children {
+        if let Some(value) = resolve_values(child, variable_map, matrix_map, list_map)? {
+            return Err(not_drawable(&value));
+        }
+    }
+
+    if has_values(root, matrix_map, list_map) {
+        return Err(AppError::MathError("A matrix can't be drawn".to_owned()));
+    }
+
+    Ok(None)
+}
+
+fn not_drawable(value: &Value) -> AppError {
+    match value {
+        Value::List(_) => AppError::MathError("A list can't be drawn, only a number calculated from it like its mean".to_owned()),
+        _ => AppError::MathError("A matrix can't be drawn".to_owned()),
+    }
+}
+
+/// A number is operated with every element of the list, and two lists element by element
+fn elementwise(a: Value, b: Value, f: fn(f64, f64) -> f64) -> error::Result<Value> {
+    match (a, b) {
+        (Value::Scalar(a), Value::Scalar(b)) => Ok(Value::Scalar(f(a, b))),
+        (Value::List(l), Value::Scalar(s)) => Ok(Value::List(l.into_iter().map(|e| f(e, s)).collect())),
+        (Value::Scalar(s), Value::List(l)) => Ok(Value::List(l.into_iter().map(|e| f(s, e)).collect())),
+        (Value::List(a), Value::List(b)) if a.len() == b.len() => Ok(Value::List(a.into_iter().zip(b).map(|(a, b)| f(a, b)).collect())),
+        (Value::List(a), Value::List(b)) => Err(AppError::MathError(format!("A list of {} elements can't be operated with one of {}", a.len(), b.len()))),
+        _ => Err(AppError::MathError("These values can't be operated together".to_owned())),
+    }
+}
+
+fn has_values(root: &Node, matrix_map: &HashMap<String, Matrix>, list_map: &HashMap<String, Vec<f64>>) -> bool {
+    match root {
+        Node::Matrix { .. } => true,
+        Node::Variable { name } => matrix_map.contains_key(name) || list_map.contains_key(name),
+        Node::Unary { child, .. } => child.iter().any(|e| has_values(e, matrix_map, list_map)),
+        Node::Binary { lhs, rhs, .. } => lhs.iter().chain(rhs.iter()).any(|e| has_values(e, matrix_map, list_map)),
+        Node::NAry { children, .. } => children.iter().any(|e| has_values(e, matrix_map, list_map)),
+        _ => false,
+    }
+}
@@ -22,6 +22,13 @@ export let backState: BackgroundState = {
     size: DEFAULT_SIZE,
 };
 
+export interface DataSet {
+    points: { x: number, y: number }[],
+    color: string,
+}
+/** The points of the imported data tables, by their number */
+export const dataSets: Map<number, DataSet> = new Map();
+
 export const returnHome = () => {
     backState = {
         x: SIDE/2,
@@ -137,6 +144,22 @@ export const drawBack = () => {
         ctx.lineTo(SIDE, y);
         ctx.stroke();
     });
+
+    drawDataSets(ctx);
+}
+
+function drawDataSets(ctx: CanvasRenderingContext2D) {
+    //The same units per pixel as the fragment shader
+    const pixel = backState.mant * Math.pow(10, backState.exp) / backState.size;
+
+    for(const set of dataSets.values()) {
+        ctx.fillStyle = set.color;
+        for(const p of set.points) {
+            ctx.beginPath();
+            ctx.arc(backState.x + p.x/pixel, backState.y - p.y/pixel, 4, 0, 2*Math.PI);
+            ctx.fill();
+        }
+    }
 }
 
 function drawAxes(
@@ -11,6 +11,10 @@ const MQ = MathQuill.getInterface(2);
 export const expressions : Map<number, EquationBox> = new Map();
 export const functionSet: Map<string, number> = new Map();
 export const variableSet: Map<string, number> = new Map();
+/** The variables defined by the solution of a system of several equations */
+export const systemVariables: Set<string> = new Set();
+/** The columns of the imported data tables, like x_1 and y_1 */
+export const listSet: Set<string> = new Set();
 
 export enum EditAction {
     ADD, REMOVE, REFRESH
@@ -23,8 +27,8 @@ export interface EditPayload {
 
 export const CHANGED_EMIT_CODE: string = 'changed';
 
-const AUTO_FUNCTIONS = 'sin cos tan sec csc cosec cotan floor abs ceil log ln';
-const AUTO_COMMANDS = 'pi theta sqrt sum rho phi lambda';
+const AUTO_FUNCTIONS = 'sin cos tan sec csc cosec cotan floor abs ceil log ln expand factor solve det eig mean median stdev var erf erfc normalpdf normalcdf binompdf poissonpdf lim';
+const AUTO_COMMANDS = 'pi theta sqrt sum rho phi lambda sim infty';
 
 export const DEFAULT_MATH_CONFIG = {
     spaceBehavesLikeTab: true,
@@ -59,6 +63,9 @@ export class EquationBox {
     mathField?: any;
     oldLatex: string;
     solutionBox?: HTMLElement;
+    solvedVariable?: string;
+    systemNames?: string[];
+    fittedNames?: string[];
     undefVarsBar: UndefVariableBar;
     code?: string;
     bytecode?: Array<Array<number>>;
@@ -127,6 +134,22 @@ export class EquationBox {
             variableSet.delete(v);
         }
 
+        if(this.solvedVariable) {
+            deleteVariable(this.solvedVariable, this);
+            variableSet.delete(this.solvedVariable);
+        }
+
+        this.systemNames?.forEach(e => {
+            deleteVariable(e, this);
+            variableSet.delete(e);
+            systemVariables.delete(e);
+        });
+
+        this.fittedNames?.forEach(e => {
+            deleteVariable(e, this);
+            variableSet.delete(e);
+        });
+
         this.htmlElement.remove();
         expressions.delete(this.number);
     }
@@ -216,20 +239,51 @@ export class EquationBox {
         return name;
     }
 
+    /**
+     * @returns The only undefined variable of an equation like a^2=9, so it can be solved for it
+     */
+    unsolvedVariable(variables: Set<string>): string | null {
+        const undefinedVariables = this.unsolvedVariables(variables);
+        return undefinedVariables.length == 1? undefinedVariables[0]! : null;
+    }
+
+    /**
+     * @returns The undefined variables of an equation that can be solved for them, alone or with other equations
+     */
+    unsolvedVariables(variables: Set<string>): string[] {
+        const latex: string = this.mathField.latex();
+        if(!latex.includes('=') || variables.has('x') || variables.has('y'))
+            return [];
+
+        return [...variables].filter(e => !"xye".includes(e) && !listSet.has(e))
+            .filter(e => !variableSet.has(e) || variableSet.get(e) === this.number || systemVariables.has(e));
+    }
+
     getVariables() {
         const fnName = this.functionCharacter();
         const varName = this.variableCharacter();
 
+        //A name with a subscript, like x_1, is a single variable
         const vars = Array.from(this.htmlElement.getElementsByTagName('var'))
-                .filter(e => !e.classList.contains('mq-operator-name'))
-                .map(e => e.textContent)
+                .filter(e => !e.classList.contains('mq-operator-name') && !e.closest('.mq-sub'))
+                .map(e => {
+                    const sub = e.nextElementSibling?.querySelector('.mq-sub');
+                    return sub? `${e.textContent}_${sub.textContent}` : e.textContent;
+                })
                 .flatMap(e => e? e:[])
                 .filter(e => !functionSet.has(e));
         
         if(fnName || varName)
             vars.splice(0, 1);
 
-        return new Set(vars);
+        //The T of a Taylor polynomial, T_{n}(f, a), isn't a variable
+        const latex: string = this.mathField.latex();
+        //Neither is the T of a transpose, A^{T}
+        const hasT = latex.includes('T_') || latex.includes('^{T}');
+        //Nor the variable of a limit, \lim_{t\to a}
+        const limitVar = latex.match(/^\\(?:operatorname\{)?lim\}?_\{([a-zA-Z])\\(?:to|rightarrow)/)?.[1];
+
+        return new Set(vars.filter(e => !(hasT && e == 'T') && e != limitVar));
     }
 
     writeFunctionBrackets() {
@@ -249,6 +303,51 @@ export class EquationBox {
         this.solutionBox.textContent = numeral(n).format('0[.][000000]');
     }
 
+    setSolutionValues(values: [string, number][]) {
+        const latex = values.map(([name, n]) => [name, (n > 0 && n < 1e-6)? 0:n])
+            .map(([name, n]) => `${name}=${numeral(n).format('0[.][000000]')}`)
+            .join(',\\ ');
+        this.setSolutionLatex(latex);
+    }
+
+    setSolutionMatrix(rows: number[][]) {
+        const body = rows.map(row => row.map(n => (n > 0 && n < 1e-6)? 0:n)
+                .map(n => numeral(n).format('0[.][000000]'))
+                .join('&'))
+            .join('\\\\');
+        this.setSolutionLatex(`\\begin{pmatrix}${body}\\end{pmatrix}`);
+    }
+
+    setSolutionList(values: number[]) {
+        const list = values.map(n => (n > 0 && n < 1e-6)? 0:n)
+            .map(n => numeral(n).format('0[.][000000]'))
+            .join(',\\ ');
+        this.setSolutionLatex(`\\left[${list}\\right]`);
+    }
+
+    setSolutionLatex(latex: string) {
+        if(!this.solutionBox) 
+            throw Error("No solution box");
+        this.solutionBox.style.display = 'inline';
+
+        MQ.StaticMath(this.solutionBox).latex(latex);
+    }
+
+    /**
+     * Shows the conditions for a drawn expression to be defined, like x ≥ 0, if there are any
+     */
+    setDomain(conditions: string[]) {
+        if(!this.solutionBox) 
+            throw Error("No solution box");
+        if(conditions.length == 0) {
+            this.hideSolutionBox();
+            return;
+        }
+
+        this.solutionBox.style.display = 'inline';
+        this.solutionBox.textContent = conditions.join(',  ');
+    }
+
     hideSolutionBox() {
         if(!this.solutionBox) 
             throw Error("No solution box");
@@ -259,7 +358,7 @@ export class EquationBox {
      * @returns The number of undefined variables
      */
     showUndefinedVariables(variables: Set<string>): number {
-        const undefinedVariables = [...variables].filter(e => !variableSet.has(e))
+        const undefinedVariables = [...variables].filter(e => !variableSet.has(e) && !listSet.has(e))
             .filter(e => !"xye".includes(e));
         this.undefVarsBar.ofArray(undefinedVariables);
         return undefinedVariables.length;
@@ -1,6 +1,7 @@
-import { returnHome } from "./background";
-import { CHANGED_EMIT_CODE, EditAction, EditPayload, EquationBox, expressions, functionSet, variableSet } from "./equations";
+import { dataSets, returnHome } from "./background";
+import { CHANGED_EMIT_CODE, EditAction, EditPayload, EquationBox, expressions, functionSet, listSet, systemVariables, variableSet } from "./equations";
 import { listen } from "@tauri-apps/api/event";
+import { message, open } from "@tauri-apps/api/dialog";
 import { changeDrawMode, draw } from "./renderer";
 import { invoke } from "@tauri-apps/api";
 
@@ -29,6 +30,30 @@ moreBtn?.addEventListener("click", () => {
 const homeBtn = document.getElementById('home');
 homeBtn?.addEventListener('click', returnHome);
 
+interface DataTable {
+    names: [string, string],
+    points: { x: number, y: number }[],
+}
+
+const importBtn = document.getElementById('import');
+importBtn?.addEventListener('click', async () => {
+    const path = await open({ filters: [{ name: 'CSV', extensions: ['csv'] }] });
+    if(typeof path !== 'string')
+        return;
+
+    const table = dataSets.size + 1;
+    try {
+        const data = <DataTable> await invoke('import_csv', { path: path, table: table });
+        data.names.forEach(n => listSet.add(n));
+        dataSets.set(table, { points: data.points, color: `hsl(${(table * 83) % 360} 69% 69%)` });
+
+        expressions.forEach(e => e.refresh());
+    } catch(error) {
+        console.warn(error);
+        await message(String(error), { title: "The table couldn't be imported", type: 'error' });
+    }
+});
+
 window.addEventListener('DOMContentLoaded', () => {
     draw();
 })
@@ -39,10 +64,15 @@ window.addEventListener('keyup', e => {
     }
 })
 
+const POLYNOMIAL_COMMAND = /^\\operatorname\{(expand|factor|solve)\}/;
+
 export interface Response {
     bytecode: number[][],
     code: string,
     num?: number,
+    matrix?: number[][],
+    list?: number[],
+    domain: string[],
 }
 
 listen(CHANGED_EMIT_CODE, async event => {
@@ -71,11 +101,52 @@ listen(CHANGED_EMIT_CODE, async event => {
         const fnName = eq.functionCharacter(); 
         variables = eq.getVariables();
 
+        const unsolvedNames = (varName || fnName)? [] : eq.unsolvedVariables(variables);
+        eq.systemNames = undefined;
+        if(unsolvedNames.length > 1) {
+            eq.setDrawable(false);
+            eq.showUndefinedVariables(new Set());
+
+            eq.systemNames = unsolvedNames;
+            await solveSystem(eq, payload.action);
+            return;
+        }
+
+        const unsolvedName = unsolvedNames.length == 1? unsolvedNames[0]! : null;
+        if(unsolvedName && !systemVariables.has(unsolvedName)) {
+            if(functionSet.has(unsolvedName))
+                throw Error(`There's already a function with that name ${unsolvedName}`);
+
+            eq.setDrawable(false);
+            eq.showUndefinedVariables(new Set());
+
+            variableSet.set(unsolvedName, id);
+            eq.solvedVariable = unsolvedName;
+            await solveVariable(unsolvedName, eq, latex, payload.action);
+            eq.toggleError();
+            return;
+        }
+
+        if(latex.includes('\\sim')) {
+            eq.showUndefinedVariables(new Set());
+            await fitModel(eq, latex, variables, payload.action, exprIdx);
+            eq.toggleError();
+            return;
+        }
+
         if(eq.showUndefinedVariables(variables) > 0) {
             eq.toggleError();
             return;
         }
 
+        if(POLYNOMIAL_COMMAND.test(latex)) {
+            eq.setDrawable(false);
+            const solution = <string> await invoke('polynomial_command', { eq: latex });
+            eq.setSolutionLatex(solution);
+            eq.toggleError();
+            return;
+        }
+
         if(varName) {
             eq.hideSolutionBox();
             if(variableSet.has(varName) && variableSet.get(varName) !== id)
@@ -85,7 +156,12 @@ listen(CHANGED_EMIT_CODE, async event => {
 
             variableSet.set(varName, id);
             const val = await addVariable(varName, eq, latex.substring(2), payload.action);
-            eq.setSolutionValue(val!);
+            if(Array.isArray(val) && Array.isArray(val[0]))
+                eq.setSolutionMatrix(<number[][]> val);
+            else if(Array.isArray(val))
+                eq.setSolutionList(<number[]> val);
+            else
+                eq.setSolutionValue(val!);
             eq.toggleError();
             return;
         } else {
@@ -118,8 +194,12 @@ listen(CHANGED_EMIT_CODE, async event => {
 
         if(response.num !== null && response.num !== undefined) {
             eq.setSolutionValue(response.num);
+        } else if(response.matrix) {
+            eq.setSolutionMatrix(response.matrix);
+        } else if(response.list) {
+            eq.setSolutionList(response.list);
         } else {
-            eq.hideSolutionBox();
+            eq.setDomain(response.domain);
 
             eq.code = response.code;
             eq.bytecode = response.bytecode;
@@ -150,6 +230,7 @@ export const addFunction = async (fnName: string, latex: string, eq: EquationBox
                 if(id != eq.number)
                     expressions.get(id)?.refresh();
 
+        eq.setDomain(response.domain);
         eq.code = response.code;
         eq.bytecode = response.bytecode;
         await draw();
@@ -181,7 +262,7 @@ export const addVariable = async (varName: string, eq: EquationBox, latex: strin
     }
     
     try {
-        const val = <number> await invoke('add_variable', { name: varName, content: latex });
+        const val = <number | number[][] | number[]> await invoke('add_variable', { name: varName, content: latex });
         if(action != EditAction.REFRESH)
             expressions.forEach(e => e.refresh());
         
@@ -192,6 +273,87 @@ export const addVariable = async (varName: string, eq: EquationBox, latex: strin
     }
 }
 
+export const solveVariable = async (varName: string, eq: EquationBox, latex: string, action: EditAction) => {
+    try {
+        const solutions = <number[]> await invoke('solve_for_variable', { name: varName, eq: latex });
+        if(action != EditAction.REFRESH)
+            expressions.forEach(e => e.refresh());
+
+        eq.setSolutionValues(solutions.map((e): [string, number] => [varName, e]));
+    } catch(error) {
+        console.warn(error);
+        eq.writeError(error);
+    }
+}
+
+interface FitResponse {
+    parameters: [string, number][],
+    r_squared: number,
+    residuals: number[],
+    curve?: Response,
+}
+
+/**
+ * Fits the undefined variables of a model like y_1 \sim a x_1 + b to the data, the curve is drawn if it only uses one list
+ */
+export const fitModel = async (eq: EquationBox, latex: string, variables: Set<string>, action: EditAction, exprIdx: number) => {
+    const names = [...variables].filter(e => !"xye".includes(e) && !listSet.has(e))
+        .filter(e => !variableSet.has(e) || variableSet.get(e) === eq.number);
+
+    try {
+        const fit = <FitResponse> await invoke('fit_parameters', { names: names, eq: latex, exprIdx: exprIdx });
+        names.forEach(e => variableSet.set(e, eq.number));
+        eq.fittedNames = names;
+
+        eq.setSolutionValues([...fit.parameters, ['R^{2}', fit.r_squared]]);
+        eq.setDrawable(fit.curve !== null && fit.curve !== undefined);
+        if(fit.curve) {
+            eq.code = fit.curve.code;
+            eq.bytecode = fit.curve.bytecode;
+            await draw();
+        }
+
+        if(action != EditAction.REFRESH)
+            expressions.forEach(e => e.refresh());
+    } catch(error) {
+        console.warn(error);
+        eq.writeError(error);
+    }
+}
+
+/**
+ * Solves the equations that share several undefined variables, when there are as many equations as variables
+ */
+export const solveSystem = async (eq: EquationBox, action: EditAction) => {
+    const system = [...expressions.values()].filter(e => e.systemNames);
+    const names = [...new Set(system.flatMap(e => e.systemNames!))];
+    if(names.length != system.length) {
+        eq.writeError(new Error(`A system with ${names.length} unknowns needs ${names.length} equations`));
+        return;
+    }
+
+    try {
+        const equations = system.map(e => <string> e.mathField.latex());
+        const solution = <Record<string, number>> await invoke('solve_for_variables', { names: names, equations: equations });
+        names.forEach(e => {
+            variableSet.set(e, eq.number);
+            systemVariables.add(e);
+        });
+
+        system.forEach(e => {
+            e.error = false;
+            e.toggleError();
+            e.setSolutionValues(e.systemNames!.map((n): [string, number] => [n, solution[n]!]));
+        });
+
+        if(action != EditAction.REFRESH)
+            expressions.forEach(e => e.refresh());
+    } catch(error) {
+        console.warn(error);
+        eq.writeError(error);
+    }
+}
+
 export const deleteVariable = async (varName: string, eq: EquationBox) => {
     try {
         await invoke('delete_variable', { name: varName });
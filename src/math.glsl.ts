@@ -7,17 +7,22 @@ const float ONE = 1.0;
 const float ZERO = 0.0;
 const float LN10 = 2.30258509299;
 
+//The quiet NaN, the undefined values are the same as in the CPU
+float fnan() { return uintBitsToFloat(0x7fc00000u); }
+//The infinity, a literal like 1e1000 overflows and some compilers reject it
+float finf() { return uintBitsToFloat(0x7f800000u); }
+
 //ONE ARGUMENT PRIMITIVES
 float fexp(float x) { return exp(x); }
 float fminus(float x) { return -x; }
 float fln(float x) { 
     if(x <= 0.0)
-        return -1e1000;
+        return -finf();
     return log(x);    
 }
 float fsqrt(float x) {
     if(x < 0.0)
-        return -1e1000;
+        return -finf();
     return sqrt(x);
 }
 float fpow(float base, float ex) {
@@ -33,11 +38,42 @@ float fceil(float x) {
     if(frac > 1e-10) n++;
     return float(n);
 }
+float ffact(float x) {
+    if(x < 0.0)
+        return fnan();
+    if(x > 34.0)
+        return finf();
+    float ans = 1.0;
+    for(int i=2; i<=int(x); ++i)
+        ans *= float(i);
+    return ans;
+}
+//Abramowitz and Stegun 7.1.26, the error is below 1.5e-7
+float ferf(float x) {
+    float t = 1.0 / (1.0 + 0.3275911 * abs(x));
+    float poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    return sign(x) * (1.0 - poly * exp(-x * x));
+}
+float ferfc(float x) {
+    return 1.0 - ferf(x);
+}
 bool fneg(float x) {
     if(x < 0.0) 
         return true;
     return false;
 }
+//The parity of the integers of floor and ceil, it changes in every jump
+bool fodd(float n) {
+    return mod(n, 2.0) >= 1.0;
+}
+//A condition of the domain: 0 is x>0, 1 is x>=0 and 2 is x!=0, NaN never satisfies them
+bool fdefined(float x, int requirement) {
+    if(requirement == 0)
+        return x > 0.0;
+    if(requirement == 1)
+        return x >= 0.0;
+    return x != 0.0 && x == x;
+}
 
 //TWO ARGUMENT PRIMITIVES
 float fadd(float x, float y) { return x + y; }
@@ -1,4 +1,5 @@
 import { ColorTranslator } from "colortranslator";
+import { invoke } from "@tauri-apps/api";
 import { SIDE, backState, drawBack } from "./background";
 import { expressions } from "./equations";
 import { MATH_GLSL } from "./math.glsl";
@@ -6,6 +7,15 @@ import { T_FRAGMENT_GLSL } from "./t_fragment.glsl";
 
 let shaderProgram: WebGLProgram | null;
 
+//The limits of the shader are defined in the backend, which checks the programs against them
+interface ShaderLimits {
+    max_stack_size: number,
+    max_expressions: number,
+    max_locals: number,
+    max_masks: number,
+}
+let shaderLimits: ShaderLimits | undefined;
+
 export enum DrawMode {
     COMPILED, INTERPRETED
 };
@@ -189,6 +199,12 @@ async function getFragmentShaderSource() {
     fsSource = fsSource.replace("%INCLUDE_MATH%", MATH_GLSL);
     fsSource = fsSource.replace("%side%", SIDE.toString());
 
+    if(!shaderLimits)
+        shaderLimits = <ShaderLimits> await invoke("shader_limits");
+    fsSource = fsSource.replace("%MAX_EXPR%", shaderLimits.max_expressions.toString());
+    fsSource = fsSource.replace("%MAX_STACK_SIZE%", shaderLimits.max_stack_size.toString());
+    fsSource = fsSource.replace("%MAX_LOCALS%", shaderLimits.max_locals.toString());
+
     const interpreted_define = drawMode == DrawMode.INTERPRETED? "#define INTERPRETED":"#undef INTERPRETED";
     fsSource = fsSource.replace("%INCLUDE_INTERPRETED%", interpreted_define);
 
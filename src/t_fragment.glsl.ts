@@ -9,14 +9,16 @@ precision highp int;
 #define TEO_WIDTH 1.0
 #define AA 2
 #define SIDE %side%
-#define MAX_EXPR 32
-#define MAX_STACK_SIZE 128
+#define MAX_EXPR %MAX_EXPR%
+#define MAX_STACK_SIZE %MAX_STACK_SIZE%
+#define MAX_LOCALS %MAX_LOCALS%
 
 #define PUSH(x) { stack[stackTop] = x; stackTop++; }
 #define POP(out) { out = stack[stackTop-1]; stackTop--; }
 #define UNARY_OP(op) { float a; POP(a); PUSH( (op(a)) ); }
 #define BINARY_OP(op) { float a,b; POP(b); POP(a); PUSH( op(a,b) ); }
 #define DIV(den_out) { float a,b; POP(b); POP(a); PUSH(fdiv(a,b)); den_out <<= 1; den_out |= int(fneg(b)); }
+#define STEP(op, den_out) { float a; POP(a); a = op(a); PUSH(a); den_out <<= 1; den_out |= int(fodd(a)); }
 
 //MEMORY OPERATORS
 #define OP_RET 0
@@ -26,12 +28,16 @@ precision highp int;
 #define OP_CPY 4
 #define OP_POP 5
 #define OP_STORE 6
+#define OP_REQUIRE 7
+#define OP_LOAD 8
+#define OP_SAVE 9
 
 //BINARY OPERATORS
 #define OP_ADD (32 | 0)
 #define OP_MUL (32 | 1)
 #define OP_DIV (32 | 2)
 #define OP_POW (32 | 3)
+#define OP_SUB (32 | 4)
 
 //UNARY OPERATORS
 #define OP_MINUS (64 | 0)
@@ -44,6 +50,9 @@ precision highp int;
 #define OP_LN    (64 | 7)
 #define OP_SQRT  (64 | 8)
 #define OP_TAN   (64 | 9)
+#define OP_ERF   (64 | 10)
+#define OP_ERFC  (64 | 11)
+#define OP_FACT  (64 | 12)
 
 uniform ivec2 origin;
 uniform int squareMant, squareExp, squareSize;
@@ -71,16 +80,18 @@ Instruction getInstruction(int i) {
 
 uniform int jumpTable[MAX_EXPR];
 uniform int programLength; 
-ivec2 eval(ivec2 p, int opt) {
+ivec3 eval(ivec2 p, int opt) {
     float pixel = (float(squareMant) * pow(10.0, float(squareExp))) / float(squareSize); 
     float unit = pixel/float(AA);
     float x = float(p.x)*unit, y = float(p.y)*unit;
 
-    ivec2 ret;
+    //x is the sign, y the signs of the denominators and the parities of floor and ceil, and z if it's outside the domain
+    ivec3 ret = ivec3(0);
 
 #ifdef INTERPRETED
     float stack[MAX_STACK_SIZE];
     int stackTop = 0;
+    float locals[MAX_LOCALS];
     int programCounter;
 
     for(programCounter=jumpTable[opt]; programCounter<programLength; programCounter++) {
@@ -113,11 +124,29 @@ ivec2 eval(ivec2 p, int opt) {
         case OP_STORE:
             ret.x = int(fneg(stack[stackTop-1]));
             break;
+        
+        case OP_LOAD:
+            PUSH(locals[int(ins.arg)]);
+            break;
+        
+        case OP_SAVE:
+            locals[int(ins.arg)] = stack[stackTop-1];
+            break;
+        
+        case OP_REQUIRE:
+            float required;
+            POP(required);
+            ret.z |= int(!fdefined(required, int(ins.arg)));
+            break;
 
         case OP_ADD:
             BINARY_OP(fadd);
             break;
         
+        case OP_SUB:
+            BINARY_OP(fsub);
+            break;
+        
         case OP_MUL:
             BINARY_OP(fmul);
             break;
@@ -142,7 +171,7 @@ ivec2 eval(ivec2 p, int opt) {
             UNARY_OP(fcos);
             break;
         case OP_FLOOR:
-            UNARY_OP(ffloor);
+            STEP(ffloor, ret.y);
             break;
         
         case OP_ABS:
@@ -150,7 +179,7 @@ ivec2 eval(ivec2 p, int opt) {
             break;
         
         case OP_CEIL:
-            UNARY_OP(fceil);
+            STEP(fceil, ret.y);
             break;
         
         case OP_LOG:
@@ -174,6 +203,18 @@ ivec2 eval(ivec2 p, int opt) {
             DIV(ret.y);
             break;
         
+        case OP_ERF:
+            UNARY_OP(ferf);
+            break;
+        
+        case OP_ERFC:
+            UNARY_OP(ferfc);
+            break;
+        
+        case OP_FACT:
+            UNARY_OP(ffact);
+            break;
+        
         default:
             break;
         }
@@ -191,15 +232,16 @@ ivec2 eval(ivec2 p, int opt) {
 }
 
 bool line(ivec2 p, int opt) {
-    ivec2 a = eval(p + ivec2(-WIDTH, -WIDTH), opt);
-    ivec2 b = eval(p + ivec2(WIDTH+1, WIDTH+1), opt);
-    ivec2 c = eval(p + ivec2(-WIDTH, WIDTH+1), opt);
-    ivec2 d = eval(p + ivec2(WIDTH+1, -WIDTH), opt);
+    ivec3 a = eval(p + ivec2(-WIDTH, -WIDTH), opt);
+    ivec3 b = eval(p + ivec2(WIDTH+1, WIDTH+1), opt);
+    ivec3 c = eval(p + ivec2(-WIDTH, WIDTH+1), opt);
+    ivec3 d = eval(p + ivec2(WIDTH+1, -WIDTH), opt);
 
     int g = a.x + b.x + c.x + d.x;
     bool denominators = a.y == b.y && b.y == c.y && c.y == d.y;
+    bool defined = (a.z | b.z | c.z | d.z) == 0;
 
-    return 0 < g && g < 4 && denominators;
+    return 0 < g && g < 4 && denominators && defined;
 }
 
 vec4 lineColor(ivec2 p, int opt, vec3 rgb) {